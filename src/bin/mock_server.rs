@@ -0,0 +1,54 @@
+// File: mock_server.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use t212_portfolio_analytics::mock_server::{self, DEFAULT_FIXTURES_DIR};
+
+/// Default port of the mock server
+const DEFAULT_PORT: u16 = 8212;
+
+// Usage: mock_server [--port <port>] [--fixtures <dir>]
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut port = DEFAULT_PORT;
+    let mut fixtures_dir = PathBuf::from(DEFAULT_FIXTURES_DIR);
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                port = args
+                    .next()
+                    .and_then(|p| p.parse().ok())
+                    .ok_or_else(|| anyhow::anyhow!("--port expects a port number"))?;
+            }
+            "--fixtures" => {
+                fixtures_dir = args
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| anyhow::anyhow!("--fixtures expects a directory"))?;
+            }
+            other => return Err(anyhow::anyhow!("Unknown argument: {}", other)),
+        }
+    }
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    mock_server::serve(addr, &fixtures_dir).await
+}
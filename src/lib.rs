@@ -3,6 +3,7 @@
 // Licensed for personal and educational use only. Commercial use prohibited.
 // See the LICENSE file for details.
 
+pub mod mock_server;
 pub mod models;
 pub mod services;
 pub mod utils;
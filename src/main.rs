@@ -17,6 +17,7 @@
 // USE THIS SOFTWARE AT YOUR OWN RISK.
 
 use t212_portfolio_analytics::models::portfolio::Portfolio;
+use t212_portfolio_analytics::services::instruments::{InstrumentCache, INSTRUMENTS_CACHE_FILE};
 use t212_portfolio_analytics::services::orchestrator::Orchestrator;
 use t212_portfolio_analytics::utils::settings::Config;
 use t212_portfolio_analytics::webui;
@@ -52,13 +53,22 @@ async fn main() -> anyhow::Result<()> {
         println!("Initializing portfolio...");
         portfolio.init(&config).await?;
 
+        // Instrument metadata is cached on disk and only downloaded again once it
+        // expires or a new ticker shows up
+        let mut instruments = InstrumentCache::load(INSTRUMENTS_CACHE_FILE);
+        let instrument_metadata = instruments
+            .refresh(&config, &portfolio.tickers(), chrono::Utc::now())
+            .await?;
+
         // Process portfolio. This stage will fetch other information for processing each
         // positions, like the yahoo finance data.
         portfolio
             .process(
                 &config,
                 orchestrator.currency_converter,
-                orchestrator.instrument_metadata,
+                instrument_metadata,
+                orchestrator.market_hours,
+                false,
             )
             .await?;
     }
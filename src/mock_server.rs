@@ -0,0 +1,699 @@
+// File: mock_server.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+// Local stand-in for the Trading 212 API, serving the fixture files of
+// `mock_data/`. Point the app at it by selecting the "Custom" API
+// environment with the address of the server as base URL.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::{Path as UrlPath, Query, State},
+    http::{header, HeaderMap, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::services::trading212::{DataIncluded, ExportRequest};
+
+/// Default directory of the fixture files
+pub const DEFAULT_FIXTURES_DIR: &str = "mock_data";
+
+/// Page size used when a request does not set `limit`
+const DEFAULT_PAGE_LIMIT: usize = 20;
+/// Largest page size accepted by the history endpoints
+const MAX_PAGE_LIMIT: usize = 50;
+
+/// Statuses an export report goes through, one step per status poll
+const EXPORT_LIFECYCLE: [&str; 4] = ["Queued", "Processing", "Running", "Finished"];
+
+/// Fixture data served by the mock server. Everything except the export
+/// CSV is stored in the exact JSON shape returned by Trading 212.
+#[derive(Debug, Clone)]
+pub struct Fixtures {
+    pub portfolio: Value,
+    pub instruments: Value,
+    /// Daily session times of each working schedule, expanded into the
+    /// time events of the coming days when served
+    pub exchanges: Value,
+    pub account_cash: Value,
+    pub account_info: Value,
+    pub orders: Vec<Value>,
+    pub dividends: Vec<Value>,
+    pub transactions: Vec<Value>,
+    pub pies: Value,
+    pub pie_details: HashMap<String, Value>,
+    pub export_csv: String,
+}
+
+impl Fixtures {
+    pub fn load(dir: &Path) -> Result<Self, anyhow::Error> {
+        let read_json = |name: &str| -> Result<Value, anyhow::Error> {
+            let path = dir.join(name);
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+            serde_json::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+        };
+        let read_list = |name: &str| -> Result<Vec<Value>, anyhow::Error> {
+            match read_json(name)? {
+                Value::Array(items) => Ok(items),
+                _ => Err(anyhow::anyhow!("{} must contain a JSON array", name)),
+            }
+        };
+
+        let pie_details = match read_json("pie_details.json")? {
+            Value::Object(map) => map.into_iter().collect(),
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "pie_details.json must contain a JSON object"
+                ))
+            }
+        };
+        let export_path = dir.join("export.csv");
+        let export_csv = std::fs::read_to_string(&export_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", export_path.display(), e))?;
+
+        Ok(Self {
+            portfolio: read_json("portfolio.json")?,
+            instruments: read_json("instruments.json")?,
+            exchanges: read_json("exchanges.json")?,
+            account_cash: read_json("account_cash.json")?,
+            account_info: read_json("account_info.json")?,
+            orders: read_list("orders.json")?,
+            dividends: read_list("dividends.json")?,
+            transactions: read_list("transactions.json")?,
+            pies: read_json("pies.json")?,
+            pie_details,
+            export_csv,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct MockExport {
+    report_id: i64,
+    request: ExportRequest,
+    polls: usize,
+}
+
+#[derive(Clone)]
+pub struct MockState {
+    fixtures: Arc<Fixtures>,
+    exports: Arc<Mutex<Vec<MockExport>>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PageQuery {
+    limit: Option<usize>,
+    cursor: Option<usize>,
+    ticker: Option<String>,
+}
+
+/// Returns one page of `items` in the paginated envelope of the history
+/// endpoints. The cursor is simply the offset of the next item.
+fn paginate(items: &[Value], path: &str, query: &PageQuery) -> Value {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT);
+    let offset = query.cursor.unwrap_or(0).min(items.len());
+    let end = (offset + limit).min(items.len());
+
+    let next_page_path = if end < items.len() {
+        let ticker = query
+            .ticker
+            .as_ref()
+            .map(|t| format!("&ticker={}", t))
+            .unwrap_or_default();
+        Value::String(format!("{}?limit={}&cursor={}{}", path, limit, end, ticker))
+    } else {
+        Value::Null
+    };
+
+    json!({
+        "items": items[offset..end].to_vec(),
+        "nextPagePath": next_page_path,
+    })
+}
+
+/// Whether a row of the export CSV belongs to one of the included data sets
+fn is_included(action: &str, data: &DataIncluded) -> bool {
+    let action = action.to_lowercase();
+    if action.starts_with("dividend") {
+        data.include_dividends
+    } else if action.contains("interest") {
+        data.include_interest
+    } else if action.contains("buy") || action.contains("sell") {
+        data.include_orders
+    } else {
+        data.include_transactions
+    }
+}
+
+/// Builds the CSV of an export report from the fixture CSV. Only the rows
+/// of the requested period and data sets are kept, and like the real
+/// reports, columns that are empty in every remaining row are dropped.
+pub fn build_export_csv(
+    csv_content: &str,
+    request: &ExportRequest,
+) -> Result<String, anyhow::Error> {
+    let from = DateTime::parse_from_rfc3339(&request.time_from)?.naive_utc();
+    let to = DateTime::parse_from_rfc3339(&request.time_to)?.naive_utc();
+
+    let mut rdr = csv::Reader::from_reader(csv_content.as_bytes());
+    let headers = rdr.headers()?.clone();
+    let action_idx = headers
+        .iter()
+        .position(|h| h == "Action")
+        .ok_or_else(|| anyhow::anyhow!("Export fixture has no Action column"))?;
+    let time_idx = headers
+        .iter()
+        .position(|h| h == "Time")
+        .ok_or_else(|| anyhow::anyhow!("Export fixture has no Time column"))?;
+
+    let mut rows = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        let time = NaiveDateTime::parse_from_str(&record[time_idx], "%Y-%m-%d %H:%M:%S")?;
+        if time >= from && time <= to && is_included(&record[action_idx], &request.data_included) {
+            rows.push(record);
+        }
+    }
+
+    let columns: Vec<usize> = (0..headers.len())
+        .filter(|&i| {
+            i == action_idx || i == time_idx || rows.iter().any(|r| !r[i].trim().is_empty())
+        })
+        .collect();
+
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    wtr.write_record(columns.iter().map(|&i| &headers[i]))?;
+    for row in &rows {
+        wtr.write_record(columns.iter().map(|&i| &row[i]))?;
+    }
+    Ok(String::from_utf8(wtr.into_inner()?)?)
+}
+
+async fn require_api_key<B>(request: Request<B>, next: Next<B>) -> Response {
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| !v.trim().is_empty());
+    if !authorized {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "code": "AuthenticationFailed" })),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+async fn get_portfolio(State(state): State<MockState>) -> Json<Value> {
+    Json(state.fixtures.portfolio.clone())
+}
+
+async fn get_instruments(State(state): State<MockState>) -> Json<Value> {
+    Json(state.fixtures.instruments.clone())
+}
+
+/// Expands the `open`/`close` times (UTC) of each working schedule into
+/// OPEN and CLOSE events on the weekdays from yesterday to a week ahead
+pub fn expand_exchange_schedules(exchanges: &Value, today: NaiveDate) -> Value {
+    let session_events = |schedule: &Value| -> Vec<Value> {
+        let time = |key: &str| {
+            schedule[key]
+                .as_str()
+                .and_then(|t| NaiveTime::parse_from_str(t, "%H:%M").ok())
+        };
+        let (Some(open), Some(close)) = (time("open"), time("close")) else {
+            return Vec::new();
+        };
+
+        (-1..=7)
+            .map(|offset| today + Duration::days(offset))
+            .filter(|day| !matches!(day.weekday(), Weekday::Sat | Weekday::Sun))
+            .flat_map(|day| {
+                [("OPEN", open), ("CLOSE", close)].map(|(event_type, time)| {
+                    json!({
+                        "date": day.and_time(time).and_utc().to_rfc3339(),
+                        "type": event_type,
+                    })
+                })
+            })
+            .collect()
+    };
+
+    let expanded = exchanges
+        .as_array()
+        .map(|list| list.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .map(|exchange| {
+            let schedules: Vec<Value> = exchange["workingSchedules"]
+                .as_array()
+                .map(|list| list.as_slice())
+                .unwrap_or_default()
+                .iter()
+                .map(|schedule| {
+                    json!({
+                        "id": schedule["id"],
+                        "timeEvents": session_events(schedule),
+                    })
+                })
+                .collect();
+            json!({
+                "id": exchange["id"],
+                "name": exchange["name"],
+                "workingSchedules": schedules,
+            })
+        })
+        .collect();
+    Value::Array(expanded)
+}
+
+async fn get_exchanges(State(state): State<MockState>) -> Json<Value> {
+    Json(expand_exchange_schedules(
+        &state.fixtures.exchanges,
+        Utc::now().date_naive(),
+    ))
+}
+
+async fn get_account_cash(State(state): State<MockState>) -> Json<Value> {
+    Json(state.fixtures.account_cash.clone())
+}
+
+async fn get_account_info(State(state): State<MockState>) -> Json<Value> {
+    Json(state.fixtures.account_info.clone())
+}
+
+async fn get_orders(State(state): State<MockState>, Query(query): Query<PageQuery>) -> Json<Value> {
+    let orders: Vec<Value> = state
+        .fixtures
+        .orders
+        .iter()
+        .filter(|o| match &query.ticker {
+            Some(ticker) => o["ticker"].as_str() == Some(ticker.as_str()),
+            None => true,
+        })
+        .cloned()
+        .collect();
+    Json(paginate(&orders, "/api/v0/equity/history/orders", &query))
+}
+
+async fn get_dividends(
+    State(state): State<MockState>,
+    Query(query): Query<PageQuery>,
+) -> Json<Value> {
+    Json(paginate(
+        &state.fixtures.dividends,
+        "/api/v0/history/dividends",
+        &query,
+    ))
+}
+
+async fn get_transactions(
+    State(state): State<MockState>,
+    Query(query): Query<PageQuery>,
+) -> Json<Value> {
+    Json(paginate(
+        &state.fixtures.transactions,
+        "/api/v0/history/transactions",
+        &query,
+    ))
+}
+
+async fn get_pies(State(state): State<MockState>) -> Json<Value> {
+    Json(state.fixtures.pies.clone())
+}
+
+async fn get_pie(State(state): State<MockState>, UrlPath(id): UrlPath<String>) -> Response {
+    match state.fixtures.pie_details.get(&id) {
+        Some(pie) => Json(pie.clone()).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(json!({ "code": "NotFound" }))).into_response(),
+    }
+}
+
+async fn post_export(
+    State(state): State<MockState>,
+    Json(request): Json<ExportRequest>,
+) -> Response {
+    if DateTime::parse_from_rfc3339(&request.time_from).is_err()
+        || DateTime::parse_from_rfc3339(&request.time_to).is_err()
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "code": "InvalidTimeRange" })),
+        )
+            .into_response();
+    }
+
+    let mut exports = state.exports.lock().unwrap();
+    let report_id = 900_000 + exports.len() as i64 + 1;
+    println!(
+        "Mock export {} requested for {} to {}",
+        report_id, request.time_from, request.time_to
+    );
+    exports.push(MockExport {
+        report_id,
+        request,
+        polls: 0,
+    });
+    Json(json!({ "reportId": report_id })).into_response()
+}
+
+async fn list_exports(State(state): State<MockState>, headers: HeaderMap) -> Json<Value> {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("127.0.0.1")
+        .to_string();
+
+    let mut exports = state.exports.lock().unwrap();
+    let list = exports
+        .iter_mut()
+        .map(|export| {
+            let status = EXPORT_LIFECYCLE[export.polls.min(EXPORT_LIFECYCLE.len() - 1)];
+            export.polls += 1;
+            let download_link = if status == "Finished" {
+                Value::String(format!(
+                    "http://{}/downloads/{}.csv",
+                    host, export.report_id
+                ))
+            } else {
+                Value::Null
+            };
+            json!({
+                "reportId": export.report_id,
+                "status": status,
+                "downloadLink": download_link,
+                "timeFrom": export.request.time_from,
+                "timeTo": export.request.time_to,
+                "dataIncluded": export.request.data_included,
+            })
+        })
+        .collect();
+    Json(Value::Array(list))
+}
+
+async fn download_export(
+    State(state): State<MockState>,
+    UrlPath(file): UrlPath<String>,
+) -> Response {
+    let report_id = file.trim_end_matches(".csv").parse::<i64>().ok();
+    let csv_content = {
+        let exports = state.exports.lock().unwrap();
+        exports
+            .iter()
+            .find(|e| Some(e.report_id) == report_id && e.polls >= EXPORT_LIFECYCLE.len())
+            .map(|e| build_export_csv(&state.fixtures.export_csv, &e.request))
+    };
+
+    match csv_content {
+        Some(Ok(content)) => ([(header::CONTENT_TYPE, "text/csv")], content).into_response(),
+        Some(Err(e)) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Builds the router of the mock server
+pub fn router(fixtures: Fixtures) -> Router {
+    let state = MockState {
+        fixtures: Arc::new(fixtures),
+        exports: Arc::new(Mutex::new(Vec::new())),
+    };
+
+    let api = Router::new()
+        .route("/api/v0/equity/portfolio", get(get_portfolio))
+        .route("/api/v0/equity/metadata/instruments", get(get_instruments))
+        .route("/api/v0/equity/metadata/exchanges", get(get_exchanges))
+        .route("/api/v0/equity/account/cash", get(get_account_cash))
+        .route("/api/v0/equity/account/info", get(get_account_info))
+        .route("/api/v0/equity/history/orders", get(get_orders))
+        .route("/api/v0/history/dividends", get(get_dividends))
+        .route("/api/v0/history/transactions", get(get_transactions))
+        .route("/api/v0/equity/pies", get(get_pies))
+        .route("/api/v0/equity/pies/:id", get(get_pie))
+        .route(
+            "/api/v0/history/exports",
+            get(list_exports).post(post_export),
+        )
+        .route_layer(middleware::from_fn(require_api_key));
+
+    // Report downloads are pre-signed links, so they do not need the API key
+    Router::new()
+        .merge(api)
+        .route("/downloads/:file", get(download_export))
+        .with_state(state)
+}
+
+/// Loads the fixtures from `fixtures_dir` and serves them on `addr`
+pub async fn serve(addr: SocketAddr, fixtures_dir: &Path) -> Result<(), anyhow::Error> {
+    let fixtures = Fixtures::load(fixtures_dir)?;
+    println!(
+        "Mock Trading212 API listening on http://{} (fixtures: {})",
+        addr,
+        fixtures_dir.display()
+    );
+
+    axum::Server::bind(&addr)
+        .serve(router(fixtures).into_make_service())
+        .await?;
+    Ok(())
+}
+
+/// Serves `app` in the background on a free local port for a test and
+/// returns its address
+#[cfg(test)]
+pub fn spawn_test_server(app: Router) -> SocketAddr {
+    let server =
+        axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(app.into_make_service());
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    addr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::market::{MarketHours, MarketStatus};
+    use crate::models::order::OrderHistoryCache;
+    use crate::models::pie::PieCache;
+    use crate::models::transaction::CashLedgerCache;
+    use crate::services::export::{ExportJobManager, ExportJobStatus, EXPORT_JOBS_FILE};
+    use crate::services::trading212::{Exchange, RequestType, Trading212Client};
+    use crate::utils::settings::{ApiEnvironment, Config, Mode};
+    use std::collections::HashSet;
+
+    fn spawn_mock_server() -> Config {
+        spawn_mock_server_with(Fixtures::load(Path::new(DEFAULT_FIXTURES_DIR)).unwrap())
+    }
+
+    fn spawn_mock_server_with(fixtures: Fixtures) -> Config {
+        let addr = spawn_test_server(router(fixtures));
+        Config {
+            mode: Mode::Live,
+            api_key: Some("mock-key".to_string()),
+            api_environment: ApiEnvironment::Custom(format!("http://{}", addr)),
+            ..Config::default()
+        }
+    }
+
+    fn export_request(orders: bool) -> ExportRequest {
+        ExportRequest {
+            data_included: DataIncluded {
+                include_dividends: true,
+                include_interest: false,
+                include_orders: orders,
+                include_transactions: false,
+            },
+            time_from: "2024-01-01T00:00:00Z".to_string(),
+            time_to: "2024-12-31T23:59:59Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_export_csv_drops_unused_columns() {
+        let csv = build_export_csv(
+            &std::fs::read_to_string("mock_data/export.csv").unwrap(),
+            &export_request(false),
+        )
+        .unwrap();
+        let mut rdr = csv::Reader::from_reader(csv.as_bytes());
+        let headers = rdr.headers().unwrap().clone();
+        assert_eq!(headers.len(), 13);
+        assert_eq!(&headers[9], "Total");
+        assert_eq!(&headers[11], "Withholding tax");
+        for record in rdr.records() {
+            let record = record.unwrap();
+            assert!(record[0].starts_with("Dividend"));
+            assert!(record[1].starts_with("2024"));
+        }
+    }
+
+    #[test]
+    fn test_expand_exchange_schedules() {
+        let exchanges: Vec<Exchange> = serde_json::from_value(expand_exchange_schedules(
+            &json!([{ "id": 2, "name": "NASDAQ",
+                      "workingSchedules": [{ "id": 71, "open": "14:30", "close": "21:00" }] }]),
+            NaiveDate::from_ymd_opt(2025, 3, 5).unwrap(),
+        ))
+        .unwrap();
+        let hours = MarketHours::new(exchanges);
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+        assert_eq!(
+            hours.status(Some(71), at("2025-03-05T15:00:00Z")),
+            MarketStatus::Open
+        );
+        assert_eq!(
+            hours.status(Some(71), at("2025-03-05T22:00:00Z")),
+            MarketStatus::Closed
+        );
+        // Saturday
+        assert_eq!(
+            hours.status(Some(71), at("2025-03-08T15:00:00Z")),
+            MarketStatus::Closed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_order_history_cache_fetches_new_pages_only() {
+        let config = spawn_mock_server();
+        let cache = OrderHistoryCache::default();
+        assert_eq!(cache.orders(&config).await.unwrap().len(), 64);
+        assert_eq!(cache.orders(&config).await.unwrap().len(), 64);
+
+        // Once an order is known, the older pages are not requested
+        let known: HashSet<i64> = cache
+            .orders(&config)
+            .await
+            .unwrap()
+            .iter()
+            .map(|o| o.id)
+            .collect();
+        let first_page = Trading212Client::new(RequestType::OrderHistory, &config)
+            .unwrap()
+            .get_order_history_since(&known)
+            .await
+            .unwrap();
+        assert!(first_page.len() < 64);
+    }
+
+    #[tokio::test]
+    async fn test_cash_ledger_cache_keeps_transactions() {
+        let config = spawn_mock_server();
+        let cache = CashLedgerCache::default();
+        let all = Trading212Client::new(RequestType::Transactions, &config)
+            .unwrap()
+            .get_transactions()
+            .await
+            .unwrap();
+        let first = cache.ledger(&config).await.unwrap();
+        let second = cache.ledger(&config).await.unwrap();
+        assert_eq!(first.transactions.len(), all.len());
+        assert_eq!(second.transactions, first.transactions);
+    }
+
+    #[tokio::test]
+    async fn test_pie_cache_serves_recent_details() {
+        let cache = PieCache::default();
+        let pies = cache.pies(&spawn_mock_server(), &[]).await.unwrap();
+        assert!(!pies.is_empty());
+
+        // Same account, but the details can no longer be fetched
+        let mut fixtures = Fixtures::load(Path::new(DEFAULT_FIXTURES_DIR)).unwrap();
+        fixtures.pie_details.clear();
+        let config = spawn_mock_server_with(fixtures);
+        let cached = cache.pies(&config, &[]).await.unwrap();
+        assert_eq!(cached.len(), pies.len());
+        assert_eq!(cached[0].instruments.len(), pies[0].instruments.len());
+        assert!(PieCache::default().pies(&config, &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_against_mock_server() {
+        let config = spawn_mock_server();
+
+        let orders = Trading212Client::new(RequestType::OrderHistory, &config)
+            .unwrap()
+            .get_order_history(None)
+            .await
+            .unwrap();
+        assert_eq!(orders.len(), 64);
+
+        let positions = Trading212Client::new(RequestType::Portfolio, &config)
+            .unwrap()
+            .get_open_positions()
+            .await
+            .unwrap();
+        assert!(!positions.is_empty());
+
+        let client = Trading212Client::new(RequestType::Export, &config).unwrap();
+        let report = client.request_export(&export_request(true)).await.unwrap();
+        let mut download_link = None;
+        for _ in 0..EXPORT_LIFECYCLE.len() {
+            let info = client
+                .get_export_status(report.report_id)
+                .await
+                .unwrap()
+                .unwrap();
+            download_link = info.download_link;
+        }
+        let csv = client
+            .download_export(&download_link.expect("export never finished"))
+            .await
+            .unwrap();
+        assert!(csv.contains("Market buy"));
+        assert!(!csv.contains("Deposit"));
+    }
+
+    #[tokio::test]
+    async fn test_export_jobs_stay_in_their_dir() {
+        let config = spawn_mock_server();
+        let dir = std::env::temp_dir().join(format!("export_jobs_mock_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manager = ExportJobManager::load(dir.join(EXPORT_JOBS_FILE));
+        let account = config.live_accounts().remove(0);
+
+        manager.enqueue_refresh(&config, &account).await.unwrap();
+        for _ in 0..=EXPORT_LIFECYCLE.len() {
+            manager.process(&config).await;
+        }
+        let jobs = manager.jobs().await;
+        let files = std::fs::read_dir(manager.account_dir(&account.name))
+            .map(|entries| entries.count())
+            .unwrap_or(0);
+        let parts_dir = dir.join("export_parts").is_dir();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(jobs.iter().all(|j| j.status == ExportJobStatus::Finished));
+        assert!(files > 0);
+        assert!(parts_dir);
+    }
+}
@@ -0,0 +1,243 @@
+// File: allocation.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::collections::HashMap;
+
+use super::portfolio::Position;
+use crate::utils::settings::ConcentrationLimits;
+
+/// Group of the positions without the information they are grouped by
+pub const UNCLASSIFIED: &str = "Unclassified";
+
+/// What the holdings are grouped by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocationDimension {
+    Sector,
+    Industry,
+    Country,
+    Currency,
+    InstrumentType,
+}
+
+impl AllocationDimension {
+    pub const ALL: [AllocationDimension; 5] = [
+        AllocationDimension::Sector,
+        AllocationDimension::Industry,
+        AllocationDimension::Country,
+        AllocationDimension::Currency,
+        AllocationDimension::InstrumentType,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AllocationDimension::Sector => "Sector",
+            AllocationDimension::Industry => "Industry",
+            AllocationDimension::Country => "Country",
+            AllocationDimension::Currency => "Currency",
+            AllocationDimension::InstrumentType => "Type",
+        }
+    }
+
+    /// Group `position` belongs to
+    pub fn group(self, position: &Position) -> String {
+        let group = match self {
+            AllocationDimension::Sector => position.profile.sector.clone(),
+            AllocationDimension::Industry => position.profile.industry.clone(),
+            AllocationDimension::Country => position.profile.country.clone(),
+            // Prices in pence are still pounds
+            AllocationDimension::Currency if position.currency == "GBX" => Some("GBP".to_string()),
+            AllocationDimension::Currency => Some(position.currency.clone()),
+            AllocationDimension::InstrumentType => Some(position.instrument_type.clone()),
+        };
+        group
+            .filter(|group| !group.trim().is_empty())
+            .unwrap_or_else(|| UNCLASSIFIED.to_string())
+    }
+
+    /// Largest weight of a group, `None` when it is not checked
+    pub fn limit(self, limits: &ConcentrationLimits) -> Option<f64> {
+        let limit = match self {
+            AllocationDimension::Sector => limits.sector,
+            AllocationDimension::Industry => limits.industry,
+            AllocationDimension::Country => limits.country,
+            AllocationDimension::Currency | AllocationDimension::InstrumentType => 0.0,
+        };
+        (limit > 0.0).then_some(limit)
+    }
+}
+
+/// Holdings of one group. Amounts are in GBP, shares are in percent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllocationSlice {
+    pub name: String,
+    pub positions: usize,
+    pub value: f64,
+    /// Share of the value of all holdings
+    pub weight: f64,
+    /// Expected dividend income of the next 12 months, after withholding tax
+    pub income: f64,
+    /// Share of the income of all holdings
+    pub income_share: f64,
+    /// The weight is above the limit of the group
+    pub over_limit: bool,
+}
+
+/// Holdings grouped by one dimension, largest group first
+#[derive(Debug, Clone)]
+pub struct Allocation {
+    pub dimension: AllocationDimension,
+    pub limit: Option<f64>,
+    pub slices: Vec<AllocationSlice>,
+}
+
+impl Allocation {
+    pub fn new(
+        positions: &[Position],
+        dimension: AllocationDimension,
+        limits: &ConcentrationLimits,
+    ) -> Self {
+        let limit = dimension.limit(limits);
+        Self {
+            dimension,
+            limit,
+            slices: slices(positions, |p| dimension.group(p), limit),
+        }
+    }
+}
+
+/// Weight of every ticker, adding up the accounts it is held in. Only the
+/// tickers above the position limit are flagged.
+pub fn position_weights(
+    positions: &[Position],
+    limits: &ConcentrationLimits,
+) -> Vec<AllocationSlice> {
+    let limit = (limits.position > 0.0).then_some(limits.position);
+    slices(positions, |p| p.ticker.clone(), limit)
+}
+
+fn slices(
+    positions: &[Position],
+    group: impl Fn(&Position) -> String,
+    limit: Option<f64>,
+) -> Vec<AllocationSlice> {
+    let mut groups: HashMap<String, AllocationSlice> = HashMap::new();
+    for p in positions {
+        let name = group(p);
+        let slice = groups
+            .entry(name.clone())
+            .or_insert_with(|| AllocationSlice {
+                name,
+                positions: 0,
+                value: 0.0,
+                weight: 0.0,
+                income: 0.0,
+                income_share: 0.0,
+                over_limit: false,
+            });
+        slice.positions += 1;
+        slice.value += p.value;
+        slice.income += p
+            .div_info
+            .as_ref()
+            .map(|d| d.annual_income_after_wht)
+            .unwrap_or(0.0);
+    }
+
+    let total_value: f64 = groups.values().map(|s| s.value).sum();
+    let total_income: f64 = groups.values().map(|s| s.income).sum();
+    let mut slices: Vec<AllocationSlice> = groups
+        .into_values()
+        .map(|mut slice| {
+            if total_value > 0.0 {
+                slice.weight = slice.value / total_value * 100.0;
+            }
+            if total_income > 0.0 {
+                slice.income_share = slice.income / total_income * 100.0;
+            }
+            // Positions missing the data are not a concentration
+            slice.over_limit =
+                slice.name != UNCLASSIFIED && limit.is_some_and(|limit| slice.weight > limit);
+            slice
+        })
+        .collect();
+    slices.sort_by(|a, b| {
+        b.value
+            .total_cmp(&a.value)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    slices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::dividend::DividendInfo;
+    use crate::services::market_data::Profile;
+
+    fn position(ticker: &str, sector: Option<&str>, value: f64, income: f64) -> Position {
+        Position {
+            ticker: ticker.to_string(),
+            currency: "GBX".to_string(),
+            value,
+            profile: Profile {
+                sector: sector.map(str::to_string),
+                ..Profile::default()
+            },
+            div_info: Some(DividendInfo {
+                annual_income_after_wht: income,
+                ..DividendInfo::default()
+            }),
+            ..Position::default()
+        }
+    }
+
+    #[test]
+    fn test_allocation_by_sector() {
+        let positions = vec![
+            position("AAPL_US_EQ", Some("Technology"), 500.0, 5.0),
+            position("MSFT_US_EQ", Some("Technology"), 100.0, 15.0),
+            position("ULVRl_EQ", Some("Consumer Defensive"), 300.0, 30.0),
+            position("VUSAl_EQ", None, 100.0, 0.0),
+        ];
+        let allocation = Allocation::new(
+            &positions,
+            AllocationDimension::Sector,
+            &ConcentrationLimits::default(),
+        );
+        let technology = &allocation.slices[0];
+        assert_eq!(technology.name, "Technology");
+        assert_eq!(technology.positions, 2);
+        assert_eq!(technology.weight, 60.0);
+        assert_eq!(technology.income_share, 40.0);
+        assert!(technology.over_limit);
+        assert!(!allocation.slices[1].over_limit);
+        assert_eq!(allocation.slices[2].name, UNCLASSIFIED);
+
+        let currencies = Allocation::new(
+            &positions,
+            AllocationDimension::Currency,
+            &ConcentrationLimits::default(),
+        );
+        assert_eq!(currencies.slices.len(), 1);
+        assert_eq!(currencies.slices[0].name, "GBP");
+        assert!(!currencies.slices[0].over_limit);
+
+        let weights = position_weights(&positions, &ConcentrationLimits::default());
+        assert_eq!(weights.iter().filter(|s| s.over_limit).count(), 2);
+    }
+}
@@ -0,0 +1,329 @@
+// File: benchmark.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use chrono::{Datelike, Months, NaiveDate};
+
+use super::transaction::DailyContribution;
+use crate::services::price_history::{PriceBar, PriceHistory, PriceSeries};
+
+/// Period the returns of the portfolio and the benchmarks are compared over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReturnPeriod {
+    OneMonth,
+    ThreeMonths,
+    YearToDate,
+    OneYear,
+    SinceInception,
+}
+
+impl ReturnPeriod {
+    pub const ALL: [ReturnPeriod; 5] = [
+        ReturnPeriod::OneMonth,
+        ReturnPeriod::ThreeMonths,
+        ReturnPeriod::YearToDate,
+        ReturnPeriod::OneYear,
+        ReturnPeriod::SinceInception,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ReturnPeriod::OneMonth => "1M",
+            ReturnPeriod::ThreeMonths => "3M",
+            ReturnPeriod::YearToDate => "YTD",
+            ReturnPeriod::OneYear => "1Y",
+            ReturnPeriod::SinceInception => "Since inception",
+        }
+    }
+
+    /// Day whose closing value the period ending `today` starts from,
+    /// `None` since inception
+    pub fn start(self, today: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            ReturnPeriod::OneMonth => today.checked_sub_months(Months::new(1)),
+            ReturnPeriod::ThreeMonths => today.checked_sub_months(Months::new(3)),
+            ReturnPeriod::YearToDate => NaiveDate::from_ymd_opt(today.year() - 1, 12, 31),
+            ReturnPeriod::OneYear => today.checked_sub_months(Months::new(12)),
+            ReturnPeriod::SinceInception => None,
+        }
+    }
+}
+
+/// Value of an investment at the end of a day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValuePoint {
+    pub date: NaiveDate,
+    pub value: f64,
+}
+
+/// Value of the last point on or before `date`
+pub fn value_on(points: &[ValuePoint], date: NaiveDate) -> Option<f64> {
+    let index = points.partition_point(|point| point.date <= date);
+    index.checked_sub(1).map(|i| points[i].value)
+}
+
+/// Symbol of the daily rate of `currency` per pound, `None` for prices in
+/// pounds or pence
+pub fn fx_symbol(currency: &str) -> Option<String> {
+    match currency {
+        "GBP" | "GBp" | "GBX" => None,
+        other => Some(format!("GBP{}=X", other.to_uppercase())),
+    }
+}
+
+/// Amount of the currency a benchmark is quoted in that one pound buys
+#[derive(Debug, Clone, Copy)]
+pub enum PoundRate<'a> {
+    Fixed(f64),
+    /// Closing rates of the `fx_symbol` of the currency, oldest first
+    Daily(&'a [PriceBar]),
+}
+
+impl<'a> PoundRate<'a> {
+    /// Rate for prices in `currency`, `None` when its rates are not stored
+    pub fn for_currency(currency: &str, history: &'a PriceHistory) -> Option<Self> {
+        match currency {
+            "GBP" => Some(PoundRate::Fixed(1.0)),
+            "GBp" | "GBX" => Some(PoundRate::Fixed(100.0)),
+            other => {
+                let bars = history.bars(&fx_symbol(other)?);
+                (!bars.is_empty()).then_some(PoundRate::Daily(bars))
+            }
+        }
+    }
+
+    /// Rate at the last close on or before `date`
+    pub fn on(&self, date: NaiveDate) -> Option<f64> {
+        match self {
+            PoundRate::Fixed(rate) => Some(*rate),
+            PoundRate::Daily(bars) => {
+                let index = bars.partition_point(|bar| bar.date <= date);
+                index.checked_sub(1).map(|i| bars[i].close)
+            }
+        }
+    }
+}
+
+/// Value in pounds over time of buying the benchmark in `series` with every
+/// contribution and selling it for every withdrawal. Money is invested at
+/// the close of its day, or of the next trading day when the market was
+/// closed, and converted at the `rate` of that day. Dividends are reinvested
+/// at the close of their ex-date, so the benchmark earns its yield like the
+/// portfolio does. There is one point per bar with a known rate from the
+/// first contribution on.
+pub fn simulate_contributions(
+    series: &PriceSeries,
+    contributions: &[DailyContribution],
+    rate: PoundRate,
+) -> Vec<ValuePoint> {
+    let mut units = 0.0;
+    let mut pending = contributions.iter().peekable();
+    let mut points = Vec::new();
+    for bar in &series.bars {
+        let Some(rate) = rate.on(bar.date) else {
+            continue;
+        };
+        if let Some(dividend) = bar.dividend {
+            units += units * dividend / bar.close;
+        }
+        let mut invested = false;
+        while let Some(contribution) = pending.next_if(|c| c.date <= bar.date) {
+            units += contribution.amount * rate / bar.close;
+            invested = true;
+        }
+        if invested || !points.is_empty() {
+            points.push(ValuePoint {
+                date: bar.date,
+                value: units * bar.close / rate,
+            });
+        }
+    }
+    points
+}
+
+/// Modified Dietz return in percent from the close of `start` to `end`.
+/// Each contribution in between counts for the part of the period it was
+/// invested, so money put in late does not show up as growth.
+pub fn modified_dietz(
+    start_value: f64,
+    end_value: f64,
+    contributions: &[DailyContribution],
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Option<f64> {
+    let days = (end - start).num_days();
+    if days <= 0 {
+        return None;
+    }
+    let (mut net, mut weighted) = (0.0, 0.0);
+    for c in contributions
+        .iter()
+        .filter(|c| c.date > start && c.date <= end)
+    {
+        net += c.amount;
+        weighted += c.amount * (end - c.date).num_days() as f64 / days as f64;
+    }
+    let invested = start_value + weighted;
+    if invested <= 0.0 {
+        return None;
+    }
+    Some((end_value - start_value - net) / invested * 100.0)
+}
+
+/// Return over each of `ReturnPeriod::ALL` of an investment worth
+/// `end_value` on `today`. `value_at` gives its value at the close of a
+/// day; periods reaching back before the first contribution start from
+/// nothing. `None` where the value at the start is not known.
+pub fn period_returns(
+    value_at: impl Fn(NaiveDate) -> Option<f64>,
+    end_value: f64,
+    contributions: &[DailyContribution],
+    today: NaiveDate,
+) -> Vec<Option<f64>> {
+    let Some(inception) = contributions.first().and_then(|c| c.date.pred_opt()) else {
+        return vec![None; ReturnPeriod::ALL.len()];
+    };
+    ReturnPeriod::ALL
+        .iter()
+        .map(|period| {
+            let (start, start_value) = match period.start(today) {
+                Some(start) if start > inception => (start, value_at(start)?),
+                _ => (inception, 0.0),
+            };
+            modified_dietz(start_value, end_value, contributions, start, today)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn series(closes: &[(&str, f64)]) -> PriceSeries {
+        PriceSeries {
+            bars: closes
+                .iter()
+                .map(|(day, close)| PriceBar {
+                    date: date(day),
+                    open: *close,
+                    high: *close,
+                    low: *close,
+                    close: *close,
+                    dividend: None,
+                })
+                .collect(),
+            ..PriceSeries::default()
+        }
+    }
+
+    #[test]
+    fn test_simulate_contributions() {
+        let series = series(&[
+            ("2025-01-02", 10.0),
+            ("2025-01-03", 12.0),
+            ("2025-01-06", 15.0),
+            ("2025-01-07", 20.0),
+        ]);
+        let contributions = vec![
+            DailyContribution {
+                date: date("2025-01-03"),
+                amount: 120.0,
+            },
+            // Saturday, bought at the close of Monday
+            DailyContribution {
+                date: date("2025-01-04"),
+                amount: 150.0,
+            },
+        ];
+        let points = simulate_contributions(&series, &contributions, PoundRate::Fixed(1.0));
+        let values: Vec<f64> = points.iter().map(|p| p.value).collect();
+        assert_eq!(values, vec![120.0, 300.0, 400.0]);
+        assert_eq!(value_on(&points, date("2025-01-05")), Some(120.0));
+        assert_eq!(value_on(&points, date("2025-01-02")), None);
+    }
+
+    #[test]
+    fn test_simulate_contributions_reinvests_dividends() {
+        let mut prices = series(&[("2025-01-02", 10.0), ("2025-01-03", 9.5)]);
+        // The price drops by the dividend on its ex-date
+        prices.bars[1].dividend = Some(0.5);
+        let contributions = vec![DailyContribution {
+            date: date("2025-01-02"),
+            amount: 100.0,
+        }];
+        let points = simulate_contributions(&prices, &contributions, PoundRate::Fixed(1.0));
+        assert!((points[1].value - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_simulate_contributions_converts_at_daily_rate() {
+        let prices = series(&[("2025-01-02", 10.0), ("2025-01-03", 10.0)]);
+        // The pound buys 1.25 dollars, then 1.0
+        let rates = series(&[("2025-01-02", 1.25), ("2025-01-03", 1.0)]);
+        let contributions = vec![DailyContribution {
+            date: date("2025-01-02"),
+            amount: 100.0,
+        }];
+        let points = simulate_contributions(&prices, &contributions, PoundRate::Daily(&rates.bars));
+        let values: Vec<f64> = points.iter().map(|p| p.value).collect();
+        // Same dollar price, but the dollars bought are worth more pounds
+        assert_eq!(values, vec![100.0, 125.0]);
+
+        assert_eq!(fx_symbol("USD").as_deref(), Some("GBPUSD=X"));
+        assert_eq!(fx_symbol("GBp"), None);
+        let history = PriceHistory::default();
+        assert!(PoundRate::for_currency("EUR", &history).is_none());
+        assert_eq!(
+            PoundRate::for_currency("GBp", &history).and_then(|r| r.on(date("2025-01-02"))),
+            Some(100.0)
+        );
+    }
+
+    #[test]
+    fn test_period_returns() {
+        let contributions = vec![
+            DailyContribution {
+                date: date("2024-01-01"),
+                amount: 1000.0,
+            },
+            DailyContribution {
+                date: date("2025-06-01"),
+                amount: 500.0,
+            },
+        ];
+        let today = date("2025-06-30");
+        let returns = period_returns(
+            |day| (day < date("2025-06-01")).then_some(1100.0),
+            1650.0,
+            &contributions,
+            today,
+        );
+        // The 500 put in a month ago is not growth
+        let one_month = returns[0].unwrap();
+        assert!((one_month - 50.0 / (1100.0 + 500.0 * 29.0 / 31.0) * 100.0).abs() < 1e-9);
+        let since_inception = returns[4].unwrap();
+        assert!(since_inception > 0.0 && since_inception < 15.0);
+
+        let unknown = period_returns(|_| None, 1650.0, &contributions, today);
+        assert_eq!(unknown[0], None);
+        assert!(unknown[4].is_some());
+    }
+}
@@ -0,0 +1,224 @@
+// File: corporate_action.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::portfolio::MonthlyPayment;
+
+/// What happened to the shares of a company
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CorporateActionKind {
+    /// Every share became `ratio` shares, `ratio` is above 1
+    Split { ratio: f64 },
+    /// Shares were consolidated, every share became `ratio` shares with
+    /// `ratio` below 1
+    ReverseSplit { ratio: f64 },
+    /// Part of the company was split off into `symbol`. `retained` is the
+    /// fraction of the value per share left with the parent, when known.
+    SpinOff {
+        symbol: Option<String>,
+        retained: Option<f64>,
+    },
+    /// One-off dividend per share on top of the regular ones
+    SpecialDividend { amount: f64 },
+}
+
+impl CorporateActionKind {
+    /// Split giving `to` shares for every `from` shares held, a reverse
+    /// split when `to` is the smaller one. `None` for an invalid ratio.
+    pub fn split(from: f64, to: f64) -> Option<Self> {
+        if !(from > 0.0 && to > 0.0) || from == to {
+            return None;
+        }
+        let ratio = to / from;
+        if ratio > 1.0 {
+            Some(CorporateActionKind::Split { ratio })
+        } else {
+            Some(CorporateActionKind::ReverseSplit { ratio })
+        }
+    }
+
+    /// Reads a ratio such as `4:1`, `1/10` or `4.0`, where the first number
+    /// is the number of shares after the split
+    pub fn parse_split(ratio: &str) -> Option<Self> {
+        let ratio = ratio.trim();
+        match ratio.split_once([':', '/']) {
+            Some((to, from)) => Self::split(from.trim().parse().ok()?, to.trim().parse().ok()?),
+            None => Self::split(1.0, ratio.parse().ok()?),
+        }
+    }
+}
+
+/// Corporate action taking effect on `date`, the ex-date
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorporateAction {
+    pub date: NaiveDate,
+    #[serde(flatten)]
+    pub kind: CorporateActionKind,
+}
+
+impl CorporateAction {
+    /// Factor a per-share amount from before the action is multiplied by to
+    /// compare with amounts after it
+    pub fn per_share_factor(&self) -> f64 {
+        match &self.kind {
+            CorporateActionKind::Split { ratio } | CorporateActionKind::ReverseSplit { ratio } => {
+                1.0 / ratio
+            }
+            CorporateActionKind::SpinOff { retained, .. } => retained.unwrap_or(1.0),
+            CorporateActionKind::SpecialDividend { .. } => 1.0,
+        }
+    }
+
+    pub fn is_split(&self) -> bool {
+        matches!(
+            self.kind,
+            CorporateActionKind::Split { .. } | CorporateActionKind::ReverseSplit { .. }
+        )
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self.kind {
+            CorporateActionKind::Split { .. } => "Split",
+            CorporateActionKind::ReverseSplit { .. } => "Reverse split",
+            CorporateActionKind::SpinOff { .. } => "Spin-off",
+            CorporateActionKind::SpecialDividend { .. } => "Special dividend",
+        }
+    }
+
+    pub fn details(&self) -> String {
+        match &self.kind {
+            CorporateActionKind::Split { ratio } => format!("{} for 1", ratio),
+            CorporateActionKind::ReverseSplit { ratio } => format!("1 for {}", 1.0 / ratio),
+            CorporateActionKind::SpinOff { symbol, retained } => {
+                let mut details = symbol.clone().unwrap_or_else(|| "-".to_string());
+                if let Some(retained) = retained {
+                    details.push_str(&format!(", {:.1}% of the value retained", retained * 100.0));
+                }
+                details
+            }
+            CorporateActionKind::SpecialDividend { amount } => format!("{} per share", amount),
+        }
+    }
+}
+
+/// Factor that converts a per-share amount of `from` into one of `to`,
+/// covering the actions that take effect in between. Works both ways, so
+/// an amount announced for after an upcoming split can be brought back to
+/// the current shares.
+pub fn per_share_factor(actions: &[CorporateAction], from: NaiveDate, to: NaiveDate) -> f64 {
+    let between = |start: NaiveDate, end: NaiveDate| {
+        actions
+            .iter()
+            .filter(|a| a.date > start && a.date <= end)
+            .map(CorporateAction::per_share_factor)
+            .product::<f64>()
+    };
+    if from <= to {
+        between(from, to)
+    } else {
+        1.0 / between(to, from)
+    }
+}
+
+/// Brings per-share dividends to the shares of `today` and drops the
+/// special dividends, which would otherwise show up as an increase
+/// followed by a cut
+pub fn adjust_dividends(
+    payments: &[MonthlyPayment],
+    actions: &[CorporateAction],
+    today: NaiveDate,
+) -> Vec<MonthlyPayment> {
+    payments
+        .iter()
+        .filter(|payment| {
+            !actions.iter().any(|action| {
+                matches!(
+                    action.kind,
+                    CorporateActionKind::SpecialDividend { amount }
+                        if action.date == payment.date && (amount - payment.amount).abs() < 1e-9
+                )
+            })
+        })
+        .map(|payment| MonthlyPayment {
+            date: payment.date,
+            amount: payment.amount * per_share_factor(actions, payment.date, today),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_split() {
+        let split = CorporateActionKind::parse_split("4:1").unwrap();
+        assert_eq!(split, CorporateActionKind::Split { ratio: 4.0 });
+        assert_eq!(
+            CorporateActionKind::parse_split("1/10"),
+            Some(CorporateActionKind::ReverseSplit { ratio: 0.1 })
+        );
+        assert_eq!(CorporateActionKind::parse_split("4.0"), Some(split));
+        assert!(CorporateActionKind::parse_split("1:1").is_none());
+        assert!(CorporateActionKind::parse_split("x").is_none());
+    }
+
+    #[test]
+    fn test_adjust_dividends() {
+        let actions = vec![
+            CorporateAction {
+                date: date("2024-06-10"),
+                kind: CorporateActionKind::Split { ratio: 4.0 },
+            },
+            CorporateAction {
+                date: date("2024-09-01"),
+                kind: CorporateActionKind::SpecialDividend { amount: 1.5 },
+            },
+        ];
+        let payments = vec![
+            MonthlyPayment {
+                date: date("2024-03-01"),
+                amount: 1.0,
+            },
+            MonthlyPayment {
+                date: date("2024-09-01"),
+                amount: 1.5,
+            },
+            MonthlyPayment {
+                date: date("2024-09-01"),
+                amount: 0.25,
+            },
+        ];
+        let adjusted = adjust_dividends(&payments, &actions, date("2025-01-01"));
+        let amounts: Vec<f64> = adjusted.iter().map(|p| p.amount).collect();
+        assert_eq!(amounts, vec![0.25, 0.25]);
+
+        // An amount announced for after an upcoming split, in current shares
+        assert_eq!(
+            per_share_factor(&actions, date("2024-07-01"), date("2024-06-01")),
+            4.0
+        );
+    }
+}
@@ -0,0 +1,117 @@
+// File: data_quality.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::portfolio::Position;
+
+/// Problem with the data behind a position, found while processing the
+/// portfolio
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DataIssue {
+    /// The ticker has no entry in `symbol_mapper.json`, so the Yahoo symbol
+    /// and the withholding tax were guessed
+    UnmappedSymbol { guessed: String },
+    /// The market data source returned an error, or nothing, for the symbol
+    MarketDataError(String),
+    /// The position is quoted in a currency that cannot be converted
+    UnsupportedCurrency(String),
+    /// Fetching the data again failed, so data of an earlier fetch is shown
+    StaleData { fetched_at: DateTime<Utc> },
+}
+
+impl DataIssue {
+    /// Short name of the kind of problem
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DataIssue::UnmappedSymbol { .. } => "Unmapped symbol",
+            DataIssue::MarketDataError(_) => "Market data error",
+            DataIssue::UnsupportedCurrency(_) => "Unsupported currency",
+            DataIssue::StaleData { .. } => "Stale data",
+        }
+    }
+
+    /// True if the position shows wrong or no figures, rather than figures
+    /// that may be slightly off
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            DataIssue::MarketDataError(_) | DataIssue::UnsupportedCurrency(_)
+        )
+    }
+
+    pub fn details(&self) -> String {
+        match self {
+            DataIssue::UnmappedSymbol { guessed } => {
+                format!("Yahoo symbol guessed as {}", guessed)
+            }
+            DataIssue::MarketDataError(error) => error.clone(),
+            DataIssue::UnsupportedCurrency(currency) => {
+                format!("{} cannot be converted to GBP", currency)
+            }
+            DataIssue::StaleData { fetched_at } => {
+                format!("Last fetched {}", fetched_at.format("%Y-%m-%d %H:%M UTC"))
+            }
+        }
+    }
+
+    /// What the user can do about the problem
+    pub fn suggestion(&self, position: &Position) -> String {
+        match self {
+            DataIssue::UnmappedSymbol { guessed } => format!(
+                "Add \"{}\" to data/symbol_mapper.json with its name, country, Yahoo symbol (currently guessed as {}) and withholding tax rate",
+                position.ticker, guessed
+            ),
+            DataIssue::MarketDataError(_) => format!(
+                "Check that {} is the right Yahoo symbol for {}. If not, set yf_ticker of \"{}\" in data/symbol_mapper.json",
+                position.yf_ticker, position.ticker, position.ticker
+            ),
+            DataIssue::UnsupportedCurrency(currency) => format!(
+                "Add {} to the supported currencies; until then values are shown unconverted",
+                currency
+            ),
+            DataIssue::StaleData { .. } => {
+                "Check the connection to the market data source in Settings, then use Refresh market data on the Portfolio page".to_string()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_suggestions_name_the_ticker() {
+        let position = Position {
+            ticker: "XYZl_EQ".to_string(),
+            yf_ticker: "XYZ.L".to_string(),
+            ..Position::default()
+        };
+        let unmapped = DataIssue::UnmappedSymbol {
+            guessed: "XYZ.L".to_string(),
+        };
+        assert!(!unmapped.is_error());
+        assert!(unmapped.suggestion(&position).contains("\"XYZl_EQ\""));
+
+        let error = DataIssue::MarketDataError("Quote not found".to_string());
+        assert!(error.is_error());
+        assert!(error.suggestion(&position).contains("XYZ.L"));
+    }
+}
@@ -16,9 +16,16 @@
 //
 // USE THIS SOFTWARE AT YOUR OWN RISK.
 
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use super::portfolio::MonthlyPayment;
+use crate::services::trading212::{RequestType, Trading212Client};
+use crate::utils::settings::Config;
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct DividendInfo {
     pub symbol: String,
     pub quantity: f64,
@@ -49,3 +56,222 @@ pub struct DividendSummary {
     pub total_cost: f64,
     pub yield_on_cost: f64,
 }
+
+/// Fraction the dividends of a year may fall short of the previous year
+/// before it counts as a cut
+const CUT_TOLERANCE: f64 = 0.05;
+
+/// Year in which the dividends per share fell compared to the year before
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DividendCut {
+    pub year: i32,
+    pub previous: f64,
+    pub amount: f64,
+}
+
+impl DividendCut {
+    pub fn percent(&self) -> f64 {
+        (self.amount / self.previous - 1.0) * 100.0
+    }
+}
+
+/// Growth of the dividends per share of a holding, based on the totals of
+/// complete calendar years
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DividendGrowth {
+    /// Last complete year the figures are based on
+    pub last_year: Option<i32>,
+    /// Compound annual growth in percent over the last 1, 3, 5 and 10 years
+    pub cagr_1y: Option<f64>,
+    pub cagr_3y: Option<f64>,
+    pub cagr_5y: Option<f64>,
+    pub cagr_10y: Option<f64>,
+    /// Years in a row, up to the last complete year, with a higher total
+    /// or average payment than the year before
+    pub consecutive_increases: u32,
+    pub cuts: Vec<DividendCut>,
+}
+
+impl DividendGrowth {
+    /// Computes the growth from the dividends per share paid up to the
+    /// last complete year before `today`. Years without any payment after
+    /// the first one, including the most recent ones, count as suspended
+    /// dividends.
+    ///
+    /// A payment moving across the year end leaves one year with a payment
+    /// less, so a year only counts as a cut when both its total and its
+    /// average payment fell, and as an increase when either rose.
+    pub fn from_history(history: &[MonthlyPayment], today: NaiveDate) -> Self {
+        let last_year = today.year() - 1;
+        let mut years: BTreeMap<i32, (f64, u32)> = BTreeMap::new();
+        for payment in history.iter().filter(|p| p.date.year() <= last_year) {
+            let year = years.entry(payment.date.year()).or_insert((0.0, 0));
+            year.0 += payment.amount;
+            year.1 += 1;
+        }
+        let Some(&first_year) = years.keys().next() else {
+            return Self::default();
+        };
+        let total = |year: i32| years.get(&year).map_or(0.0, |y| y.0);
+        let average = |year: i32| match years.get(&year) {
+            Some(&(total, count)) if count > 0 => total / count as f64,
+            _ => 0.0,
+        };
+        let fell = |figure: &dyn Fn(i32) -> f64, year: i32| {
+            figure(year) < figure(year - 1) * (1.0 - CUT_TOLERANCE)
+        };
+
+        let cagr = |years: i32| {
+            let start = last_year - years;
+            if start < first_year || total(start) <= 0.0 || total(last_year) <= 0.0 {
+                return None;
+            }
+            Some(((total(last_year) / total(start)).powf(1.0 / years as f64) - 1.0) * 100.0)
+        };
+
+        let consecutive_increases = (first_year + 1..=last_year)
+            .rev()
+            .take_while(|&year| total(year) > total(year - 1) || average(year) > average(year - 1))
+            .count() as u32;
+
+        let cuts = (first_year + 1..=last_year)
+            .filter(|&year| fell(&total, year) && fell(&average, year))
+            .map(|year| DividendCut {
+                year,
+                previous: total(year - 1),
+                amount: total(year),
+            })
+            .collect();
+
+        Self {
+            last_year: Some(last_year),
+            cagr_1y: cagr(1),
+            cagr_3y: cagr(3),
+            cagr_5y: cagr(5),
+            cagr_10y: cagr(10),
+            consecutive_increases,
+            cuts,
+        }
+    }
+}
+
+/// A dividend paid into the account, as reported by `/history/dividends`.
+/// `amount` is the net amount in the account currency, while
+/// `gross_amount_per_share` is in the instrument currency.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DividendPayment {
+    pub ticker: String,
+    pub reference: String,
+    pub quantity: f64,
+    pub amount: f64,
+    pub gross_amount_per_share: f64,
+    pub paid_on: DateTime<Utc>,
+    pub dividend_type: String,
+}
+
+/// Fetches every dividend paid into the account from Trading 212, newest first
+pub async fn fetch_dividend_history(
+    config: &Config,
+) -> Result<Vec<DividendPayment>, anyhow::Error> {
+    let trading212_client = Trading212Client::new(RequestType::DividendsPaid, config)
+        .map_err(|e| anyhow::anyhow!("Failed to initialize Trading212 client: {}", e))?;
+
+    let mut payments = trading212_client
+        .get_dividend_history()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to fetch dividend history: {}", e))?;
+
+    payments.sort_by_key(|p| std::cmp::Reverse(p.paid_on));
+    Ok(payments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payments(list: &[(&str, f64)]) -> Vec<MonthlyPayment> {
+        list.iter()
+            .map(|(date, amount)| MonthlyPayment {
+                date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+                amount: *amount,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dividend_growth() {
+        let history = payments(&[
+            ("2018-06-01", 1.0),
+            ("2019-06-01", 1.2),
+            ("2020-06-01", 0.6),
+            ("2022-06-01", 0.8),
+            ("2023-06-01", 0.9),
+            ("2024-03-01", 0.5),
+            ("2024-09-01", 0.5),
+            // The current year is incomplete and left out
+            ("2025-03-01", 0.1),
+        ]);
+        let today = NaiveDate::from_ymd_opt(2025, 5, 1).unwrap();
+        let growth = DividendGrowth::from_history(&history, today);
+
+        assert_eq!(growth.last_year, Some(2024));
+        assert!((growth.cagr_1y.unwrap() - 11.111).abs() < 0.01);
+        assert!((growth.cagr_5y.unwrap() - (-3.58)).abs() < 0.01);
+        // 2021 paid nothing, so there is no 3 year figure
+        assert_eq!(growth.cagr_3y, None);
+        assert_eq!(growth.cagr_10y, None);
+        assert_eq!(growth.consecutive_increases, 3);
+        let cut_years: Vec<i32> = growth.cuts.iter().map(|c| c.year).collect();
+        assert_eq!(cut_years, vec![2020, 2021]);
+        assert!((growth.cuts[0].percent() - (-50.0)).abs() < 1e-9);
+
+        assert_eq!(
+            DividendGrowth::from_history(&[], today),
+            DividendGrowth::default()
+        );
+    }
+
+    #[test]
+    fn test_suspended_dividend() {
+        let history = payments(&[
+            ("2020-06-01", 1.0),
+            ("2021-06-01", 1.1),
+            ("2022-06-01", 1.2),
+            // Nothing paid since
+        ]);
+        let today = NaiveDate::from_ymd_opt(2025, 5, 1).unwrap();
+        let growth = DividendGrowth::from_history(&history, today);
+
+        assert_eq!(growth.last_year, Some(2024));
+        assert_eq!(growth.cagr_1y, None);
+        assert_eq!(growth.cagr_3y, None);
+        assert_eq!(growth.consecutive_increases, 0);
+        let cut_years: Vec<i32> = growth.cuts.iter().map(|c| c.year).collect();
+        assert_eq!(cut_years, vec![2023]);
+        assert_eq!(growth.cuts[0].amount, 0.0);
+    }
+
+    #[test]
+    fn test_payment_moved_across_year_end() {
+        let history = payments(&[
+            ("2021-03-01", 0.25),
+            ("2021-06-01", 0.25),
+            ("2021-09-01", 0.25),
+            ("2021-12-01", 0.25),
+            ("2022-03-01", 0.26),
+            ("2022-06-01", 0.26),
+            ("2022-09-01", 0.26),
+            // The December payment slipped into January
+            ("2023-01-05", 0.26),
+            ("2023-03-01", 0.27),
+            ("2023-06-01", 0.27),
+            ("2023-09-01", 0.27),
+            ("2023-12-01", 0.27),
+        ]);
+        let today = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let growth = DividendGrowth::from_history(&history, today);
+
+        assert!(growth.cuts.is_empty());
+        assert_eq!(growth.consecutive_increases, 2);
+    }
+}
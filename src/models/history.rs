@@ -0,0 +1,584 @@
+// File: history.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::order::{Order, OrderSide};
+use super::transaction::{CashTransaction, TransactionKind};
+
+/// Columns holding fees and taxes charged on a trade, in the account currency
+const FEE_COLUMNS: &[&str] = &[
+    "Currency conversion fee",
+    "Transaction fee",
+    "Finra fee",
+    "Stamp duty",
+    "Stamp duty reserve tax",
+    "French transaction tax",
+    "PTM Levy",
+];
+
+/// Time formats used by the exports. Newer exports use ISO dates, older
+/// ones and files saved from the app use the day first.
+const TIME_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%d/%m/%Y %H:%M:%S",
+    "%d/%m/%Y %H:%M",
+];
+
+/// Parses the `Time` column of an export in any of the formats Trading 212
+/// has used
+pub fn parse_export_time(value: &str) -> Option<DateTime<Utc>> {
+    TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value.trim(), format).ok())
+        .map(|t| t.and_utc())
+}
+
+#[derive(Debug, Error)]
+pub enum ExportParseError {
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("Missing column: {0}")]
+    MissingColumn(&'static str),
+    #[error("Row {row}: invalid {column} '{value}'")]
+    InvalidValue {
+        row: usize,
+        column: String,
+        value: String,
+    },
+}
+
+/// How an order in the export was placed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OrderKind {
+    Market,
+    Limit,
+    Stop,
+    StopLimit,
+}
+
+/// Kind of a dividend, taken from the part of the action in parentheses,
+/// e.g. `Dividend (Ordinary)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DividendKind {
+    Ordinary,
+    ReturnOfCapital,
+    ManufacturedPayment,
+    TaxExempt,
+    Other(String),
+}
+
+impl From<&str> for DividendKind {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            ""
+            | "ordinary"
+            | "dividend"
+            | "dividends paid by us corporations"
+            | "dividends paid by foreign corporations" => DividendKind::Ordinary,
+            "return of capital" => DividendKind::ReturnOfCapital,
+            "dividend manufactured payment" => DividendKind::ManufacturedPayment,
+            "tax exempted" => DividendKind::TaxExempt,
+            _ => DividendKind::Other(s.to_string()),
+        }
+    }
+}
+
+/// The `Action` column of an export row
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExportAction {
+    Order { side: OrderSide, kind: OrderKind },
+    Dividend(DividendKind),
+    Deposit,
+    Withdrawal,
+    Interest,
+    CurrencyConversion,
+    Other(String),
+}
+
+impl From<&str> for ExportAction {
+    fn from(s: &str) -> Self {
+        let action = s.trim();
+        let lower = action.to_lowercase();
+        if action
+            .get(..8)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("dividend"))
+        {
+            let kind = action[8..]
+                .trim()
+                .trim_start_matches('(')
+                .trim_end_matches(')');
+            return ExportAction::Dividend(DividendKind::from(kind));
+        }
+
+        let order = |side| {
+            let kind = match lower.rsplit_once(' ').map(|(kind, _)| kind) {
+                Some("market") => OrderKind::Market,
+                Some("limit") => OrderKind::Limit,
+                Some("stop") => OrderKind::Stop,
+                Some("stop limit") => OrderKind::StopLimit,
+                _ => return ExportAction::Other(action.to_string()),
+            };
+            ExportAction::Order { side, kind }
+        };
+        match lower.as_str() {
+            a if a.ends_with(" buy") => order(OrderSide::Buy),
+            a if a.ends_with(" sell") => order(OrderSide::Sell),
+            "deposit" => ExportAction::Deposit,
+            "withdrawal" => ExportAction::Withdrawal,
+            "interest on cash" | "lending interest" => ExportAction::Interest,
+            "currency conversion" => ExportAction::CurrencyConversion,
+            _ => ExportAction::Other(action.to_string()),
+        }
+    }
+}
+
+/// Instrument columns of a trade or dividend row
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Instrument {
+    pub isin: String,
+    pub ticker: String,
+    pub name: String,
+}
+
+/// A filled order. `price_per_share` is in `price_currency`, while
+/// `total`, `result` and `fees` are in the account currency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeEvent {
+    pub id: Option<String>,
+    pub time: DateTime<Utc>,
+    pub side: OrderSide,
+    pub kind: OrderKind,
+    pub instrument: Instrument,
+    pub quantity: f64,
+    pub price_per_share: f64,
+    pub price_currency: String,
+    pub exchange_rate: Option<f64>,
+    /// Realised profit or loss, only reported for sells
+    pub result: Option<f64>,
+    pub total: f64,
+    pub total_currency: String,
+    pub fees: f64,
+}
+
+/// A dividend payment. `total` is net of withholding tax.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DividendEvent {
+    pub id: Option<String>,
+    pub time: DateTime<Utc>,
+    pub kind: DividendKind,
+    pub instrument: Instrument,
+    pub quantity: f64,
+    pub price_per_share: f64,
+    pub price_currency: String,
+    pub total: f64,
+    pub total_currency: String,
+    pub withholding_tax: f64,
+    pub withholding_tax_currency: String,
+}
+
+/// An exchange between two currencies of the account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversionEvent {
+    pub id: Option<String>,
+    pub time: DateTime<Utc>,
+    pub from_amount: f64,
+    pub from_currency: String,
+    pub to_amount: f64,
+    pub to_currency: String,
+    pub fee: f64,
+}
+
+/// One row of a Trading 212 CSV export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HistoryEvent {
+    Trade(TradeEvent),
+    Dividend(DividendEvent),
+    /// Deposits, withdrawals and interest, with the export ID as reference
+    Cash(CashTransaction),
+    Conversion(ConversionEvent),
+    /// Actions without a typed representation, e.g. card payments
+    Other {
+        action: String,
+        id: Option<String>,
+        time: DateTime<Utc>,
+        total: Option<f64>,
+    },
+}
+
+impl HistoryEvent {
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            HistoryEvent::Trade(e) => e.id.as_deref(),
+            HistoryEvent::Dividend(e) => e.id.as_deref(),
+            HistoryEvent::Cash(t) => Some(t.reference.as_str()).filter(|r| !r.is_empty()),
+            HistoryEvent::Conversion(e) => e.id.as_deref(),
+            HistoryEvent::Other { id, .. } => id.as_deref(),
+        }
+    }
+
+    pub fn time(&self) -> DateTime<Utc> {
+        match self {
+            HistoryEvent::Trade(e) => e.time,
+            HistoryEvent::Dividend(e) => e.time,
+            HistoryEvent::Cash(t) => t.date_time,
+            HistoryEvent::Conversion(e) => e.time,
+            HistoryEvent::Other { time, .. } => *time,
+        }
+    }
+}
+
+impl From<TradeEvent> for Order {
+    fn from(trade: TradeEvent) -> Self {
+        let order_type = match trade.kind {
+            OrderKind::Market => "MARKET",
+            OrderKind::Limit => "LIMIT",
+            OrderKind::Stop => "STOP",
+            OrderKind::StopLimit => "STOP_LIMIT",
+        };
+        Order {
+            // Exports have no numeric order id
+            id: 0,
+            ticker: trade.instrument.ticker,
+            order_type: order_type.to_string(),
+            status: "FILLED".to_string(),
+            side: trade.side,
+            quantity: trade.quantity,
+            fill_price: trade.price_per_share,
+            filled_value: trade.total.abs(),
+            fees: trade.fees,
+            fx_rate: trade.exchange_rate,
+            date_created: Some(trade.time),
+            date_executed: Some(trade.time),
+        }
+    }
+}
+
+/// A data row together with the column positions of its file
+struct Row<'a> {
+    columns: &'a HashMap<String, usize>,
+    record: &'a csv::StringRecord,
+    /// Line number in the file, counting the header as line 1
+    line: usize,
+}
+
+impl Row<'_> {
+    fn text(&self, column: &str) -> Option<String> {
+        self.columns
+            .get(column)
+            .and_then(|&i| self.record.get(i))
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(String::from)
+    }
+
+    fn invalid(&self, column: &str, value: &str) -> ExportParseError {
+        ExportParseError::InvalidValue {
+            row: self.line,
+            column: column.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn number(&self, column: &str) -> Result<Option<f64>, ExportParseError> {
+        self.text(column)
+            .map(|v| v.parse::<f64>().map_err(|_| self.invalid(column, &v)))
+            .transpose()
+    }
+
+    /// Number that is only informative, such as the exchange rate. Texts
+    /// like `Not available` leave it unknown instead of failing the row.
+    fn optional_number(&self, column: &str) -> Option<f64> {
+        self.text(column).and_then(|v| v.parse::<f64>().ok())
+    }
+
+    fn amount(&self, column: &str) -> Result<f64, ExportParseError> {
+        Ok(self.number(column)?.unwrap_or(0.0))
+    }
+
+    fn currency(&self, column: &str) -> String {
+        self.text(&format!("Currency ({})", column))
+            .unwrap_or_default()
+    }
+
+    fn time(&self) -> Result<DateTime<Utc>, ExportParseError> {
+        let value = self.text("Time").unwrap_or_default();
+        parse_export_time(&value).ok_or_else(|| self.invalid("Time", &value))
+    }
+
+    fn instrument(&self) -> Instrument {
+        Instrument {
+            isin: self.text("ISIN").unwrap_or_default(),
+            ticker: self.text("Ticker").unwrap_or_default(),
+            name: self.text("Name").unwrap_or_default(),
+        }
+    }
+
+    fn cash(
+        &self,
+        kind: TransactionKind,
+        id: Option<String>,
+        time: DateTime<Utc>,
+    ) -> Result<HistoryEvent, ExportParseError> {
+        Ok(HistoryEvent::Cash(CashTransaction {
+            kind,
+            amount: self.amount("Total")?,
+            reference: id.unwrap_or_default(),
+            date_time: time,
+        }))
+    }
+
+    fn event(&self) -> Result<HistoryEvent, ExportParseError> {
+        let action = self.text("Action").unwrap_or_default();
+        let id = self.text("ID");
+        let time = self.time()?;
+
+        let event = match ExportAction::from(action.as_str()) {
+            ExportAction::Order { side, kind } => {
+                let mut fees = 0.0;
+                for column in FEE_COLUMNS {
+                    fees += self.amount(column)?.abs();
+                }
+                HistoryEvent::Trade(TradeEvent {
+                    id,
+                    time,
+                    side,
+                    kind,
+                    instrument: self.instrument(),
+                    quantity: self.amount("No. of shares")?,
+                    price_per_share: self.amount("Price / share")?,
+                    price_currency: self.currency("Price / share"),
+                    exchange_rate: self.optional_number("Exchange rate"),
+                    result: self.optional_number("Result"),
+                    total: self.amount("Total")?,
+                    total_currency: self.currency("Total"),
+                    fees,
+                })
+            }
+            ExportAction::Dividend(kind) => HistoryEvent::Dividend(DividendEvent {
+                id,
+                time,
+                kind,
+                instrument: self.instrument(),
+                quantity: self.amount("No. of shares")?,
+                price_per_share: self.amount("Price / share")?,
+                price_currency: self.currency("Price / share"),
+                total: self.amount("Total")?,
+                total_currency: self.currency("Total"),
+                withholding_tax: self.amount("Withholding tax")?.abs(),
+                withholding_tax_currency: self.currency("Withholding tax"),
+            }),
+            ExportAction::Deposit => self.cash(TransactionKind::Deposit, id, time)?,
+            ExportAction::Withdrawal => self.cash(TransactionKind::Withdrawal, id, time)?,
+            ExportAction::Interest => self.cash(TransactionKind::Interest, id, time)?,
+            ExportAction::CurrencyConversion => HistoryEvent::Conversion(ConversionEvent {
+                id,
+                time,
+                from_amount: self.amount("Currency conversion from amount")?,
+                from_currency: self.currency("Currency conversion from amount"),
+                to_amount: self.amount("Currency conversion to amount")?,
+                to_currency: self.currency("Currency conversion to amount"),
+                fee: self.amount("Currency conversion fee")?.abs(),
+            }),
+            ExportAction::Other(action) => HistoryEvent::Other {
+                action,
+                id,
+                time,
+                total: self.number("Total")?,
+            },
+        };
+        Ok(event)
+    }
+}
+
+/// Events of an export together with the rows that could not be read
+#[derive(Debug, Default)]
+pub struct ParsedExport {
+    pub events: Vec<HistoryEvent>,
+    /// Errors of the skipped rows, with their line numbers
+    pub skipped: Vec<ExportParseError>,
+}
+
+/// Parses a Trading 212 CSV export into typed events
+///
+/// Columns are looked up by their header, so their order does not matter
+/// and unknown columns are ignored. Only `Action` and `Time` are required,
+/// since Trading 212 leaves out the columns that are empty in an export.
+/// A row that cannot be read is skipped rather than failing the file; only
+/// an unusable header is an error.
+pub fn parse_export(csv_content: &str) -> Result<ParsedExport, ExportParseError> {
+    let mut rdr = csv::Reader::from_reader(csv_content.as_bytes());
+    let columns: HashMap<String, usize> = rdr
+        .headers()?
+        .iter()
+        .enumerate()
+        .map(|(i, h)| (h.trim().to_string(), i))
+        .collect();
+    for required in ["Action", "Time"] {
+        if !columns.contains_key(required) {
+            return Err(ExportParseError::MissingColumn(required));
+        }
+    }
+
+    let mut parsed = ParsedExport::default();
+    for (i, record) in rdr.records().enumerate() {
+        let event = record.map_err(ExportParseError::from).and_then(|record| {
+            Row {
+                columns: &columns,
+                record: &record,
+                line: i + 2,
+            }
+            .event()
+        });
+        match event {
+            Ok(event) => parsed.events.push(event),
+            Err(e) => parsed.skipped.push(e),
+        }
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_action_from_str() {
+        assert_eq!(
+            ExportAction::from("Market buy"),
+            ExportAction::Order {
+                side: OrderSide::Buy,
+                kind: OrderKind::Market
+            }
+        );
+        assert_eq!(
+            ExportAction::from("Stop limit sell"),
+            ExportAction::Order {
+                side: OrderSide::Sell,
+                kind: OrderKind::StopLimit
+            }
+        );
+        assert_eq!(
+            ExportAction::from("Dividend (Dividends paid by us corporations)"),
+            ExportAction::Dividend(DividendKind::Ordinary)
+        );
+        assert_eq!(
+            ExportAction::from("Dividend (Return of capital)"),
+            ExportAction::Dividend(DividendKind::ReturnOfCapital)
+        );
+        assert_eq!(
+            ExportAction::from("Interest on cash"),
+            ExportAction::Interest
+        );
+        assert_eq!(
+            ExportAction::from("Card debit"),
+            ExportAction::Other("Card debit".to_string())
+        );
+        // Lowercasing changes the byte length of some characters
+        assert_eq!(
+            ExportAction::from("İİ dividend"),
+            ExportAction::Other("İİ dividend".to_string())
+        );
+        assert_eq!(
+            ExportAction::from("DIVIDEND (Ordinary)"),
+            ExportAction::Dividend(DividendKind::Ordinary)
+        );
+    }
+
+    #[test]
+    fn test_parse_mock_export() {
+        let parsed =
+            parse_export(&std::fs::read_to_string("mock_data/export.csv").unwrap()).unwrap();
+        assert!(parsed.skipped.is_empty());
+        let events = parsed.events;
+        let count = |pred: fn(&HistoryEvent) -> bool| events.iter().filter(|e| pred(e)).count();
+
+        assert_eq!(events.len(), 169);
+        assert_eq!(count(|e| matches!(e, HistoryEvent::Dividend(_))), 80);
+        assert_eq!(
+            count(|e| matches!(e, HistoryEvent::Trade(t) if t.side == OrderSide::Sell)),
+            2
+        );
+        assert_eq!(
+            count(|e| matches!(e, HistoryEvent::Cash(t) if t.kind == TransactionKind::Interest)),
+            6
+        );
+        assert_eq!(events[0].id(), Some("DEP-20240102"));
+    }
+
+    #[test]
+    fn test_parse_tolerates_reordered_and_extra_columns() {
+        let csv = "Notes,Total,Currency (Total),Time,Action,ID,Ticker,No. of shares,Price / share,\
+                   Currency (Price / share),Withholding tax,Currency (Withholding tax),Merchant name\n\
+                   ,1.53,GBP,2024-03-28 10:15:02,Dividend (Ordinary),,VUSA,10,0.18,USD,-0.27,USD,\n\
+                   ,-0.50,GBP,2024-03-29 12:00:00,Card debit,CARD-1,,,,,,,Coffee\n\
+                   ,100.00,GBP,02/04/2024 09:00,Currency conversion,FX-1,,,,,,,\n";
+
+        let events = parse_export(csv).unwrap().events;
+        match &events[0] {
+            HistoryEvent::Dividend(d) => {
+                assert_eq!(d.instrument.ticker, "VUSA");
+                assert_eq!(d.total, 1.53);
+                assert_eq!(d.withholding_tax, 0.27);
+                assert_eq!(d.price_currency, "USD");
+                assert_eq!(d.id, None);
+            }
+            other => panic!("expected a dividend, got {:?}", other),
+        }
+        assert!(
+            matches!(&events[1], HistoryEvent::Other { action, total: Some(t), .. }
+            if action == "Card debit" && *t == -0.5)
+        );
+        assert_eq!(events[2].id(), Some("FX-1"));
+        assert_eq!(
+            events[2].time().format("%Y-%m-%d").to_string(),
+            "2024-04-02"
+        );
+    }
+
+    #[test]
+    fn test_parse_skips_invalid_rows() {
+        assert!(matches!(
+            parse_export("Action,Total\nDeposit,1\n"),
+            Err(ExportParseError::MissingColumn("Time"))
+        ));
+        let parsed = parse_export(
+            "Action,Time,Ticker,No. of shares,Price / share,Exchange rate,Total\n\
+             Deposit,2024-01-01 10:00:00,,,,,abc\n\
+             Market buy,2024-01-02 10:00:00,VUSA,1,80,Not available,80\n",
+        )
+        .unwrap();
+        match &parsed.skipped[..] {
+            [ExportParseError::InvalidValue { row, column, .. }] => {
+                assert_eq!(*row, 2);
+                assert_eq!(column, "Total");
+            }
+            other => panic!("expected one invalid value, got {:?}", other),
+        }
+        // An unknown exchange rate does not lose the trade
+        match &parsed.events[..] {
+            [HistoryEvent::Trade(t)] => assert_eq!(t.exchange_rate, None),
+            other => panic!("expected one trade, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+// File: market.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::services::trading212::{Exchange, TimeEvent};
+
+/// Whether the market of an instrument is in its regular trading session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum MarketStatus {
+    Open,
+    Closed,
+    /// No schedule is known for the instrument, or the schedule does not
+    /// cover the current time
+    #[default]
+    Unknown,
+}
+
+impl std::fmt::Display for MarketStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MarketStatus::Open => write!(f, "Open"),
+            MarketStatus::Closed => write!(f, "Closed"),
+            MarketStatus::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+/// Trading schedule of one exchange session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Schedule {
+    pub exchange: String,
+    /// Time events sorted by date
+    pub events: Vec<TimeEvent>,
+}
+
+impl Schedule {
+    /// Status of the regular session at `at`. Pre-market and after-hours
+    /// trading count as closed.
+    pub fn status_at(&self, at: DateTime<Utc>) -> MarketStatus {
+        let covers = self.events.iter().any(|e| e.date > at);
+        let last = self.events.iter().rev().find(|e| e.date <= at);
+        match last {
+            Some(event) if covers => match event.event_type.as_str() {
+                "OPEN" | "BREAK_END" => MarketStatus::Open,
+                _ => MarketStatus::Closed,
+            },
+            _ => MarketStatus::Unknown,
+        }
+    }
+}
+
+/// Trading schedules of every exchange, keyed by working schedule id
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MarketHours {
+    pub schedules: HashMap<i64, Schedule>,
+}
+
+impl MarketHours {
+    pub fn new(exchanges: Vec<Exchange>) -> Self {
+        let mut schedules = HashMap::new();
+        for exchange in exchanges {
+            for schedule in exchange.working_schedules {
+                let mut events = schedule.time_events;
+                events.sort_by_key(|e| e.date);
+                schedules.insert(
+                    schedule.id,
+                    Schedule {
+                        exchange: exchange.name.clone(),
+                        events,
+                    },
+                );
+            }
+        }
+        Self { schedules }
+    }
+
+    pub fn exchange_name(&self, schedule_id: Option<i64>) -> Option<&str> {
+        schedule_id
+            .and_then(|id| self.schedules.get(&id))
+            .map(|s| s.exchange.as_str())
+    }
+
+    pub fn status(&self, schedule_id: Option<i64>, at: DateTime<Utc>) -> MarketStatus {
+        schedule_id
+            .and_then(|id| self.schedules.get(&id))
+            .map(|s| s.status_at(at))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::trading212::WorkingSchedule;
+
+    fn event(date: &str, event_type: &str) -> TimeEvent {
+        TimeEvent {
+            date: date.parse().unwrap(),
+            event_type: event_type.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_market_status_from_schedule() {
+        let hours = MarketHours::new(vec![Exchange {
+            id: 1,
+            name: "NYSE".to_string(),
+            working_schedules: vec![WorkingSchedule {
+                id: 53,
+                time_events: vec![
+                    event("2025-03-03T21:00:00Z", "CLOSE"),
+                    event("2025-03-03T14:30:00Z", "OPEN"),
+                    event("2025-03-03T09:00:00Z", "PRE_MARKET_OPEN"),
+                    event("2025-03-04T09:00:00Z", "PRE_MARKET_OPEN"),
+                ],
+            }],
+        }]);
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+        assert_eq!(hours.exchange_name(Some(53)), Some("NYSE"));
+        assert_eq!(
+            hours.status(Some(53), at("2025-03-03T10:00:00Z")),
+            MarketStatus::Closed
+        );
+        assert_eq!(
+            hours.status(Some(53), at("2025-03-03T15:00:00Z")),
+            MarketStatus::Open
+        );
+        assert_eq!(
+            hours.status(Some(53), at("2025-03-03T22:00:00Z")),
+            MarketStatus::Closed
+        );
+        // Past the end of the schedule
+        assert_eq!(
+            hours.status(Some(53), at("2025-03-05T15:00:00Z")),
+            MarketStatus::Unknown
+        );
+        assert_eq!(
+            hours.status(None, at("2025-03-03T15:00:00Z")),
+            MarketStatus::Unknown
+        );
+    }
+}
@@ -15,5 +15,14 @@
 // financial losses, or other issues arising from the use of this software.
 //
 // USE THIS SOFTWARE AT YOUR OWN RISK.
+pub mod allocation;
+pub mod benchmark;
+pub mod corporate_action;
+pub mod data_quality;
 pub mod dividend;
+pub mod history;
+pub mod market;
+pub mod order;
+pub mod pie;
 pub mod portfolio;
+pub mod transaction;
@@ -0,0 +1,342 @@
+// File: order.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex as TokioMutex;
+
+use crate::services::trading212::{RequestType, Trading212Client};
+use crate::utils::settings::Config;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+impl std::fmt::Display for OrderSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderSide::Buy => write!(f, "Buy"),
+            OrderSide::Sell => write!(f, "Sell"),
+        }
+    }
+}
+
+/// A single historical order as reported by `/equity/history/orders`.
+///
+/// `fill_price` is in the instrument currency while `filled_value` and
+/// `fees` are in the account currency.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Order {
+    pub id: i64,
+    pub ticker: String,
+    pub order_type: String,
+    pub status: String,
+    pub side: OrderSide,
+    pub quantity: f64,
+    pub fill_price: f64,
+    pub filled_value: f64,
+    pub fees: f64,
+    pub fx_rate: Option<f64>,
+    pub date_created: Option<DateTime<Utc>>,
+    pub date_executed: Option<DateTime<Utc>>,
+}
+
+impl Order {
+    /// The API does not return the FX rate of a fill, but it can be derived
+    /// from the fill price (instrument currency) and the filled value
+    /// (account currency).
+    pub fn derive_fx_rate(quantity: f64, fill_price: f64, filled_value: f64) -> Option<f64> {
+        let instrument_value = quantity * fill_price;
+        if instrument_value > 0.0 && filled_value > 0.0 {
+            Some(filled_value / instrument_value)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_filled(&self) -> bool {
+        self.quantity > 0.0
+    }
+
+    /// Execution time if the order was filled, otherwise the creation time
+    pub fn date(&self) -> Option<DateTime<Utc>> {
+        self.date_executed.or(self.date_created)
+    }
+
+    pub fn date_str(&self) -> String {
+        self.date()
+            .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    pub fn fx_rate_str(&self) -> String {
+        self.fx_rate
+            .map(|r| format!("{:.4}", r))
+            .unwrap_or_else(|| "-".to_string())
+    }
+}
+
+/// Filter applied to the order history on the `/orders` page
+#[derive(Debug, Clone, Default)]
+pub struct OrderFilter {
+    pub ticker: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl OrderFilter {
+    pub fn matches(&self, order: &Order) -> bool {
+        if let Some(ticker) = &self.ticker {
+            if !order.ticker.eq_ignore_ascii_case(ticker) {
+                return false;
+            }
+        }
+
+        let date = order.date().map(|d| d.date_naive());
+        if let Some(from) = self.from {
+            if date.is_none_or(|d| d < from) {
+                return false;
+            }
+        }
+        if let Some(to) = self.to {
+            if date.is_none_or(|d| d > to) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn apply(&self, orders: Vec<Order>) -> Vec<Order> {
+        orders.into_iter().filter(|o| self.matches(o)).collect()
+    }
+}
+
+/// Aggregated fills of one ticker, used to audit the average price
+#[derive(Debug, Clone)]
+pub struct TickerOrderSummary {
+    pub ticker: String,
+    pub bought_quantity: f64,
+    pub sold_quantity: f64,
+    pub avg_buy_price: f64,
+    pub total_fees: f64,
+}
+
+pub fn summarize_by_ticker(orders: &[Order]) -> Vec<TickerOrderSummary> {
+    // ticker -> (bought qty, bought cost, sold qty, fees)
+    let mut map: HashMap<String, (f64, f64, f64, f64)> = HashMap::new();
+    for order in orders.iter().filter(|o| o.is_filled()) {
+        let entry = map
+            .entry(order.ticker.clone())
+            .or_insert((0.0, 0.0, 0.0, 0.0));
+        match order.side {
+            OrderSide::Buy => {
+                entry.0 += order.quantity;
+                entry.1 += order.quantity * order.fill_price;
+            }
+            OrderSide::Sell => entry.2 += order.quantity,
+        }
+        entry.3 += order.fees;
+    }
+
+    let mut summary: Vec<TickerOrderSummary> = map
+        .into_iter()
+        .map(|(ticker, (bought, cost, sold, fees))| TickerOrderSummary {
+            ticker,
+            bought_quantity: bought,
+            sold_quantity: sold,
+            avg_buy_price: if bought > 0.0 { cost / bought } else { 0.0 },
+            total_fees: fees,
+        })
+        .collect();
+    summary.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+    summary
+}
+
+/// Order history of each account, kept between page views so that only
+/// the orders placed since the last view are fetched again
+#[derive(Debug, Clone, Default)]
+pub struct OrderHistoryCache {
+    /// Orders by the API key of the account, newest first
+    accounts: Arc<TokioMutex<HashMap<String, Vec<Order>>>>,
+}
+
+impl OrderHistoryCache {
+    /// Order history of the account of `config`, newest first. The lock is
+    /// not held while the new orders are fetched.
+    pub async fn orders(&self, config: &Config) -> Result<Vec<Order>, anyhow::Error> {
+        let key = config.api_key.clone().unwrap_or_default();
+        let known: HashSet<i64> = self
+            .accounts
+            .lock()
+            .await
+            .get(&key)
+            .map(|orders| orders.iter().map(|o| o.id).collect())
+            .unwrap_or_default();
+
+        let trading212_client = Trading212Client::new(RequestType::OrderHistory, config)
+            .map_err(|e| anyhow::anyhow!("Failed to initialize Trading212 client: {}", e))?;
+        let new_orders = trading212_client
+            .get_order_history_since(&known)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch order history: {}", e))?;
+
+        let mut accounts = self.accounts.lock().await;
+        let orders = accounts.entry(key).or_default();
+        merge_orders(orders, new_orders);
+        Ok(orders.clone())
+    }
+}
+
+/// Adds `new_orders` to `orders`, replacing the orders with the same id,
+/// and sorts them newest first
+fn merge_orders(orders: &mut Vec<Order>, new_orders: Vec<Order>) {
+    let new_ids: HashSet<i64> = new_orders.iter().map(|o| o.id).collect();
+    orders.retain(|o| !new_ids.contains(&o.id));
+    orders.extend(new_orders);
+    orders.sort_by_key(|o| std::cmp::Reverse(o.date()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(ticker: &str, side: OrderSide, quantity: f64, price: f64, date: &str) -> Order {
+        Order {
+            id: 1,
+            ticker: ticker.to_string(),
+            order_type: "MARKET".to_string(),
+            status: "FILLED".to_string(),
+            side,
+            quantity,
+            fill_price: price,
+            filled_value: quantity * price,
+            fees: 0.5,
+            fx_rate: None,
+            date_created: None,
+            date_executed: Some(date.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_derive_fx_rate() {
+        assert_eq!(Order::derive_fx_rate(2.0, 100.0, 160.0), Some(0.8));
+        assert_eq!(Order::derive_fx_rate(0.0, 100.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_filter_by_ticker_and_date() {
+        let orders = vec![
+            order(
+                "AAPL_US_EQ",
+                OrderSide::Buy,
+                1.0,
+                100.0,
+                "2024-01-10T10:00:00Z",
+            ),
+            order(
+                "AAPL_US_EQ",
+                OrderSide::Buy,
+                1.0,
+                120.0,
+                "2024-03-10T10:00:00Z",
+            ),
+            order(
+                "VUSAl_EQ",
+                OrderSide::Buy,
+                1.0,
+                80.0,
+                "2024-02-10T10:00:00Z",
+            ),
+        ];
+        let filter = OrderFilter {
+            ticker: Some("aapl_us_eq".to_string()),
+            from: NaiveDate::from_ymd_opt(2024, 2, 1),
+            to: None,
+        };
+
+        let filtered = filter.apply(orders);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].fill_price, 120.0);
+    }
+
+    #[test]
+    fn test_merge_orders_replaces_known_orders() {
+        let mut orders = vec![order(
+            "AAPL_US_EQ",
+            OrderSide::Buy,
+            1.0,
+            100.0,
+            "2024-01-10T10:00:00Z",
+        )];
+        let mut newer = order(
+            "VUSAl_EQ",
+            OrderSide::Buy,
+            1.0,
+            80.0,
+            "2024-02-10T10:00:00Z",
+        );
+        newer.id = 2;
+        let mut corrected = orders[0].clone();
+        corrected.fees = 0.0;
+
+        merge_orders(&mut orders, vec![newer, corrected]);
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0].id, 2);
+        assert_eq!(orders[1].fees, 0.0);
+    }
+
+    #[test]
+    fn test_summarize_by_ticker() {
+        let orders = vec![
+            order(
+                "AAPL_US_EQ",
+                OrderSide::Buy,
+                1.0,
+                100.0,
+                "2024-01-10T10:00:00Z",
+            ),
+            order(
+                "AAPL_US_EQ",
+                OrderSide::Buy,
+                3.0,
+                120.0,
+                "2024-03-10T10:00:00Z",
+            ),
+            order(
+                "AAPL_US_EQ",
+                OrderSide::Sell,
+                2.0,
+                130.0,
+                "2024-04-10T10:00:00Z",
+            ),
+        ];
+
+        let summary = summarize_by_ticker(&orders);
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].bought_quantity, 4.0);
+        assert_eq!(summary[0].sold_quantity, 2.0);
+        assert_eq!(summary[0].avg_buy_price, 115.0);
+        assert_eq!(summary[0].total_fees, 1.5);
+    }
+}
@@ -0,0 +1,265 @@
+// File: pie.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex as TokioMutex;
+
+use crate::models::portfolio::Position;
+use crate::services::trading212::{RequestType, Trading212Client};
+use crate::utils::settings::Config;
+
+/// Age after which the instruments of a pie are fetched again. The detail
+/// endpoint is heavily throttled, the totals are refreshed on every view.
+pub const PIE_DETAILS_TTL: Duration = Duration::minutes(30);
+
+/// What happens to the dividends paid by the instruments of a pie
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DividendCashAction {
+    Reinvest,
+    ToAccountCash,
+    Unknown(String),
+}
+
+impl From<&str> for DividendCashAction {
+    fn from(s: &str) -> Self {
+        match s.to_uppercase().as_str() {
+            "REINVEST" => DividendCashAction::Reinvest,
+            "TO_ACCOUNT_CASH" => DividendCashAction::ToAccountCash,
+            other => DividendCashAction::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for DividendCashAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DividendCashAction::Reinvest => write!(f, "Reinvest"),
+            DividendCashAction::ToAccountCash => write!(f, "To account cash"),
+            DividendCashAction::Unknown(action) => write!(f, "{}", action),
+        }
+    }
+}
+
+/// One instrument of a pie. Weights are fractions between 0 and 1 and the
+/// amounts are in the account currency.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PieInstrument {
+    pub ticker: String,
+    pub current_share: f64,
+    pub expected_share: f64,
+    pub owned_quantity: f64,
+    pub invested: f64,
+    pub value: f64,
+    pub result: f64,
+    /// Matching open position of the portfolio, if any
+    pub position: Option<Position>,
+}
+
+impl PieInstrument {
+    /// Difference between the current and the target weight in percentage points
+    pub fn drift_percent(&self) -> f64 {
+        (self.current_share - self.expected_share) * 100.0
+    }
+
+    pub fn current_price_str(&self) -> String {
+        self.position
+            .as_ref()
+            .map(|p| format!("{:.2} {}", p.current_price, p.currency))
+            .unwrap_or_else(|| "-".to_string())
+    }
+}
+
+/// A pie with its settings, totals and instruments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pie {
+    pub id: i64,
+    pub name: String,
+    pub status: String,
+    pub cash: f64,
+    pub invested: f64,
+    pub value: f64,
+    pub result: f64,
+    pub dividend_gained: f64,
+    pub dividend_reinvested: f64,
+    pub dividend_in_cash: f64,
+    pub dividend_cash_action: DividendCashAction,
+    pub instruments: Vec<PieInstrument>,
+}
+
+impl Pie {
+    pub fn result_percent(&self) -> f64 {
+        if self.invested != 0.0 {
+            self.result / self.invested * 100.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Attaches the open positions of the portfolio to the pie instruments
+    /// with the same ticker
+    pub fn link_positions(&mut self, positions: &[Position]) {
+        for instrument in &mut self.instruments {
+            instrument.position = positions
+                .iter()
+                .find(|p| p.ticker == instrument.ticker)
+                .cloned();
+        }
+    }
+}
+
+/// Details of a pie and when they were fetched
+#[derive(Debug, Clone)]
+struct CachedPie {
+    pie: Pie,
+    fetched_at: DateTime<Utc>,
+}
+
+/// Pie details of each account, kept between page views so that a pie is
+/// only fetched again once its details are older than `PIE_DETAILS_TTL`
+#[derive(Debug, Clone, Default)]
+pub struct PieCache {
+    /// Pies by id, by the API key of the account
+    accounts: Arc<TokioMutex<HashMap<String, HashMap<i64, CachedPie>>>>,
+}
+
+impl PieCache {
+    /// Every pie of the account of `config` together with its instruments.
+    /// The list is fetched each time; details that cannot be fetched again
+    /// are served from the cache. The lock is not held while fetching.
+    pub async fn pies(
+        &self,
+        config: &Config,
+        positions: &[Position],
+    ) -> Result<Vec<Pie>, anyhow::Error> {
+        let key = config.api_key.clone().unwrap_or_default();
+        let cached = self
+            .accounts
+            .lock()
+            .await
+            .get(&key)
+            .cloned()
+            .unwrap_or_default();
+
+        let trading212_client = Trading212Client::new(RequestType::Pies, config)
+            .map_err(|e| anyhow::anyhow!("Failed to initialize Trading212 client: {}", e))?;
+        let summaries = trading212_client
+            .get_pies()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch pies: {}", e))?;
+
+        let now = Utc::now();
+        let mut fetched = HashMap::new();
+        for summary in summaries {
+            let cached = cached.get(&summary.id);
+            let entry = match cached {
+                Some(entry) if now - entry.fetched_at < PIE_DETAILS_TTL => entry.clone(),
+                _ => match trading212_client.get_pie(&summary).await {
+                    Ok(pie) => CachedPie {
+                        pie,
+                        fetched_at: now,
+                    },
+                    Err(e) => match cached {
+                        Some(entry) => {
+                            eprintln!("Using cached details of pie {}: {}", summary.id, e);
+                            entry.clone()
+                        }
+                        None => return Err(anyhow::anyhow!("Failed to fetch pie details: {}", e)),
+                    },
+                },
+            };
+            fetched.insert(summary.id, (summary, entry));
+        }
+
+        // Pies that were removed are dropped from the cache
+        let mut pies: Vec<Pie> = fetched
+            .values()
+            .map(|(summary, entry)| {
+                let mut pie = entry.pie.clone();
+                summary.apply_to(&mut pie);
+                pie.link_positions(positions);
+                pie
+            })
+            .collect();
+        self.accounts.lock().await.insert(
+            key,
+            fetched
+                .into_iter()
+                .map(|(id, (_, entry))| (id, entry))
+                .collect(),
+        );
+
+        pies.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(pies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dividend_cash_action_from_api() {
+        assert_eq!(
+            DividendCashAction::from("REINVEST"),
+            DividendCashAction::Reinvest
+        );
+        assert_eq!(
+            DividendCashAction::from("TO_ACCOUNT_CASH"),
+            DividendCashAction::ToAccountCash
+        );
+        assert_eq!(
+            DividendCashAction::from("OTHER"),
+            DividendCashAction::Unknown("OTHER".to_string())
+        );
+    }
+
+    #[test]
+    fn test_drift_and_result_percent() {
+        let pie = Pie {
+            id: 1,
+            name: "Income".to_string(),
+            status: String::new(),
+            cash: 0.0,
+            invested: 200.0,
+            value: 230.0,
+            result: 30.0,
+            dividend_gained: 0.0,
+            dividend_reinvested: 0.0,
+            dividend_in_cash: 0.0,
+            dividend_cash_action: DividendCashAction::Reinvest,
+            instruments: vec![PieInstrument {
+                ticker: "VUSAl_EQ".to_string(),
+                current_share: 0.55,
+                expected_share: 0.5,
+                owned_quantity: 2.0,
+                invested: 100.0,
+                value: 126.5,
+                result: 26.5,
+                position: None,
+            }],
+        };
+
+        assert_eq!(pie.result_percent(), 15.0);
+        assert!((pie.instruments[0].drift_percent() - 5.0).abs() < 1e-9);
+        assert_eq!(pie.instruments[0].current_price_str(), "-");
+    }
+}
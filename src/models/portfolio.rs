@@ -15,21 +15,25 @@
 // financial losses, or other issues arising from the use of this software.
 //
 // USE THIS SOFTWARE AT YOUR OWN RISK.
-use std::path::Path;
-use std::process::Command;
+use std::collections::HashMap;
 use std::str::FromStr;
-use std::{collections::HashMap, fs};
 
-use chrono::{DateTime, NaiveDate, Utc};
+use chrono::{DateTime, Months, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use thiserror::Error;
 
-use super::dividend::DividendInfo;
-use crate::services::trading212::{DataIncluded, ExportRequest, RequestType, Trading212Client};
+use super::benchmark;
+use super::corporate_action::{self, CorporateAction};
+use super::data_quality::DataIssue;
+use super::dividend::{DividendGrowth, DividendInfo};
+use super::market::{MarketHours, MarketStatus};
+use crate::services::market_data::{self, MarketDataCache, Profile, MARKET_DATA_CACHE_FILE};
+use crate::services::price_history::{self, PriceHistory, PRICE_HISTORY_FILE};
+use crate::services::trading212::{AccountCash, RequestType, Trading212Client, Trading212Error};
+use crate::services::value_history::{ValueHistory, VALUE_HISTORY_FILE};
 use crate::utils::currency::CurrencyConverter;
-use crate::utils::settings::{Config, Mode};
-use crate::utils::symbol_mapper::extract_symbol;
+use crate::utils::settings::{Account, AccountKind, Config, Mode};
+use crate::utils::symbol_mapper::{extract_symbol, is_mapped};
 use crate::{services::trading212::InstrumentMetadata, utils::currency::Currency};
 
 #[derive(Debug, Error)]
@@ -41,7 +45,7 @@ pub enum PortfolioError {
     Unknown,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Position {
     pub ticker: String,
     pub yf_ticker: String,
@@ -56,9 +60,39 @@ pub struct Position {
     pub div_info: Option<DividendInfo>,
     pub div_prediction: DividendPrediction,
     pub wht: f64,
+    /// Name of the account holding the position
+    #[serde(default)]
+    pub account: String,
+    #[serde(default)]
+    pub exchange: String,
+    #[serde(default)]
+    pub working_schedule_id: Option<i64>,
+    #[serde(default)]
+    pub market_status: MarketStatus,
+    /// Problems found with the data of the position in the last update
+    #[serde(default)]
+    pub data_issues: Vec<DataIssue>,
+    /// Every dividend per share on record, oldest first
+    #[serde(default)]
+    pub dividend_history: Vec<MonthlyPayment>,
+    #[serde(default)]
+    pub dividend_growth: DividendGrowth,
+    /// Past and announced corporate actions, oldest first
+    #[serde(default)]
+    pub corporate_actions: Vec<CorporateAction>,
+    /// Rate the prices were converted to GBP with, `None` while they are in
+    /// the instrument currency
+    #[serde(default)]
+    pub gbp_rate: Option<f64>,
+    /// Sector, industry and country of the company
+    #[serde(default)]
+    pub profile: Profile,
+    /// Kind of instrument as reported by Trading 212, such as STOCK or ETF
+    #[serde(default)]
+    pub instrument_type: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MonthlyPayment {
     pub date: NaiveDate,
     pub amount: f64,
@@ -76,29 +110,99 @@ pub struct DividendPrediction {
     pub predicted_monthly_payments: Option<Vec<MonthlyPayment>>,
 }
 
+/// Cash and totals of a single account. All amounts are in GBP once the
+/// portfolio has been processed.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct AccountSummary {
+    pub name: String,
+    pub kind: AccountKind,
+    pub currency: String,
+    pub cash: AccountCash,
+    pub holdings_value: f64,
+    pub total_value: f64,
+    pub total_cost: f64,
+    pub total_ppl: f64,
+    /// Expected dividend income of the next 12 months, after withholding tax
+    pub annual_dividend: f64,
+    /// Rate the cash was converted to GBP at, for amounts of the account
+    /// that are fetched later such as the cash transactions
+    #[serde(default)]
+    pub gbp_rate: Option<f64>,
+    /// Why the positions of the account could not be fetched in the last
+    /// update, its values are missing from the totals then
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl AccountSummary {
+    pub fn new(account: &Account) -> Self {
+        Self {
+            name: account.name.clone(),
+            kind: account.kind.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Recomputes the totals from the positions held in this account
+    pub fn update_totals(&mut self, positions: &[Position]) {
+        let positions = positions.iter().filter(|p| p.account == self.name);
+        let (mut value, mut cost, mut ppl, mut dividend) = (0.0, 0.0, 0.0, 0.0);
+        for p in positions {
+            value += p.value;
+            cost += p.quantity * p.average_price;
+            ppl += p.ppl;
+            dividend += p
+                .div_info
+                .as_ref()
+                .map(|d| d.annual_income_after_wht)
+                .unwrap_or(0.0);
+        }
+        self.holdings_value = value;
+        self.total_cost = cost;
+        self.total_ppl = ppl;
+        self.annual_dividend = dividend;
+        self.total_value = value + self.cash.uninvested();
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Portfolio {
     pub positions: Vec<Position>,
+    pub cash: AccountCash,
+    /// Totals of each account, the portfolio itself is the consolidated view
+    pub accounts: Vec<AccountSummary>,
+    pub holdings_value: f64,
     pub total_value: f64,
     pub total_cost: f64,
     pub total_ppl: f64,
     pub total_ppl_percent: f64,
     pub last_updated: DateTime<Utc>,
     pub update_count: i128,
+    pub market_hours: MarketHours,
 }
 
 impl Portfolio {
     pub async fn init(&mut self, config: &Config) -> Result<(), anyhow::Error> {
         // Check if we're in Demo mode
         if config.mode == Mode::Demo {
+            let mut account = AccountSummary::new(&Account {
+                name: Mode::Demo.to_string(),
+                ..Account::default()
+            });
+
             // Try to load from saved file
             if let Ok(file) = std::fs::File::open("demo_data/demo_positions.json") {
                 let reader = std::io::BufReader::new(file);
-                match serde_json::from_reader(reader) {
+                match serde_json::from_reader::<_, Vec<Position>>(reader) {
                     Ok(positions) => {
                         println!("Loaded positions from demo_positions.json");
-                        self.positions = positions;
-                        return Ok(());
+                        self.positions = positions
+                            .into_iter()
+                            .map(|p| Position {
+                                account: account.name.clone(),
+                                ..p
+                            })
+                            .collect();
                     }
                     Err(e) => {
                         println!("Failed to parse demo positions data: {}", e);
@@ -108,19 +212,92 @@ impl Portfolio {
             } else {
                 return Err(anyhow::anyhow!("No demo positions data available"));
             }
+
+            // Cash is optional in Demo mode, the portfolio is still usable without it
+            if let Ok(file) = std::fs::File::open("demo_data/demo_cash.json") {
+                let reader = std::io::BufReader::new(file);
+                match serde_json::from_reader(reader) {
+                    Ok(cash) => {
+                        println!("Loaded account cash from demo_cash.json");
+                        account.cash = cash;
+                        account.currency = "GBP".to_string();
+                    }
+                    Err(e) => println!("Failed to parse demo cash data: {}", e),
+                }
+            }
+            self.accounts.push(account);
         } else {
-            // Live mode - proceed with Trading212 API
-            let trading212_client =
-                Trading212Client::new(RequestType::Portfolio, config).map_err(|e| {
-                    eprintln!("Trading 212 API error: client initialization failed: {}", e);
-                    e
-                })?;
-
-            // Fetch open positions
-            self.positions = trading212_client.get_open_positions().await.map_err(|e| {
-                eprintln!("Trading 212 API error: failed to get open positions: {}", e);
-                e
-            })?;
+            // Live mode - proceed with Trading212 API, one account at a time
+            let accounts = config.live_accounts();
+            if accounts.is_empty() {
+                eprintln!("Trading 212 API error: no account is configured");
+                return Err(Trading212Error::MissingApiKey.into());
+            }
+
+            let mut first_error = None;
+            for account in &accounts {
+                let account_config = config.for_account(account);
+                let mut summary = AccountSummary::new(account);
+
+                // Fetch open positions. An account that fails is shown as
+                // failed, the other accounts are still updated.
+                let positions = async {
+                    Trading212Client::new(RequestType::Portfolio, &account_config)?
+                        .get_open_positions()
+                        .await
+                }
+                .await;
+                let positions = match positions {
+                    Ok(positions) => positions,
+                    Err(e) => {
+                        eprintln!(
+                            "Trading 212 API error: failed to get open positions of {}: {}",
+                            account.name, e
+                        );
+                        summary.error = Some(e.to_string());
+                        self.accounts.push(summary);
+                        first_error.get_or_insert(e);
+                        continue;
+                    }
+                };
+                self.positions
+                    .extend(positions.into_iter().map(|p| Position {
+                        account: account.name.clone(),
+                        ..p
+                    }));
+
+                // Fetch account currency and cash balances. A failure here should not
+                // prevent the positions from being shown, so only log the error.
+                match Trading212Client::new(RequestType::AccountInfo, &account_config) {
+                    Ok(client) => match client.get_account_info().await {
+                        Ok(info) => summary.currency = info.currency_code,
+                        Err(e) => {
+                            eprintln!("Trading 212 API error: failed to get account info: {}", e)
+                        }
+                    },
+                    Err(e) => {
+                        eprintln!("Trading 212 API error: client initialization failed: {}", e)
+                    }
+                }
+                match Trading212Client::new(RequestType::AccountCash, &account_config) {
+                    Ok(client) => match client.get_account_cash().await {
+                        Ok(cash) => summary.cash = cash,
+                        Err(e) => {
+                            eprintln!("Trading 212 API error: failed to get account cash: {}", e)
+                        }
+                    },
+                    Err(e) => {
+                        eprintln!("Trading 212 API error: client initialization failed: {}", e)
+                    }
+                }
+
+                self.accounts.push(summary);
+            }
+            if self.accounts.iter().all(|a| a.error.is_some()) {
+                if let Some(e) = first_error {
+                    return Err(e.into());
+                }
+            }
 
             #[cfg(debug_assertions)]
             {
@@ -133,6 +310,15 @@ impl Portfolio {
                         println!("Saved positions data to demo_positions.json");
                     }
                 }
+                if let Ok(file) = std::fs::File::create("demo_cash.json") {
+                    let writer = std::io::BufWriter::new(file);
+                    let cash = AccountCash::sum(self.accounts.iter().map(|a| &a.cash));
+                    if let Err(e) = serde_json::to_writer_pretty(writer, &cash) {
+                        eprintln!("Failed to save cash data: {}", e);
+                    } else {
+                        println!("Saved cash data to demo_cash.json");
+                    }
+                }
             }
         }
 
@@ -143,7 +329,9 @@ impl Portfolio {
         &mut self,
         config: &Config,
         converter: CurrencyConverter,
-        instrument_metadata: Vec<InstrumentMetadata>,
+        instrument_metadata: &[InstrumentMetadata],
+        market_hours: MarketHours,
+        force_market_data: bool,
     ) -> Result<(), anyhow::Error> {
         if self.positions.is_empty() {
             println!("No positions are available!");
@@ -152,14 +340,22 @@ impl Portfolio {
 
         let meta_data_lookup: HashMap<_, _> = instrument_metadata
             .iter()
-            .map(|inst| (inst.ticker.clone(), inst.currency_code.clone()))
+            .map(|inst| (inst.ticker.clone(), inst))
             .collect();
         // Update vec2 based on the lookup map
         for inst in &mut self.positions {
-            if let Some(code) = meta_data_lookup.get(&inst.ticker) {
-                inst.currency = (*code).clone();
+            if let Some(meta) = meta_data_lookup.get(&inst.ticker) {
+                inst.currency = meta.currency_code.clone();
+                inst.working_schedule_id = meta.working_schedule_id;
+                inst.instrument_type = meta.instrument_type.clone();
             }
+            inst.exchange = market_hours
+                .exchange_name(inst.working_schedule_id)
+                .unwrap_or_default()
+                .to_string();
         }
+        self.market_hours = market_hours;
+        self.update_market_status(Utc::now());
         let yfinance_tickers = self
             .positions
             .iter_mut()
@@ -167,81 +363,94 @@ impl Portfolio {
                 let result = extract_symbol(p.ticker.as_str());
                 p.yf_ticker = result.1.yf_ticker.clone();
                 p.wht = result.1.tax.into();
+                p.data_issues.clear();
+                p.gbp_rate = None;
+                if !is_mapped(&p.ticker) {
+                    p.data_issues.push(DataIssue::UnmappedSymbol {
+                        guessed: p.yf_ticker.clone(),
+                    });
+                }
                 result.1.yf_ticker
             })
             .collect::<Vec<_>>();
 
         println!("{:?}", yfinance_tickers);
 
-        let mut cache_file = "output.json";
-        if config.mode == Mode::Demo {
-            cache_file = "demo_data/output.json"
-        }
-        let json_str = if Path::new(cache_file).exists() {
-            // ✅ Read from cache
-            println!("Reading from cache...");
-            fs::read_to_string(cache_file)?
+        let now = Utc::now();
+        let provider = market_data::provider(config);
+        let mut cache = MarketDataCache::default();
+        let market_data = if config.mode == Mode::Demo {
+            provider.fetch(&yfinance_tickers).await?
         } else {
-            println!("Fetching details form Yfinance...");
-            let output = Command::new("python3")
-                .arg("stock_info.py")
-                .arg(yfinance_tickers.join(","))
-                .output()
-                .expect("Failed to run Python script");
-            // Check if the Python script ran successfully
-            if !output.status.success() {
-                eprintln!("Python script failed to run.");
-                eprintln!("Exit code: {:?}", output.status.code());
-                eprintln!("Stderr:\n{}", String::from_utf8_lossy(&output.stderr));
+            // Only symbols that are new or whose data has expired are fetched,
+            // unless a refresh of everything was asked for
+            cache = MarketDataCache::load(MARKET_DATA_CACHE_FILE);
+            if force_market_data {
+                cache.expire_all();
             }
-            let json_output = String::from_utf8_lossy(&output.stdout).to_string();
-            fs::write(cache_file, &json_output)?; // ✅ Save to file
-            json_output
+            cache
+                .refresh(
+                    provider.as_ref(),
+                    &market_data::source_key(&config.market_data),
+                    &yfinance_tickers,
+                    config.market_data_ttl,
+                    now,
+                )
+                .await
         };
 
-        let parsed: Value = serde_json::from_str(&json_str).unwrap();
         for p in &mut self.positions {
-            match parsed.get(p.yf_ticker.clone()) {
-                Some(info) => {
-                    let yield_opt = info.get("dividendYield").and_then(|v| v.as_f64());
-                    let mut rate_opt = info.get("dividendRate").and_then(|v| v.as_f64());
-
-                    p.div_prediction.last_4_dividends_dates = info
-                        .get("last_4_dividends")
-                        .and_then(|v| v.as_object())
-                        .map(|obj| {
-                            obj.iter()
-                                .filter_map(|(date_str, value)| {
-                                    NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
-                                        .ok()
-                                        .and_then(|date| {
-                                            value
-                                                .as_f64()
-                                                .map(|v| MonthlyPayment { date, amount: v })
-                                        })
-                                })
-                                .collect()
+            match market_data.data.get(&p.yf_ticker) {
+                Some(data) => {
+                    if let Some(entry) =
+                        cache.stale_entry(&p.yf_ticker, config.market_data_ttl, now)
+                    {
+                        p.data_issues.push(DataIssue::StaleData {
+                            fetched_at: entry.fetched_at,
                         });
+                    }
 
-                    p.div_prediction.next_payment_date = info.get("dividendDate").and_then(|v| {
-                        // dividendDate is always integer (timestamp)
-                        v.as_i64()
-                            .and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0))
-                    });
-
-                    p.div_prediction.next_exdate = info.get("exDividendDate").and_then(|v| {
-                        // exDividendDate is always integer (timestamp)
-                        v.as_i64()
-                            .and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0))
-                    });
-                    p.div_prediction.payment_amount_per_share = info
-                        .get("corporateActions")
-                        .and_then(|arr| arr.get(0))
-                        .and_then(|entry| entry.get("meta"))
-                        .and_then(|entry| entry.get("amount"))
-                        .and_then(|a| {
-                            a.as_f64()
-                                .or_else(|| a.as_str().and_then(|s| s.parse::<f64>().ok()))
+                    let yield_opt = data.dividends.yield_percent;
+                    let mut rate_opt = data.dividends.rate;
+
+                    p.profile = data.profile.clone();
+
+                    let today = now.date_naive();
+                    p.corporate_actions = data.corporate_actions.clone();
+                    // Yahoo Finance adjusts dividends for splits itself, so
+                    // only the other actions are applied to them
+                    let dividend_actions: Vec<CorporateAction> = p
+                        .corporate_actions
+                        .iter()
+                        .filter(|a| data.dividends.as_paid || !a.is_split())
+                        .cloned()
+                        .collect();
+                    p.div_prediction.last_4_dividends_dates =
+                        Some(corporate_action::adjust_dividends(
+                            &data.dividends.recent,
+                            &dividend_actions,
+                            today,
+                        ));
+                    p.dividend_history = corporate_action::adjust_dividends(
+                        &data.dividends.history,
+                        &dividend_actions,
+                        today,
+                    );
+                    p.dividend_growth = DividendGrowth::from_history(&p.dividend_history, today);
+                    p.div_prediction.next_payment_date = data.dividends.payment_date;
+                    p.div_prediction.next_exdate = data.dividends.ex_date;
+                    // The announced amount is per share held on the ex-date,
+                    // which differs from the current shares across a split
+                    p.div_prediction.payment_amount_per_share =
+                        data.dividends.next_amount.map(|amount| {
+                            data.dividends.ex_date.map_or(amount, |ex_date| {
+                                amount
+                                    * corporate_action::per_share_factor(
+                                        &p.corporate_actions,
+                                        ex_date.date_naive(),
+                                        today,
+                                    )
+                            })
                         });
 
                     if p.div_prediction.payment_amount_per_share.is_some() {
@@ -264,6 +473,7 @@ impl Portfolio {
                         p.average_price /= 100.0;
                         p.current_price /= 100.0;
                         p.value /= 100.0;
+                        p.gbp_rate = Some(0.01);
                     } else {
                         let target_currency = Currency::GBP;
                         let stock_currency =
@@ -273,6 +483,8 @@ impl Portfolio {
                                 "Add support for currency = {:?} stock = {}",
                                 p.currency, p.yf_ticker
                             );
+                            p.data_issues
+                                .push(DataIssue::UnsupportedCurrency(p.currency.clone()));
                         } else {
                             let conv_fact = converter
                                 .get_conversion_factor(stock_currency, target_currency)
@@ -281,6 +493,7 @@ impl Portfolio {
                             p.average_price *= conv_fact;
                             p.current_price *= conv_fact;
                             p.value *= conv_fact;
+                            p.gbp_rate = Some(conv_fact);
                             rate_opt = rate_opt.map(|rate| rate * conv_fact);
                         }
 
@@ -297,11 +510,97 @@ impl Portfolio {
                 }
                 None => {
                     println!("{} missing in response", p.yf_ticker);
+                    let error = market_data
+                        .errors
+                        .get(&p.yf_ticker)
+                        .cloned()
+                        .unwrap_or_else(|| "No market data found".to_string());
+                    p.data_issues.push(DataIssue::MarketDataError(error));
                 }
             }
         }
 
-        self.total_value = self.positions.iter().map(|p| p.value).sum::<f64>();
+        if config.mode != Mode::Demo {
+            // Only the days since the last stored price are fetched
+            let today = now.date_naive();
+            let actions: HashMap<String, Vec<CorporateAction>> = self
+                .positions
+                .iter()
+                .map(|p| (p.yf_ticker.clone(), p.corporate_actions.clone()))
+                .collect();
+            let source = market_data::source_key(&config.market_data);
+            let mut history = PriceHistory::load(PRICE_HISTORY_FILE);
+            history
+                .refresh(
+                    provider.as_ref(),
+                    &source,
+                    &yfinance_tickers,
+                    &actions,
+                    price_history::default_start(today),
+                    today,
+                )
+                .await;
+            // Contributions are compared with the benchmarks as far back as
+            // the exported history reaches, converted at the rates of the
+            // currencies the benchmarks are quoted in
+            let benchmarks_start = today
+                .checked_sub_months(Months::new(12 * config.export.history_years))
+                .unwrap_or(today)
+                .min(price_history::default_start(today));
+            let quotes = cache
+                .refresh(
+                    provider.as_ref(),
+                    &source,
+                    &config.benchmarks,
+                    config.market_data_ttl,
+                    now,
+                )
+                .await;
+            let mut symbols = config.benchmarks.clone();
+            symbols.extend(
+                quotes
+                    .data
+                    .values()
+                    .filter_map(|data| benchmark::fx_symbol(data.quote.currency.as_deref()?)),
+            );
+            history
+                .refresh(
+                    provider.as_ref(),
+                    &source,
+                    &symbols,
+                    &HashMap::new(),
+                    benchmarks_start,
+                    today,
+                )
+                .await;
+        }
+
+        // Cash is reported in the account currency, bring it to GBP like the positions
+        for account in &mut self.accounts {
+            let cash_currency = if account.currency.is_empty() {
+                Currency::GBP
+            } else {
+                Currency::from_str(&account.currency).unwrap_or(Currency::UnSupported)
+            };
+            if cash_currency == Currency::UnSupported {
+                println!(
+                    "Add support for account currency = {:?} (account {})",
+                    account.currency, account.name
+                );
+            } else {
+                let conv_fact = converter
+                    .get_conversion_factor(cash_currency, Currency::GBP)
+                    .await
+                    .unwrap_or(1.00);
+                account.cash = account.cash.converted(conv_fact);
+                account.gbp_rate = Some(conv_fact);
+            }
+            account.update_totals(&self.positions);
+        }
+        self.cash = AccountCash::sum(self.accounts.iter().map(|a| &a.cash));
+
+        self.holdings_value = self.positions.iter().map(|p| p.value).sum::<f64>();
+        self.total_value = self.holdings_value + self.cash.uninvested();
 
         self.total_cost = self
             .positions
@@ -309,9 +608,66 @@ impl Portfolio {
             .map(|p| p.quantity * p.average_price)
             .sum::<f64>();
 
+        if config.mode != Mode::Demo {
+            // The benchmark comparison needs the value of earlier days
+            let mut values = ValueHistory::load(VALUE_HISTORY_FILE);
+            values.record(now.date_naive(), &self.accounts);
+            if let Err(e) = values.save() {
+                eprintln!("Failed to save value history: {}", e);
+            }
+        }
+
         self.last_updated = Utc::now();
         Ok(())
     }
+
+    /// Returns the portfolio of a single account, with its own cash and totals
+    pub fn for_account(&self, name: &str) -> Option<Portfolio> {
+        let account = self.accounts.iter().find(|a| a.name == name)?.clone();
+        Some(Portfolio {
+            positions: self
+                .positions
+                .iter()
+                .filter(|p| p.account == name)
+                .cloned()
+                .collect(),
+            cash: account.cash.clone(),
+            holdings_value: account.holdings_value,
+            total_value: account.total_value,
+            total_cost: account.total_cost,
+            total_ppl: account.total_ppl,
+            total_ppl_percent: if account.total_cost > 0.0 {
+                account.total_ppl / account.total_cost * 100.0
+            } else {
+                0.0
+            },
+            accounts: vec![account],
+            last_updated: self.last_updated,
+            update_count: self.update_count,
+            market_hours: self.market_hours.clone(),
+        })
+    }
+
+    /// Trading 212 tickers of the positions of every account
+    pub fn tickers(&self) -> Vec<String> {
+        self.positions.iter().map(|p| p.ticker.clone()).collect()
+    }
+
+    /// Recomputes whether the market of each position is open at `at`
+    pub fn update_market_status(&mut self, at: DateTime<Utc>) {
+        for p in &mut self.positions {
+            p.market_status = self.market_hours.status(p.working_schedule_id, at);
+        }
+    }
+
+    /// True only if the market of every position is known to be closed.
+    /// Positions without a schedule never block a refresh.
+    pub fn all_markets_closed(&self, at: DateTime<Utc>) -> bool {
+        !self.positions.is_empty()
+            && self.positions.iter().all(|p| {
+                self.market_hours.status(p.working_schedule_id, at) == MarketStatus::Closed
+            })
+    }
 }
 
 fn calculate_dividend(p: &mut Position, yield_opt: Option<f64>, rate_opt: Option<f64>) {
@@ -355,103 +711,3 @@ fn calculate_dividend(p: &mut Position, yield_opt: Option<f64>, rate_opt: Option
     p.div_info = Some(div_info);
     //println!("{:?}", p.div_info);
 }
-
-pub async fn download_export_if_needed(config: &Config) -> Result<(), anyhow::Error> {
-    // Check if we already have a recent export
-    if std::fs::read_dir(".")?
-        .filter_map(|entry| entry.ok())
-        .any(|entry| {
-            entry.path().is_file()
-                && entry.file_name().to_str().map_or(false, |name| {
-                    name.starts_with("export_") && name.ends_with(".csv")
-                })
-        })
-    {
-        return Ok(());
-    }
-
-    println!("No existing export found. Initiating download from Trading212...");
-
-    // Initialize Trading212 client for exports
-    let trading212_client = Trading212Client::new(RequestType::Export, config)
-        .map_err(|e| anyhow::anyhow!("Failed to initialize Trading212 client: {}", e))?;
-
-    // Calculate date range for the last year
-    let now = Utc::now();
-    let one_year_ago = now - chrono::Duration::days(365);
-
-    // Create the export request
-    let export_request = ExportRequest {
-        data_included: DataIncluded {
-            include_dividends: true,
-            include_interest: false,
-            include_orders: false,
-            include_transactions: false,
-        },
-        time_from: one_year_ago.to_rfc3339(),
-        time_to: now.to_rfc3339(),
-    };
-
-    println!(
-        "Requesting export for period: {} to {}",
-        one_year_ago.format("%Y-%m-%d"),
-        now.format("%Y-%m-%d")
-    );
-
-    // Request new export
-    let export_response = trading212_client
-        .request_export(&export_request)
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to request export: {}", e))?;
-
-    println!("Export initiated with ID: {}", export_response.report_id);
-
-    // Wait and check for export completion
-    for attempt in 1..=30 {
-        println!("Checking export status (attempt {}/30)...", attempt);
-        tokio::time::sleep(tokio::time::Duration::from_secs(15)).await;
-
-        if let Some(export_info) = trading212_client
-            .get_export_status(export_response.report_id)
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to check export status: {}", e))?
-        {
-            println!("Export status: {}", export_info.status);
-
-            match export_info.status.as_str() {
-                "Finished" => {
-                    if let Some(download_link) = &export_info.download_link {
-                        println!("Export ready! Downloading...");
-
-                        // Download the export
-                        let export_data = trading212_client
-                            .download_export(download_link)
-                            .await
-                            .map_err(|e| anyhow::anyhow!("Failed to download export: {}", e))?;
-
-                        // Save the export
-                        let filename = format!("export_{}.csv", export_info.report_id);
-                        std::fs::write(&filename, export_data)
-                            .map_err(|e| anyhow::anyhow!("Failed to save export file: {}", e))?;
-
-                        println!("Export saved to {}", filename);
-                        return Ok(());
-                    }
-                }
-                "Failed" | "Canceled" => {
-                    return Err(anyhow::anyhow!(
-                        "Export {} failed or was canceled",
-                        export_response.report_id
-                    ));
-                }
-                _ => {
-                    println!("Export still processing...");
-                }
-            }
-        } else {
-            println!("Export not found in list, waiting...");
-        }
-    }
-
-    Err(anyhow::anyhow!("Export timed out after 30 attempts"))
-}
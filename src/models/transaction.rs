@@ -0,0 +1,344 @@
+// File: transaction.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex as TokioMutex;
+
+use crate::services::trading212::{RequestType, Trading212Client};
+use crate::utils::settings::Config;
+
+/// Kind of a cash movement in the account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TransactionKind {
+    Deposit,
+    Withdrawal,
+    Fee,
+    Transfer,
+    Interest,
+    Other(String),
+}
+
+impl From<&str> for TransactionKind {
+    fn from(s: &str) -> Self {
+        match s.to_uppercase().as_str() {
+            "DEPOSIT" => TransactionKind::Deposit,
+            "WITHDRAW" | "WITHDRAWAL" => TransactionKind::Withdrawal,
+            "FEE" => TransactionKind::Fee,
+            "TRANSFER" => TransactionKind::Transfer,
+            "INTEREST" => TransactionKind::Interest,
+            other => TransactionKind::Other(other.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for TransactionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransactionKind::Deposit => write!(f, "Deposit"),
+            TransactionKind::Withdrawal => write!(f, "Withdrawal"),
+            TransactionKind::Fee => write!(f, "Fee"),
+            TransactionKind::Transfer => write!(f, "Transfer"),
+            TransactionKind::Interest => write!(f, "Interest"),
+            TransactionKind::Other(kind) => write!(f, "{}", kind),
+        }
+    }
+}
+
+/// A single cash movement. `amount` is in the account currency and is
+/// signed: money coming into the account is positive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CashTransaction {
+    pub kind: TransactionKind,
+    pub amount: f64,
+    pub reference: String,
+    pub date_time: DateTime<Utc>,
+}
+
+impl CashTransaction {
+    /// Amount that counts towards the money put into the account by the user.
+    /// Fees and interest are not contributions, they are part of the return.
+    pub fn contribution(&self) -> f64 {
+        match self.kind {
+            TransactionKind::Deposit => self.amount.abs(),
+            TransactionKind::Withdrawal => -self.amount.abs(),
+            TransactionKind::Transfer => self.amount,
+            _ => 0.0,
+        }
+    }
+
+    /// Identifies the transaction between fetches: the reference, or its
+    /// time, kind and amount when it has none
+    pub fn key(&self) -> String {
+        if self.reference.is_empty() {
+            format!(
+                "{}|{}|{}",
+                self.date_time.to_rfc3339(),
+                self.kind,
+                self.amount
+            )
+        } else {
+            self.reference.clone()
+        }
+    }
+
+    /// Returns a copy with the amount multiplied by `factor`, used to move
+    /// it from the account currency into the base currency
+    pub fn converted(self, factor: f64) -> Self {
+        Self {
+            amount: self.amount * factor,
+            ..self
+        }
+    }
+}
+
+/// Cash flow totals of a single calendar month
+#[derive(Debug, Clone, Default)]
+pub struct MonthlyCashflow {
+    pub month: String,
+    pub deposits: f64,
+    pub withdrawals: f64,
+    pub fees: f64,
+    pub interest: f64,
+    pub net_contribution: f64,
+    pub cumulative_contribution: f64,
+}
+
+/// Net money put into the account on a single day, negative when more
+/// was taken out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DailyContribution {
+    pub date: NaiveDate,
+    pub amount: f64,
+}
+
+/// Ledger of every cash movement in the account, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct CashLedger {
+    pub transactions: Vec<CashTransaction>,
+}
+
+impl CashLedger {
+    pub fn new(mut transactions: Vec<CashTransaction>) -> Self {
+        transactions.sort_by_key(|t| t.date_time);
+        Self { transactions }
+    }
+
+    /// Adds `transactions`, replacing the ones with the same key
+    pub fn merge(&mut self, transactions: Vec<CashTransaction>) {
+        let keys: HashSet<String> = transactions.iter().map(|t| t.key()).collect();
+        self.transactions.retain(|t| !keys.contains(&t.key()));
+        self.transactions.extend(transactions);
+        self.transactions.sort_by_key(|t| t.date_time);
+    }
+
+    pub fn total_deposits(&self) -> f64 {
+        self.sum_of(|t| t.kind == TransactionKind::Deposit)
+    }
+
+    pub fn total_withdrawals(&self) -> f64 {
+        self.sum_of(|t| t.kind == TransactionKind::Withdrawal)
+    }
+
+    pub fn total_fees(&self) -> f64 {
+        self.sum_of(|t| t.kind == TransactionKind::Fee)
+    }
+
+    pub fn total_interest(&self) -> f64 {
+        self.sum_of(|t| t.kind == TransactionKind::Interest)
+    }
+
+    /// The API has no interest transactions, so interest is only known when
+    /// the CSV exports include it
+    pub fn has_interest(&self) -> bool {
+        self.transactions
+            .iter()
+            .any(|t| t.kind == TransactionKind::Interest)
+    }
+
+    /// Deposits minus withdrawals, including transfers between accounts
+    pub fn net_contributions(&self) -> f64 {
+        self.transactions.iter().map(|t| t.contribution()).sum()
+    }
+
+    /// Contributions summed per day, oldest first. Days without a net
+    /// contribution are left out.
+    pub fn daily_contributions(&self) -> Vec<DailyContribution> {
+        let mut days: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+        for t in &self.transactions {
+            *days.entry(t.date_time.date_naive()).or_default() += t.contribution();
+        }
+        days.into_iter()
+            .filter(|(_, amount)| *amount != 0.0)
+            .map(|(date, amount)| DailyContribution { date, amount })
+            .collect()
+    }
+
+    fn sum_of(&self, pred: impl Fn(&CashTransaction) -> bool) -> f64 {
+        self.transactions
+            .iter()
+            .filter(|t| pred(t))
+            .map(|t| t.amount.abs())
+            .sum()
+    }
+
+    /// Monthly totals in chronological order, with a running total of the
+    /// net contributions
+    pub fn monthly_summary(&self) -> Vec<MonthlyCashflow> {
+        let mut months: BTreeMap<NaiveDate, MonthlyCashflow> = BTreeMap::new();
+        for t in &self.transactions {
+            let date = t.date_time.date_naive();
+            let key = NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap_or(date);
+            let entry = months.entry(key).or_insert_with(|| MonthlyCashflow {
+                month: key.format("%b %Y").to_string(),
+                ..Default::default()
+            });
+            match t.kind {
+                TransactionKind::Deposit => entry.deposits += t.amount.abs(),
+                TransactionKind::Withdrawal => entry.withdrawals += t.amount.abs(),
+                TransactionKind::Fee => entry.fees += t.amount.abs(),
+                TransactionKind::Interest => entry.interest += t.amount.abs(),
+                _ => {}
+            }
+            entry.net_contribution += t.contribution();
+        }
+
+        let mut cumulative = 0.0;
+        months
+            .into_values()
+            .map(|mut m| {
+                cumulative += m.net_contribution;
+                m.cumulative_contribution = cumulative;
+                m
+            })
+            .collect()
+    }
+}
+
+/// Cash transactions of each account, kept between page views so that only
+/// the transactions made since the last view are fetched again
+#[derive(Debug, Clone, Default)]
+pub struct CashLedgerCache {
+    /// Ledgers by the API key of the account
+    accounts: Arc<TokioMutex<HashMap<String, CashLedger>>>,
+}
+
+impl CashLedgerCache {
+    /// Cash ledger of the account of `config`. The lock is not held while
+    /// the new transactions are fetched.
+    pub async fn ledger(&self, config: &Config) -> Result<CashLedger, anyhow::Error> {
+        let key = config.api_key.clone().unwrap_or_default();
+        let known: HashSet<String> = self
+            .accounts
+            .lock()
+            .await
+            .get(&key)
+            .map(|ledger| ledger.transactions.iter().map(|t| t.key()).collect())
+            .unwrap_or_default();
+
+        let trading212_client = Trading212Client::new(RequestType::Transactions, config)
+            .map_err(|e| anyhow::anyhow!("Failed to initialize Trading212 client: {}", e))?;
+        let new_transactions = trading212_client
+            .get_transactions_since(&known)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch transactions: {}", e))?;
+
+        let mut accounts = self.accounts.lock().await;
+        let ledger = accounts.entry(key).or_default();
+        ledger.merge(new_transactions);
+        Ok(ledger.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(kind: &str, amount: f64, date: &str) -> CashTransaction {
+        CashTransaction {
+            kind: TransactionKind::from(kind),
+            amount,
+            reference: String::new(),
+            date_time: date.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_transaction_kind_from_api_type() {
+        assert_eq!(
+            TransactionKind::from("WITHDRAW"),
+            TransactionKind::Withdrawal
+        );
+        assert_eq!(TransactionKind::from("deposit"), TransactionKind::Deposit);
+        assert_eq!(
+            TransactionKind::from("BONUS"),
+            TransactionKind::Other("BONUS".to_string())
+        );
+    }
+
+    #[test]
+    fn test_monthly_summary_and_contributions() {
+        let ledger = CashLedger::new(vec![
+            transaction("DEPOSIT", 500.0, "2024-02-03T09:00:00Z"),
+            transaction("DEPOSIT", 1000.0, "2024-01-05T09:00:00Z"),
+            transaction("WITHDRAW", -200.0, "2024-02-20T09:00:00Z"),
+            transaction("FEE", -1.5, "2024-02-21T09:00:00Z"),
+        ]);
+
+        assert_eq!(ledger.net_contributions(), 1300.0);
+        assert_eq!(ledger.total_fees(), 1.5);
+
+        let months = ledger.monthly_summary();
+        assert_eq!(months.len(), 2);
+        assert_eq!(months[0].month, "Jan 2024");
+        assert_eq!(months[0].cumulative_contribution, 1000.0);
+        assert_eq!(months[1].deposits, 500.0);
+        assert_eq!(months[1].withdrawals, 200.0);
+        assert_eq!(months[1].net_contribution, 300.0);
+        assert_eq!(months[1].cumulative_contribution, 1300.0);
+
+        let converted = CashLedger::new(
+            ledger
+                .transactions
+                .into_iter()
+                .map(|t| t.converted(0.5))
+                .collect(),
+        );
+        assert_eq!(converted.net_contributions(), 650.0);
+    }
+
+    #[test]
+    fn test_merge_replaces_known_transactions() {
+        let mut deposit = transaction("DEPOSIT", 500.0, "2024-02-03T09:00:00Z");
+        deposit.reference = "DEP-1".to_string();
+        let mut ledger = CashLedger::new(vec![
+            deposit.clone(),
+            transaction("FEE", -1.5, "2024-02-21T09:00:00Z"),
+        ]);
+        ledger.merge(vec![
+            deposit,
+            transaction("FEE", -1.5, "2024-02-21T09:00:00Z"),
+            transaction("DEPOSIT", 100.0, "2024-01-05T09:00:00Z"),
+        ]);
+        assert_eq!(ledger.transactions.len(), 3);
+        assert_eq!(ledger.transactions[0].amount, 100.0);
+    }
+}
@@ -0,0 +1,1373 @@
+// File: export.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex as TokioMutex, Notify};
+
+use crate::models::history::{parse_export, parse_export_time, ExportParseError, HistoryEvent};
+use crate::models::transaction::TransactionKind;
+use crate::services::trading212::{DataIncluded, ExportRequest, RequestType, Trading212Client};
+use crate::utils::settings::{Account, Config, ExportSettings, Mode};
+
+/// File the export jobs are persisted to
+pub const EXPORT_JOBS_FILE: &str = "export_jobs.json";
+
+/// Directory holding the export files, with one subdirectory per account
+const EXPORTS_DIR: &str = "exports";
+
+/// Directory next to the jobs file holding downloaded exports until their
+/// batch is merged
+const EXPORT_PARTS_DIR: &str = "export_parts";
+
+/// Delay between two status checks of the active exports
+const EXPORT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Exports that are not finished after this long are given up on
+const EXPORT_JOB_TIMEOUT: Duration = Duration::hours(2);
+
+/// Age of the newest export after which the gap since is exported
+const EXPORT_MAX_AGE: Duration = Duration::days(1);
+
+/// Number of batches shown on the status page
+const MAX_KEPT_BATCHES: usize = 10;
+
+impl From<&ExportSettings> for DataIncluded {
+    fn from(settings: &ExportSettings) -> Self {
+        DataIncluded {
+            include_dividends: settings.include_dividends,
+            include_interest: settings.include_interest,
+            include_orders: settings.include_orders,
+            include_transactions: settings.include_transactions,
+        }
+    }
+}
+
+/// Letters of the data sets in export file names, in the order of
+/// `DataIncluded::flags`
+const DATA_SET_CODES: [char; 4] = ['D', 'I', 'O', 'T'];
+
+impl DataIncluded {
+    fn flags(&self) -> [bool; 4] {
+        [
+            self.include_dividends,
+            self.include_interest,
+            self.include_orders,
+            self.include_transactions,
+        ]
+    }
+
+    /// Letters of the included data sets, e.g. `DO` for dividends and
+    /// orders
+    pub fn code(&self) -> String {
+        DATA_SET_CODES
+            .iter()
+            .zip(self.flags())
+            .filter(|(_, included)| *included)
+            .map(|(code, _)| *code)
+            .collect()
+    }
+
+    /// Reads the data sets from the letters written by `code`
+    pub fn from_code(code: &str) -> Option<Self> {
+        if code.is_empty() || !code.chars().all(|c| DATA_SET_CODES.contains(&c)) {
+            return None;
+        }
+        Some(DataIncluded {
+            include_dividends: code.contains('D'),
+            include_interest: code.contains('I'),
+            include_orders: code.contains('O'),
+            include_transactions: code.contains('T'),
+        })
+    }
+
+    /// True if every data set of `other` is included as well
+    pub fn covers(&self, other: &DataIncluded) -> bool {
+        self.flags()
+            .iter()
+            .zip(other.flags())
+            .all(|(own, other)| *own || !other)
+    }
+}
+
+/// Splits `from..to` into consecutive ranges of at most `chunk_days`,
+/// oldest first. Each range starts where the previous one ended.
+pub fn export_ranges(
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    chunk_days: u32,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let chunk = Duration::days(chunk_days.max(1) as i64);
+    let mut ranges = Vec::new();
+    let mut start = from;
+    while start < to {
+        let end = (start + chunk).min(to);
+        ranges.push((start, end));
+        start = end;
+    }
+    ranges
+}
+
+/// Merges several export CSVs into one
+///
+/// Trading 212 leaves out the columns that are empty in an export, so the
+/// files of different periods can have different headers. The merged file
+/// has every column seen in any of them, in order of first appearance.
+/// Rows found in more than one file, e.g. a transaction on the boundary of
+/// two ranges, are kept once: by transaction ID where the export has one,
+/// otherwise by its time, action, ticker and total (see `row_key`). A row
+/// of a later file replaces an earlier one with the same key, since
+/// Trading 212 corrects rows in later exports.
+pub fn merge_exports(exports: &[String]) -> Result<String, anyhow::Error> {
+    let mut headers: Vec<String> = Vec::new();
+    let mut files = Vec::new();
+    for export in exports {
+        let mut rdr = csv::Reader::from_reader(export.as_bytes());
+        let file_headers: Vec<String> = rdr.headers()?.iter().map(String::from).collect();
+        for header in &file_headers {
+            if !headers.contains(header) {
+                headers.push(header.clone());
+            }
+        }
+        let records = rdr.records().collect::<Result<Vec<_>, _>>()?;
+        files.push((file_headers, records));
+    }
+
+    // Position of each row in `rows`, so a later row can take its place
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut rows: Vec<Vec<&str>> = Vec::new();
+    for (file_headers, records) in &files {
+        for record in records {
+            let row: Vec<&str> = headers
+                .iter()
+                .map(|h| {
+                    file_headers
+                        .iter()
+                        .position(|fh| fh == h)
+                        .and_then(|i| record.get(i))
+                        .unwrap_or("")
+                })
+                .collect();
+            let key = row_key(&headers, &row);
+            match seen.get(&key) {
+                Some(&index) => rows[index] = row,
+                None => {
+                    seen.insert(key, rows.len());
+                    rows.push(row);
+                }
+            }
+        }
+    }
+
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    wtr.write_record(&headers)?;
+    for row in rows {
+        wtr.write_record(&row)?;
+    }
+    Ok(String::from_utf8(wtr.into_inner()?)?)
+}
+
+/// Key a row of `merge_exports` is deduplicated by. Rows without an ID are
+/// identified by their parsed time, action, ticker and total, so the same
+/// row formatted differently, or with other optional columns filled, still
+/// matches.
+fn row_key(headers: &[String], row: &[&str]) -> String {
+    let field = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .map_or("", |i| row[i].trim())
+    };
+    let id = field("ID");
+    if !id.is_empty() {
+        return format!("ID:{}", id);
+    }
+
+    let time = parse_export_time(field("Time"))
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_else(|| field("Time").to_string());
+    let total = field("Total")
+        .parse::<f64>()
+        .map(|t| t.to_string())
+        .unwrap_or_else(|_| field("Total").to_string());
+    format!(
+        "{}\u{1f}{}\u{1f}{}\u{1f}{}",
+        time,
+        field("Action").to_lowercase(),
+        field("Ticker"),
+        total
+    )
+}
+
+/// Finished export files in `dir`, none when it does not exist yet
+pub fn export_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("export_") && name.ends_with(".csv"))
+        })
+        .collect())
+}
+
+/// Ranges that have to be exported to cover `history_from..now`, given the
+/// periods covered by the existing exports, oldest first and not
+/// overlapping as returned by `covered_periods`
+///
+/// Besides the gap since the last export, this backfills the start of the
+/// history when it was extended in the settings and any gap between two
+/// covered periods. The last export is only refreshed once it is older than
+/// `EXPORT_MAX_AGE`.
+pub fn missing_ranges(
+    covered: &[(NaiveDate, NaiveDate)],
+    history_from: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    if covered.is_empty() {
+        return vec![(history_from, now)];
+    }
+    let start_of = |date: NaiveDate| date.and_time(NaiveTime::MIN).and_utc();
+
+    let mut ranges = Vec::new();
+    let mut exported_to = history_from;
+    for &(from, to) in covered {
+        if exported_to < start_of(from) {
+            ranges.push((exported_to, start_of(from)));
+        }
+        exported_to = exported_to.max(start_of(to));
+    }
+    if now - exported_to > EXPORT_MAX_AGE {
+        ranges.push((exported_to, now));
+    }
+    ranges
+}
+
+/// An export file, the period it covers and the data sets it includes
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportFile {
+    pub path: PathBuf,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// `None` for files of older versions, which did not record it
+    pub data: Option<DataIncluded>,
+}
+
+impl ExportFile {
+    /// Name of the file covering `from..=to` with the `data` sets
+    pub fn name(from: NaiveDate, to: NaiveDate, data: &DataIncluded) -> String {
+        format!(
+            "export_{}_{}_{}.csv",
+            from.format("%Y%m%d"),
+            to.format("%Y%m%d"),
+            data.code()
+        )
+    }
+
+    /// Reads the period and data sets from an `export_<from>_<to>_<data>.csv`
+    /// name. Older versions left out the data sets, and before that named
+    /// the files after the report id, so their period is taken from the
+    /// first and last row instead.
+    pub fn open(path: PathBuf) -> Option<Self> {
+        let stem = path.file_stem()?.to_str()?.strip_prefix("export_")?;
+        let parse = |s: &str| NaiveDate::parse_from_str(s, "%Y%m%d").ok();
+        let parts: Vec<&str> = stem.split('_').collect();
+        let (from, to, data) = match parts[..] {
+            [from, to, data] => (
+                parse(from)?,
+                parse(to)?,
+                Some(DataIncluded::from_code(data)?),
+            ),
+            [from, to] => (parse(from)?, parse(to)?, None),
+            [_] => {
+                let (from, to) = covered_period(&std::fs::read_to_string(&path).ok()?)?;
+                (from, to, None)
+            }
+            _ => return None,
+        };
+        Some(Self {
+            path,
+            from,
+            to,
+            data,
+        })
+    }
+}
+
+/// First and last day of the rows of an export
+fn covered_period(csv_content: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let mut rdr = csv::Reader::from_reader(csv_content.as_bytes());
+    let time_idx = rdr.headers().ok()?.iter().position(|h| h == "Time")?;
+    let dates: Vec<NaiveDate> = rdr
+        .records()
+        .filter_map(|r| r.ok())
+        .filter_map(|r| {
+            let time = r.get(time_idx)?;
+            NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+                .or_else(|_| NaiveDateTime::parse_from_str(time, "%d/%m/%Y %H:%M"))
+                .ok()
+                .map(|dt| dt.date())
+        })
+        .collect();
+    Some((*dates.iter().min()?, *dates.iter().max()?))
+}
+
+/// Export files in `dir` with a known period, oldest first
+pub fn covered_exports(dir: &Path) -> std::io::Result<Vec<ExportFile>> {
+    let mut files: Vec<ExportFile> = export_files(dir)?
+        .into_iter()
+        .filter_map(ExportFile::open)
+        .collect();
+    files.sort_by_key(|f| (f.from, f.to));
+    Ok(files)
+}
+
+/// Periods covered by the `files` that include every data set of `data`,
+/// oldest first. Periods that overlap or touch are joined, gaps between
+/// files are kept.
+pub fn covered_periods(files: &[ExportFile], data: &DataIncluded) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut periods: Vec<(NaiveDate, NaiveDate)> = files
+        .iter()
+        .filter(|f| f.data.as_ref().is_some_and(|d| d.covers(data)))
+        .map(|f| (f.from, f.to))
+        .collect();
+    periods.sort();
+
+    let mut joined: Vec<(NaiveDate, NaiveDate)> = Vec::new();
+    for (from, to) in periods {
+        match joined.last_mut() {
+            Some(last) if from <= last.1 => last.1 = last.1.max(to),
+            _ => joined.push((from, to)),
+        }
+    }
+    joined
+}
+
+/// Data sets the rows of an export belong to. Uploaded files do not say
+/// which data sets were requested, so only the ones with rows are known.
+pub fn data_included_of(events: &[HistoryEvent]) -> DataIncluded {
+    let mut data = DataIncluded {
+        include_dividends: false,
+        include_interest: false,
+        include_orders: false,
+        include_transactions: false,
+    };
+    for event in events {
+        match event {
+            HistoryEvent::Dividend(_) => data.include_dividends = true,
+            HistoryEvent::Trade(_) => data.include_orders = true,
+            HistoryEvent::Cash(t) if t.kind == TransactionKind::Interest => {
+                data.include_interest = true
+            }
+            _ => data.include_transactions = true,
+        }
+    }
+    data
+}
+
+/// State of an export job. Trading 212 also reports `Running` and
+/// `Canceled`, which count as `Processing` and `Failed`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ExportJobStatus {
+    Queued,
+    Processing,
+    Finished,
+    Failed,
+}
+
+impl ExportJobStatus {
+    fn from_api(status: &str) -> Self {
+        match status {
+            "Queued" => ExportJobStatus::Queued,
+            "Finished" => ExportJobStatus::Finished,
+            "Failed" | "Canceled" => ExportJobStatus::Failed,
+            _ => ExportJobStatus::Processing,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self, ExportJobStatus::Queued | ExportJobStatus::Processing)
+    }
+}
+
+impl std::fmt::Display for ExportJobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportJobStatus::Queued => write!(f, "Queued"),
+            ExportJobStatus::Processing => write!(f, "Processing"),
+            ExportJobStatus::Finished => write!(f, "Finished"),
+            ExportJobStatus::Failed => write!(f, "Failed"),
+        }
+    }
+}
+
+/// One export request covering a single date range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportJob {
+    /// Jobs requested together share a batch and are merged into one file
+    pub batch: i64,
+    /// Name of the account the export is requested for
+    pub account: String,
+    pub time_from: DateTime<Utc>,
+    pub time_to: DateTime<Utc>,
+    pub data_included: DataIncluded,
+    /// Id assigned by Trading 212, `None` until the export is requested
+    pub report_id: Option<i64>,
+    pub status: ExportJobStatus,
+    /// Last error, including transient ones that are retried
+    pub error: Option<String>,
+    /// Downloaded CSV waiting to be merged with the rest of the batch
+    pub part: Option<PathBuf>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ExportJob {
+    /// Creates one queued job per chunk of the given ranges
+    fn batch(
+        config: &Config,
+        account: &str,
+        ranges: &[(DateTime<Utc>, DateTime<Utc>)],
+        now: DateTime<Utc>,
+    ) -> Vec<ExportJob> {
+        let settings = &config.export;
+        ranges
+            .iter()
+            .flat_map(|&(from, to)| export_ranges(from, to, settings.chunk_days))
+            .map(|(time_from, time_to)| ExportJob {
+                batch: now.timestamp(),
+                account: account.to_string(),
+                time_from,
+                time_to,
+                data_included: DataIncluded::from(settings),
+                report_id: None,
+                status: ExportJobStatus::Queued,
+                error: None,
+                part: None,
+                created_at: now,
+                updated_at: now,
+            })
+            .collect()
+    }
+
+    /// Requests the export, or checks on it and downloads it once finished.
+    /// Errors leave the job as it was so that it is retried on the next poll,
+    /// except when its account was removed, which fails the job. The download
+    /// is kept in the parts directory under `dir` until its batch is merged.
+    async fn advance(&mut self, config: &Config, dir: &Path) -> Result<(), anyhow::Error> {
+        let Some(account_config) = config
+            .live_accounts()
+            .iter()
+            .find(|a| a.name == self.account)
+            .map(|a| config.for_account(a))
+        else {
+            self.status = ExportJobStatus::Failed;
+            return Err(anyhow::anyhow!(
+                "Account {} is no longer configured",
+                self.account
+            ));
+        };
+        let trading212_client = Trading212Client::new(RequestType::Export, &account_config)
+            .map_err(|e| anyhow::anyhow!("Failed to initialize Trading212 client: {}", e))?;
+
+        let Some(report_id) = self.report_id else {
+            let export_request = ExportRequest {
+                data_included: self.data_included.clone(),
+                time_from: self.time_from.to_rfc3339(),
+                time_to: self.time_to.to_rfc3339(),
+            };
+            let export_response = trading212_client
+                .request_export(&export_request)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to request export: {}", e))?;
+            println!("Export initiated with ID: {}", export_response.report_id);
+            self.report_id = Some(export_response.report_id);
+            return Ok(());
+        };
+
+        let Some(export_info) = trading212_client
+            .get_export_status(report_id)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to check export status: {}", e))?
+        else {
+            return Err(anyhow::anyhow!("Export {} not found in list", report_id));
+        };
+
+        match ExportJobStatus::from_api(&export_info.status) {
+            ExportJobStatus::Finished => {
+                let download_link = export_info
+                    .download_link
+                    .ok_or_else(|| anyhow::anyhow!("Export {} has no download link", report_id))?;
+                let export_data = trading212_client
+                    .download_export(&download_link)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Failed to download export: {}", e))?;
+                let parts_dir = dir.join(EXPORT_PARTS_DIR);
+                std::fs::create_dir_all(&parts_dir)?;
+                let part = parts_dir.join(format!("{}.csv", report_id));
+                std::fs::write(&part, export_data)
+                    .map_err(|e| anyhow::anyhow!("Failed to save export file: {}", e))?;
+                self.part = Some(part);
+                self.status = ExportJobStatus::Finished;
+            }
+            status => self.status = status,
+        }
+        Ok(())
+    }
+}
+
+/// Runs export jobs in the background so that no request handler has to
+/// wait for Trading 212 to generate a report
+///
+/// Jobs are saved to `export_jobs.json` after every change, so exports that
+/// were requested before a restart are picked up again by their report id.
+/// The export files of each account are kept in its own directory under
+/// `exports/`, next to the jobs file.
+#[derive(Debug, Clone)]
+pub struct ExportJobManager {
+    jobs: Arc<TokioMutex<Vec<ExportJob>>>,
+    path: PathBuf,
+    dir: PathBuf,
+    wake: Arc<Notify>,
+}
+
+impl ExportJobManager {
+    /// Loads the jobs saved at `path`, starting empty if there are none
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let jobs = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                eprintln!("Failed to parse {}: {}", path.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        Self {
+            jobs: Arc::new(TokioMutex::new(jobs)),
+            path,
+            dir,
+            wake: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Directory of the export files of `account`
+    pub fn account_dir(&self, account: &str) -> PathBuf {
+        account_dir(&self.dir, account)
+    }
+
+    /// Moves the export files stored before they were kept per account to
+    /// `account`, along with the jobs that were requested for no account
+    pub async fn adopt_legacy_exports(&self, account: &str) {
+        let mut jobs = self.jobs.lock().await;
+        let files = export_files(&self.dir).unwrap_or_default();
+        if !files.is_empty() {
+            let dir = self.account_dir(account);
+            if let Err(e) = std::fs::create_dir_all(&dir) {
+                eprintln!("Failed to create {}: {}", dir.display(), e);
+                return;
+            }
+            for file in files {
+                let Some(name) = file.file_name() else {
+                    continue;
+                };
+                match std::fs::rename(&file, dir.join(name)) {
+                    Ok(()) => println!("Moved {} to {}", file.display(), dir.display()),
+                    Err(e) => eprintln!("Failed to move {}: {}", file.display(), e),
+                }
+            }
+        }
+        let mut adopted = false;
+        for job in jobs.iter_mut().filter(|j| j.account.is_empty()) {
+            job.account = account.to_string();
+            adopted = true;
+        }
+        if adopted {
+            self.save(&jobs);
+        }
+    }
+
+    pub async fn jobs(&self) -> Vec<ExportJob> {
+        self.jobs.lock().await.clone()
+    }
+
+    /// Returns true while an export of `account` is being generated or
+    /// downloaded
+    pub async fn is_refreshing(&self, account: &str) -> bool {
+        self.jobs
+            .lock()
+            .await
+            .iter()
+            .any(|j| j.account == account && j.status.is_active())
+    }
+
+    /// Returns true while any export is being generated or downloaded
+    async fn has_active_jobs(&self) -> bool {
+        self.jobs.lock().await.iter().any(|j| j.status.is_active())
+    }
+
+    /// Queues exports of whatever part of the configured history is not
+    /// covered by the export files of `account` yet, unless its exports are
+    /// in progress
+    pub async fn enqueue_refresh(
+        &self,
+        config: &Config,
+        account: &Account,
+    ) -> Result<(), anyhow::Error> {
+        let mut jobs = self.jobs.lock().await;
+        if jobs
+            .iter()
+            .any(|j| j.account == account.name && j.status.is_active())
+        {
+            return Ok(());
+        }
+        config.export.validate()?;
+
+        // Files without one of the configured data sets do not count, so
+        // a data set turned on later is exported for the whole history
+        let files = covered_exports(&self.account_dir(&account.name))?;
+        let covered = covered_periods(&files, &DataIncluded::from(&config.export));
+        let now = Utc::now();
+        let history_from = now
+            .checked_sub_months(Months::new(12 * config.export.history_years))
+            .unwrap_or(now);
+        let ranges = missing_ranges(&covered, history_from, now);
+        if ranges.is_empty() {
+            return Ok(());
+        }
+
+        let batch = ExportJob::batch(config, &account.name, &ranges, now);
+        println!(
+            "Queued {} export(s) to update the export history of {}",
+            batch.len(),
+            account.name
+        );
+        jobs.extend(batch);
+        self.save(&jobs);
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    /// Advances every active job by one step and merges the batches that
+    /// are complete
+    pub async fn process(&self, config: &Config) {
+        let active: Vec<(usize, ExportJob)> = self
+            .jobs
+            .lock()
+            .await
+            .iter()
+            .cloned()
+            .enumerate()
+            .filter(|(_, job)| job.status.is_active())
+            .collect();
+
+        // The lock is not held while talking to Trading 212
+        let mut updated = Vec::new();
+        for (index, mut job) in active {
+            let now = Utc::now();
+            match job.advance(config, &self.dir).await {
+                Ok(()) => job.error = None,
+                Err(e) => {
+                    eprintln!("Export job of {}: {}", job.account, e);
+                    job.error = Some(e.to_string());
+                }
+            }
+            if job.status.is_active() && now - job.created_at > EXPORT_JOB_TIMEOUT {
+                job.status = ExportJobStatus::Failed;
+                job.error = Some("Export timed out".to_string());
+            }
+            job.updated_at = now;
+            updated.push((index, job));
+        }
+
+        let mut jobs = self.jobs.lock().await;
+        for (index, job) in updated {
+            jobs[index] = job;
+        }
+        finish_batches(&mut jobs, &self.dir);
+        self.save(&jobs);
+    }
+
+    /// Processes the jobs until the application exits
+    pub async fn run(self, config: Arc<TokioMutex<Config>>) {
+        loop {
+            if self.has_active_jobs().await {
+                let config = config.lock().await.clone();
+                if config.mode == Mode::Live {
+                    self.process(&config).await;
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(EXPORT_POLL_INTERVAL) => {}
+                    _ = self.wake.notified() => {}
+                }
+            } else {
+                self.wake.notified().await;
+            }
+        }
+    }
+
+    /// Merges uploaded exports, already checked with `validate_upload`,
+    /// into the stored history of `account`
+    ///
+    /// Each upload only covers the period from its first to its last row,
+    /// so uploading an older year next to the stored files leaves the gap
+    /// in between to be exported. Runs under the job lock so that it cannot
+    /// interleave with a batch being merged.
+    pub async fn import(
+        &self,
+        account: &str,
+        uploads: Vec<(String, NaiveDate, NaiveDate)>,
+    ) -> Result<ImportSummary, anyhow::Error> {
+        let _jobs = self.jobs.lock().await;
+        let dir = self.account_dir(account);
+        std::fs::create_dir_all(&dir)?;
+        let before = read_history(&dir)?.len();
+        let files = uploads.len();
+        for (export, from, to) in uploads {
+            let data = data_included_of(&parse_export(&export)?.events);
+            merge_into_history(&dir, vec![export], from, to, &data)?;
+        }
+        let total = read_history(&dir)?.len();
+        Ok(ImportSummary {
+            files,
+            added: total.saturating_sub(before),
+            total,
+        })
+    }
+
+    fn save(&self, jobs: &[ExportJob]) {
+        let result = serde_json::to_string_pretty(jobs)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(std::fs::write(&self.path, json)?));
+        if let Err(e) = result {
+            eprintln!("Failed to save export jobs: {}", e);
+        }
+    }
+}
+
+/// Merges the parts of every batch whose jobs all finished and drops the
+/// parts of failed batches. Only the jobs of the latest batches are kept.
+fn finish_batches(jobs: &mut Vec<ExportJob>, dir: &Path) {
+    let mut batches: Vec<i64> = jobs.iter().map(|j| j.batch).collect();
+    batches.sort_unstable();
+    batches.dedup();
+
+    for &batch in &batches {
+        let mut parts: Vec<&mut ExportJob> = jobs.iter_mut().filter(|j| j.batch == batch).collect();
+        if parts.iter().any(|j| j.status.is_active()) || parts.iter().all(|j| j.part.is_none()) {
+            continue;
+        }
+        parts.sort_by_key(|j| j.time_from);
+
+        if parts.iter().all(|j| j.status == ExportJobStatus::Finished) {
+            if let Err(e) = merge_batch(dir, &parts) {
+                eprintln!("Failed to merge exports: {}", e);
+                continue;
+            }
+        }
+        for job in parts {
+            if let Some(part) = job.part.take() {
+                let _ = std::fs::remove_file(part);
+            }
+        }
+    }
+
+    let keep: Vec<i64> = batches.into_iter().rev().take(MAX_KEPT_BATCHES).collect();
+    jobs.retain(|j| keep.contains(&j.batch));
+}
+
+/// Directory in `dir` holding the export files of `account`
+fn account_dir(dir: &Path, account: &str) -> PathBuf {
+    dir.join(EXPORTS_DIR).join(Account::dir_name(account))
+}
+
+/// Merges the parts of a batch into the export files of its account
+fn merge_batch(dir: &Path, parts: &[&mut ExportJob]) -> Result<(), anyhow::Error> {
+    let (Some(first), Some(last)) = (parts.first(), parts.last()) else {
+        return Ok(());
+    };
+    let dir = account_dir(dir, &first.account);
+    std::fs::create_dir_all(&dir)?;
+    let exports = parts
+        .iter()
+        .filter_map(|j| j.part.as_ref())
+        .map(std::fs::read_to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    merge_into_history(
+        &dir,
+        exports,
+        first.time_from.date_naive(),
+        last.time_to.date_naive(),
+        &first.data_included,
+    )
+}
+
+/// Merges `exports` covering `from..=to` with the `data` sets into the
+/// export files in `dir`
+///
+/// Files with the same data sets that overlap or touch the period are
+/// joined with it, so there is one file per covered period and a gap
+/// between two periods stays visible to `missing_ranges`. Files with fewer
+/// data sets that lie within the joined period are superseded by it. Rows
+/// of `exports` replace older rows with the same ID.
+fn merge_into_history(
+    dir: &Path,
+    mut exports: Vec<String>,
+    from: NaiveDate,
+    to: NaiveDate,
+    data: &DataIncluded,
+) -> Result<(), anyhow::Error> {
+    let existing = covered_exports(dir)?;
+    let (mut from, mut to) = (from, to);
+    let mut joined = vec![false; existing.len()];
+    // Joining one file can make the period touch the next one
+    let mut grown = true;
+    while grown {
+        grown = false;
+        for (file, joined) in existing.iter().zip(joined.iter_mut()) {
+            if !*joined && file.data.as_ref() == Some(data) && file.from <= to && file.to >= from {
+                *joined = true;
+                from = from.min(file.from);
+                to = to.max(file.to);
+                grown = true;
+            }
+        }
+    }
+    for (file, joined) in existing.iter().zip(joined.iter_mut()) {
+        if file.data.as_ref().is_some_and(|d| data.covers(d)) && file.from >= from && file.to <= to
+        {
+            *joined = true;
+        }
+    }
+    let joined: Vec<&ExportFile> = existing
+        .iter()
+        .zip(joined)
+        .filter_map(|(file, joined)| joined.then_some(file))
+        .collect();
+
+    let mut contents = joined
+        .iter()
+        .map(|file| std::fs::read_to_string(&file.path))
+        .collect::<Result<Vec<_>, _>>()?;
+    contents.append(&mut exports);
+    let merged = merge_exports(&contents)?;
+    let path = dir.join(ExportFile::name(from, to, data));
+    std::fs::write(&path, merged)
+        .map_err(|e| anyhow::anyhow!("Failed to save export file: {}", e))?;
+    println!("Export saved to {}", path.display());
+
+    for file in joined {
+        if file.path != path {
+            println!("Removing superseded export {}", file.path.display());
+            let _ = std::fs::remove_file(&file.path);
+        }
+    }
+    Ok(())
+}
+
+/// Every event of the export files in `dir`, oldest first. Rows found in
+/// more than one file are returned once.
+pub fn read_history(dir: &Path) -> Result<Vec<HistoryEvent>, anyhow::Error> {
+    let exports = covered_exports(dir)?
+        .iter()
+        .map(|f| std::fs::read_to_string(&f.path))
+        .collect::<Result<Vec<_>, _>>()?;
+    if exports.is_empty() {
+        return Ok(Vec::new());
+    }
+    let parsed = parse_export(&merge_exports(&exports)?)?;
+    if !parsed.skipped.is_empty() {
+        eprintln!(
+            "Skipped {} unreadable row(s) of the export history",
+            parsed.skipped.len()
+        );
+    }
+    let mut events = parsed.events;
+    events.sort_by_key(|e| e.time());
+    Ok(events)
+}
+
+/// Period covered by an uploaded export and the rows that will be left out
+#[derive(Debug)]
+pub struct UploadCheck {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub skipped: Vec<ExportParseError>,
+}
+
+/// Checks that an uploaded file is a Trading 212 export with at least one
+/// readable row and returns the period it covers
+pub fn validate_upload(csv_content: &str) -> Result<UploadCheck, anyhow::Error> {
+    let parsed = parse_export(csv_content)?;
+    let from = parsed.events.iter().map(|e| e.time().date_naive()).min();
+    let to = parsed.events.iter().map(|e| e.time().date_naive()).max();
+    let (from, to) = from
+        .zip(to)
+        .ok_or_else(|| anyhow::anyhow!("The file has no readable rows"))?;
+    Ok(UploadCheck {
+        from,
+        to,
+        skipped: parsed.skipped,
+    })
+}
+
+/// Result of importing uploaded exports into the stored history
+#[derive(Debug, Clone)]
+pub struct ImportSummary {
+    pub files: usize,
+    /// Rows that were not part of the history yet
+    pub added: usize,
+    pub total: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_ranges_are_chunked() {
+        let from: DateTime<Utc> = "2020-01-01T00:00:00Z".parse().unwrap();
+        let to: DateTime<Utc> = "2022-06-01T00:00:00Z".parse().unwrap();
+
+        let ranges = export_ranges(from, to, 365);
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges[0].0, from);
+        assert_eq!(ranges[1].0, ranges[0].1);
+        assert_eq!(ranges[2].1, to);
+        assert!(ranges
+            .iter()
+            .all(|(start, end)| *end - *start <= Duration::days(365)));
+
+        assert!(export_ranges(to, from, 365).is_empty());
+    }
+
+    #[test]
+    fn test_merge_exports_unions_columns() {
+        let dividends = "Action,Time,Ticker,Total\n\
+                         Dividend (Dividend),2021-03-01 10:00:00,VUSA,1.20\n"
+            .to_string();
+        let orders = "Action,Time,Ticker,No. of shares,Total\n\
+                      Market buy,2022-02-01 09:30:00,VUSA,2,120.00\n\
+                      Dividend (Dividend),2021-03-01 10:00:00,VUSA,,1.20\n"
+            .to_string();
+
+        let merged = merge_exports(&[dividends, orders]).unwrap();
+        let mut rdr = csv::Reader::from_reader(merged.as_bytes());
+        assert_eq!(
+            rdr.headers().unwrap(),
+            vec!["Action", "Time", "Ticker", "Total", "No. of shares"]
+        );
+        let rows: Vec<csv::StringRecord> = rdr.records().map(|r| r.unwrap()).collect();
+        // The dividend on the boundary of both files is kept once
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[1][4], "2");
+        assert_eq!(&rows[1][3], "120.00");
+    }
+
+    #[test]
+    fn test_export_jobs_cover_history_in_chunks() {
+        let config = Config {
+            export: ExportSettings {
+                history_years: 3,
+                ..ExportSettings::default()
+            },
+            ..Config::default()
+        };
+        let now: DateTime<Utc> = "2025-06-01T00:00:00Z".parse().unwrap();
+
+        let history_from = "2022-06-01T00:00:00Z".parse().unwrap();
+        let jobs = ExportJob::batch(&config, "ISA", &[(history_from, now)], now);
+        assert_eq!(jobs.len(), 4);
+        assert!(jobs.iter().all(|j| j.status == ExportJobStatus::Queued));
+        assert_eq!(
+            jobs[0].time_from,
+            "2022-06-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(jobs[3].time_to, now);
+        assert!(jobs[0].data_included.include_dividends);
+    }
+
+    #[test]
+    fn test_export_status_from_api() {
+        assert_eq!(
+            ExportJobStatus::from_api("Running"),
+            ExportJobStatus::Processing
+        );
+        assert_eq!(
+            ExportJobStatus::from_api("Canceled"),
+            ExportJobStatus::Failed
+        );
+        assert!(ExportJobStatus::from_api("Queued").is_active());
+        assert!(!ExportJobStatus::from_api("Finished").is_active());
+    }
+
+    #[tokio::test]
+    async fn test_jobs_survive_restart_and_old_batches_are_pruned() {
+        let path = std::env::temp_dir().join(format!("export_jobs_{}.json", std::process::id()));
+        let config = Config::default();
+        let manager = ExportJobManager::load(&path);
+        {
+            let mut jobs = manager.jobs.lock().await;
+            for batch in 0..(MAX_KEPT_BATCHES as i64 + 2) {
+                let now = DateTime::from_timestamp(1_700_000_000 + batch, 0).unwrap();
+                let ranges = [(now - Duration::days(30), now)];
+                jobs.extend(
+                    ExportJob::batch(&config, "Invest", &ranges, now)
+                        .into_iter()
+                        .map(|mut j| {
+                            j.status = ExportJobStatus::Failed;
+                            j
+                        }),
+                );
+            }
+            jobs.last_mut().unwrap().status = ExportJobStatus::Processing;
+            finish_batches(&mut jobs, &manager.dir);
+            manager.save(&jobs);
+        }
+
+        let reloaded = ExportJobManager::load(&path);
+        let jobs = reloaded.jobs().await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(jobs.len(), MAX_KEPT_BATCHES);
+        assert_eq!(jobs[0].batch, 1_700_000_002);
+        assert!(reloaded.is_refreshing("Invest").await);
+        assert!(!reloaded.is_refreshing("ISA").await);
+    }
+
+    #[tokio::test]
+    async fn test_job_of_removed_account_fails() {
+        let path =
+            std::env::temp_dir().join(format!("export_jobs_removed_{}.json", std::process::id()));
+        let config = Config::default();
+        let manager = ExportJobManager::load(&path);
+        let now = Utc::now();
+        manager.jobs.lock().await.extend(ExportJob::batch(
+            &config,
+            "Removed",
+            &[(now - Duration::days(30), now)],
+            now,
+        ));
+
+        manager.process(&config).await;
+        let jobs = manager.jobs().await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(jobs[0].status, ExportJobStatus::Failed);
+        assert_eq!(
+            jobs[0].error.as_deref(),
+            Some("Account Removed is no longer configured")
+        );
+    }
+
+    #[test]
+    fn test_merge_exports_dedupes_by_transaction_id() {
+        let older = "Action,Time,ID,Total\n\
+                     Deposit,2024-01-02 08:00:00,DEP-1,500.00\n\
+                     Market buy,2024-01-08 09:30:00,EOF-1,44.65\n"
+            .to_string();
+        // The same order again with a corrected total, and a new deposit
+        let newer = "Action,Time,ID,Total\n\
+                     Market buy,2024-01-08 09:30:00,EOF-1,44.66\n\
+                     Deposit,2024-02-01 08:00:00,DEP-2,250.00\n"
+            .to_string();
+
+        let merged = merge_exports(&[older, newer]).unwrap();
+        let mut rdr = csv::Reader::from_reader(merged.as_bytes());
+        let rows: Vec<csv::StringRecord> = rdr.records().map(|r| r.unwrap()).collect();
+        let ids: Vec<&str> = rows.iter().map(|r| &r[2]).collect();
+        assert_eq!(ids, vec!["DEP-1", "EOF-1", "DEP-2"]);
+        // The newer export wins
+        assert_eq!(&rows[1][3], "44.66");
+    }
+
+    #[test]
+    fn test_merge_exports_dedupes_rows_without_id() {
+        let older = "Action,Time,Ticker,Total\n\
+                     Dividend (Dividend),2024-03-01 10:00:00,VOD,1.50\n"
+            .to_string();
+        // The same dividend from an export with a note column and a total
+        // written differently
+        let newer = "Action,Time,Ticker,Total,Notes\n\
+                     Dividend (Dividend),2024-03-01 10:00:00.000,VOD,1.5,Paid\n\
+                     Dividend (Dividend),2024-06-01 10:00:00,VOD,1.50,\n"
+            .to_string();
+
+        let merged = merge_exports(&[older, newer]).unwrap();
+        let mut rdr = csv::Reader::from_reader(merged.as_bytes());
+        let rows: Vec<csv::StringRecord> = rdr.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][4], "Paid");
+    }
+
+    #[test]
+    fn test_missing_ranges() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+        let history_from = at("2023-06-01T00:00:00Z");
+        let now = at("2025-06-01T12:00:00Z");
+
+        assert_eq!(
+            missing_ranges(&[], history_from, now),
+            vec![(history_from, now)]
+        );
+        // Exported this morning, nothing to do
+        assert!(missing_ranges(
+            &[(date("2023-06-01"), date("2025-06-01"))],
+            history_from,
+            now
+        )
+        .is_empty());
+        // A week old export that started later than the configured history
+        assert_eq!(
+            missing_ranges(
+                &[(date("2024-06-01"), date("2025-05-25"))],
+                history_from,
+                now
+            ),
+            vec![
+                (history_from, at("2024-06-01T00:00:00Z")),
+                (at("2025-05-25T00:00:00Z"), now)
+            ]
+        );
+        // The gap between two exports is filled in
+        assert_eq!(
+            missing_ranges(
+                &[
+                    (date("2023-06-01"), date("2024-01-01")),
+                    (date("2024-07-01"), date("2025-06-01"))
+                ],
+                history_from,
+                now
+            ),
+            vec![(at("2024-01-01T00:00:00Z"), at("2024-07-01T00:00:00Z"))]
+        );
+    }
+
+    fn export_file(from: &str, to: &str, code: &str) -> ExportFile {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        ExportFile {
+            path: PathBuf::new(),
+            from: date(from),
+            to: date(to),
+            data: DataIncluded::from_code(code),
+        }
+    }
+
+    #[test]
+    fn test_covered_periods_need_every_data_set() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let all = DataIncluded::from_code("DIOT").unwrap();
+        let files = vec![
+            export_file("2023-01-01", "2023-06-01", "DIOT"),
+            export_file("2023-06-01", "2023-12-31", "DIOT"),
+            // Lacks the interest, so it does not cover anything
+            export_file("2024-01-01", "2024-06-01", "DOT"),
+            export_file("2024-09-01", "2025-01-01", "DIOT"),
+            // Older versions did not record the data sets
+            export_file("2025-01-01", "2025-06-01", "not a code"),
+        ];
+        assert_eq!(
+            covered_periods(&files, &all),
+            vec![
+                (date("2023-01-01"), date("2023-12-31")),
+                (date("2024-09-01"), date("2025-01-01"))
+            ]
+        );
+        assert_eq!(
+            covered_periods(&files, &DataIncluded::from_code("D").unwrap()).len(),
+            3
+        );
+        assert_eq!(all.code(), "DIOT");
+        assert!(all.covers(&DataIncluded::from_code("OT").unwrap()));
+        assert!(!DataIncluded::from_code("OT").unwrap().covers(&all));
+    }
+
+    #[test]
+    fn test_merge_into_history_keeps_gaps() {
+        let dir = std::env::temp_dir().join(format!("export_merge_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let all = DataIncluded::from_code("DIOT").unwrap();
+        let export =
+            |id: &str, time: &str| format!("Action,Time,ID,Total\nDeposit,{time},{id},5\n");
+
+        merge_into_history(
+            &dir,
+            vec![export("DEP-1", "2023-02-01 08:00:00")],
+            date("2023-01-01"),
+            date("2023-06-01"),
+            &all,
+        )
+        .unwrap();
+        merge_into_history(
+            &dir,
+            vec![export("DEP-2", "2024-02-01 08:00:00")],
+            date("2024-01-01"),
+            date("2024-06-01"),
+            &all,
+        )
+        .unwrap();
+        let files = covered_exports(&dir).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(
+            files[0].path.file_name().unwrap(),
+            "export_20230101_20230601_DIOT.csv"
+        );
+
+        // Touching the first file joins it, the gap to the second stays
+        merge_into_history(
+            &dir,
+            vec![export("DEP-3", "2023-07-01 08:00:00")],
+            date("2023-06-01"),
+            date("2023-08-01"),
+            &all,
+        )
+        .unwrap();
+        let periods = covered_periods(&covered_exports(&dir).unwrap(), &all);
+        let history = read_history(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            periods,
+            vec![
+                (date("2023-01-01"), date("2023-08-01")),
+                (date("2024-01-01"), date("2024-06-01"))
+            ]
+        );
+        assert_eq!(history.len(), 3);
+    }
+
+    #[test]
+    fn test_export_file_period_from_name() {
+        let file = ExportFile::open(PathBuf::from("export_20230601_20250601_DOT.csv")).unwrap();
+        assert_eq!(file.from, NaiveDate::from_ymd_opt(2023, 6, 1).unwrap());
+        assert_eq!(file.to, NaiveDate::from_ymd_opt(2025, 6, 1).unwrap());
+        assert_eq!(file.data, DataIncluded::from_code("DOT"));
+        let legacy = ExportFile::open(PathBuf::from("export_20230601_20250601.csv")).unwrap();
+        assert_eq!(legacy.data, None);
+        assert!(ExportFile::open(PathBuf::from("export_parts.csv")).is_none());
+        assert_eq!(
+            covered_period("Action,Time\nDeposit,02/01/2024 08:00\nDeposit,2024-03-05 10:00:00\n"),
+            Some((
+                NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 3, 5).unwrap()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_does_not_bridge_gaps() {
+        let dir = std::env::temp_dir().join(format!("export_import_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manager = ExportJobManager::load(dir.join(EXPORT_JOBS_FILE));
+        let upload = |rows: &[(&str, &str)]| {
+            let mut csv = "Action,Time,ID,Total\n".to_string();
+            for (id, time) in rows {
+                csv.push_str(&format!("Deposit,{time},{id},5\n"));
+            }
+            let check = validate_upload(&csv).unwrap();
+            (csv, check.from, check.to)
+        };
+
+        manager
+            .import(
+                "ISA",
+                vec![upload(&[
+                    ("DEP-1", "2023-01-02 08:00:00"),
+                    ("DEP-2", "2025-01-02 08:00:00"),
+                ])],
+            )
+            .await
+            .unwrap();
+        let summary = manager
+            .import("ISA", vec![upload(&[("DEP-3", "2019-03-01 08:00:00")])])
+            .await
+            .unwrap();
+
+        let data = DataIncluded::from_code("T").unwrap();
+        let covered = covered_periods(
+            &covered_exports(&manager.account_dir("ISA")).unwrap(),
+            &data,
+        );
+        // The other account has no history of its own
+        assert!(read_history(&manager.account_dir("Invest"))
+            .unwrap()
+            .is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(summary.added, 1);
+        assert_eq!(summary.total, 3);
+        assert_eq!(covered.len(), 2);
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+        let ranges = missing_ranges(
+            &covered,
+            at("2019-01-01T00:00:00Z"),
+            at("2025-01-02T12:00:00Z"),
+        );
+        assert!(ranges.contains(&(at("2019-03-01T00:00:00Z"), at("2023-01-02T00:00:00Z"))));
+    }
+
+    #[tokio::test]
+    async fn test_adopt_legacy_exports() {
+        let dir = std::env::temp_dir().join(format!("export_legacy_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let legacy = "Action,Time,ID,Total\nDeposit,2024-01-02 08:00:00,DEP-1,5\n";
+        std::fs::write(dir.join("export_20240102_20240102.csv"), legacy).unwrap();
+        let manager = ExportJobManager::load(dir.join(EXPORT_JOBS_FILE));
+        manager.jobs.lock().await.extend(ExportJob::batch(
+            &Config::default(),
+            "",
+            &[(Utc::now() - Duration::days(30), Utc::now())],
+            Utc::now(),
+        ));
+
+        manager.adopt_legacy_exports("My ISA").await;
+        let moved = read_history(&manager.account_dir("My ISA")).unwrap();
+        let left = export_files(&dir).unwrap();
+        let jobs = manager.jobs().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(moved.len(), 1);
+        assert!(left.is_empty());
+        assert!(jobs.iter().all(|j| j.account == "My ISA"));
+        assert!(manager.account_dir("My ISA").ends_with("exports/My_ISA"));
+    }
+
+    #[test]
+    fn test_validate_upload() {
+        let check =
+            validate_upload(&std::fs::read_to_string("mock_data/export.csv").unwrap()).unwrap();
+        assert_eq!(check.from, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert!(check.to > check.from);
+        assert!(check.skipped.is_empty());
+
+        assert!(validate_upload("Action,Time,Total\n").is_err());
+        assert!(validate_upload("Date,Amount\n2024-01-01,5\n").is_err());
+    }
+}
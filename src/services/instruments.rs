@@ -0,0 +1,298 @@
+// File: instruments.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::services::trading212::{
+    InstrumentMetadata, RequestType, Trading212Client, Trading212Error,
+};
+use crate::utils::json_store::{self, Unparsable};
+use crate::utils::settings::{Config, Mode};
+
+/// File the instrument metadata downloaded from Trading 212 is cached in
+pub const INSTRUMENTS_CACHE_FILE: &str = "instruments_cache.json";
+
+/// Instrument metadata used in Demo mode
+pub const DEMO_INSTRUMENTS_FILE: &str = "demo_data/demo_instruments.json";
+
+/// Source recorded for the metadata of Demo mode
+const DEMO_SOURCE: &str = "demo";
+
+/// Why the cached instrument metadata has to be downloaded again
+#[derive(Debug, Clone, PartialEq)]
+pub enum RefreshReason {
+    /// Nothing has been downloaded yet
+    Empty,
+    /// The metadata belongs to another API environment
+    SourceChanged,
+    /// The metadata is older than the configured TTL
+    Expired,
+    /// Positions hold tickers that were not known at the last download
+    NewTickers(Vec<String>),
+}
+
+impl std::fmt::Display for RefreshReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RefreshReason::Empty => write!(f, "no cached metadata"),
+            RefreshReason::SourceChanged => write!(f, "API environment changed"),
+            RefreshReason::Expired => write!(f, "cached metadata expired"),
+            RefreshReason::NewTickers(tickers) => write!(f, "new tickers {}", tickers.join(", ")),
+        }
+    }
+}
+
+/// Instrument metadata kept on disk between portfolio updates. The list is
+/// only downloaded again once it is older than the TTL, or when a position
+/// holds a ticker that has not been looked up yet.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct InstrumentCache {
+    /// Base URL of the API the metadata was downloaded from
+    pub source: String,
+    pub fetched_at: Option<DateTime<Utc>>,
+    /// Tickers that were missing from the last download. They are not
+    /// looked up again until the metadata expires.
+    #[serde(default)]
+    pub unknown: BTreeSet<String>,
+    pub instruments: Vec<InstrumentMetadata>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl InstrumentCache {
+    /// Loads the cache from `path`. A missing or unreadable file gives an
+    /// empty cache, which is filled by the next refresh.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut cache: InstrumentCache =
+            json_store::load(&path, "instrument cache", Unparsable::Ignore);
+        cache.path = path;
+        cache
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        json_store::save(&self.path, self)
+    }
+
+    /// Checks whether the metadata has to be downloaded before `tickers`
+    /// can be looked up
+    pub fn refresh_reason(
+        &self,
+        source: &str,
+        ttl: Duration,
+        tickers: &[String],
+        now: DateTime<Utc>,
+    ) -> Option<RefreshReason> {
+        let Some(fetched_at) = self.fetched_at else {
+            return Some(RefreshReason::Empty);
+        };
+        if self.source != source {
+            return Some(RefreshReason::SourceChanged);
+        }
+        let age = (now - fetched_at).to_std().unwrap_or_default();
+        if age >= ttl {
+            return Some(RefreshReason::Expired);
+        }
+
+        let known: HashSet<&str> = self.instruments.iter().map(|i| i.ticker.as_str()).collect();
+        let mut new_tickers: Vec<String> = tickers
+            .iter()
+            .filter(|t| !known.contains(t.as_str()) && !self.unknown.contains(*t))
+            .cloned()
+            .collect();
+        new_tickers.sort();
+        new_tickers.dedup();
+        if new_tickers.is_empty() {
+            None
+        } else {
+            Some(RefreshReason::NewTickers(new_tickers))
+        }
+    }
+
+    /// Makes sure the metadata covers `tickers` and is not older than the
+    /// configured TTL, downloading it only when needed. A failed download
+    /// falls back to the cached metadata of the same environment.
+    pub async fn refresh(
+        &mut self,
+        config: &Config,
+        tickers: &[String],
+        now: DateTime<Utc>,
+    ) -> Result<&[InstrumentMetadata], Trading212Error> {
+        if config.mode == Mode::Demo {
+            if self.source != DEMO_SOURCE {
+                self.load_demo()?;
+            }
+            return Ok(&self.instruments);
+        }
+
+        let source = config.api_environment.base_url().to_string();
+        let Some(reason) =
+            self.refresh_reason(&source, config.instrument_metadata_ttl, tickers, now)
+        else {
+            return Ok(&self.instruments);
+        };
+
+        println!("Downloading instrument metadata: {}", reason);
+        let metadata = match Trading212Client::new(RequestType::InstrumentsMetadata, config) {
+            Ok(client) => client.get_instruments_metadata().await,
+            Err(e) => Err(e),
+        };
+        match metadata {
+            Ok(instruments) => {
+                self.source = source;
+                self.fetched_at = Some(now);
+                self.instruments = instruments;
+                let known: HashSet<&str> =
+                    self.instruments.iter().map(|i| i.ticker.as_str()).collect();
+                self.unknown = tickers
+                    .iter()
+                    .filter(|t| !known.contains(t.as_str()))
+                    .cloned()
+                    .collect();
+                if !self.unknown.is_empty() {
+                    println!(
+                        "No instrument metadata for {}",
+                        self.unknown.iter().cloned().collect::<Vec<_>>().join(", ")
+                    );
+                }
+                match self.save() {
+                    Ok(()) => println!(
+                        "Saved metadata of {} instruments to {}",
+                        self.instruments.len(),
+                        self.path.display()
+                    ),
+                    Err(e) => eprintln!("Failed to save instrument metadata: {}", e),
+                }
+            }
+            Err(e) if self.source == source && !self.instruments.is_empty() => {
+                eprintln!(
+                    "Failed to download instrument metadata, using the cached list: {}",
+                    e
+                );
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(&self.instruments)
+    }
+
+    fn load_demo(&mut self) -> Result<(), Trading212Error> {
+        let content = std::fs::read_to_string(DEMO_INSTRUMENTS_FILE).map_err(|_| {
+            Trading212Error::RequestFailed("No demo instruments data available".to_string())
+        })?;
+        self.instruments = serde_json::from_str(&content).map_err(|e| {
+            Trading212Error::ParseError(format!("Failed to parse demo instruments data: {}", e))
+        })?;
+        self.source = DEMO_SOURCE.to_string();
+        self.fetched_at = None;
+        self.unknown.clear();
+        println!("Loaded instruments metadata from {}", DEMO_INSTRUMENTS_FILE);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::{router, spawn_test_server, Fixtures, DEFAULT_FIXTURES_DIR};
+    use crate::utils::settings::ApiEnvironment;
+    use std::path::Path;
+
+    fn instrument(ticker: &str) -> InstrumentMetadata {
+        InstrumentMetadata {
+            added_on: "2020-01-01T00:00:00.000+00:00".to_string(),
+            currency_code: "USD".to_string(),
+            isin: String::new(),
+            name: ticker.to_string(),
+            short_name: ticker.to_string(),
+            ticker: ticker.to_string(),
+            instrument_type: "STOCK".to_string(),
+            working_schedule_id: None,
+        }
+    }
+
+    #[test]
+    fn test_refresh_reason() {
+        let now: DateTime<Utc> = "2025-03-05T12:00:00Z".parse().unwrap();
+        let ttl = Duration::from_secs(24 * 60 * 60);
+        let tickers = vec!["AAPL_US_EQ".to_string(), "GONE_US_EQ".to_string()];
+        let mut cache = InstrumentCache::default();
+        assert_eq!(
+            cache.refresh_reason("http://a", ttl, &tickers, now),
+            Some(RefreshReason::Empty)
+        );
+
+        cache.source = "http://a".to_string();
+        cache.fetched_at = Some(now - chrono::Duration::hours(2));
+        cache.instruments = vec![instrument("AAPL_US_EQ")];
+        cache.unknown.insert("GONE_US_EQ".to_string());
+        assert_eq!(cache.refresh_reason("http://a", ttl, &tickers, now), None);
+        assert_eq!(
+            cache.refresh_reason("http://b", ttl, &tickers, now),
+            Some(RefreshReason::SourceChanged)
+        );
+        assert_eq!(
+            cache.refresh_reason("http://a", Duration::from_secs(3600), &tickers, now),
+            Some(RefreshReason::Expired)
+        );
+
+        let tickers = vec!["MSFT_US_EQ".to_string(), "AAPL_US_EQ".to_string()];
+        assert_eq!(
+            cache.refresh_reason("http://a", ttl, &tickers, now),
+            Some(RefreshReason::NewTickers(vec!["MSFT_US_EQ".to_string()]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refresh_downloads_and_persists() {
+        let fixtures = Fixtures::load(Path::new(DEFAULT_FIXTURES_DIR)).unwrap();
+        let addr = spawn_test_server(router(fixtures));
+        let config = Config {
+            mode: Mode::Live,
+            api_key: Some("mock-key".to_string()),
+            api_environment: ApiEnvironment::Custom(format!("http://{}", addr)),
+            ..Config::default()
+        };
+
+        let path = std::env::temp_dir().join(format!("instruments_{}.json", addr.port()));
+        let now = Utc::now();
+        let tickers = vec!["NOPE_US_EQ".to_string()];
+        let mut cache = InstrumentCache::load(&path);
+        let count = cache.refresh(&config, &tickers, now).await.unwrap().len();
+        assert!(count > 0);
+        assert!(cache.unknown.contains("NOPE_US_EQ"));
+
+        let loaded = InstrumentCache::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.instruments.len(), count);
+        assert_eq!(loaded.fetched_at, Some(now));
+        assert_eq!(
+            loaded.refresh_reason(
+                config.api_environment.base_url(),
+                config.instrument_metadata_ttl,
+                &tickers,
+                now
+            ),
+            None
+        );
+    }
+}
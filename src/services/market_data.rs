@@ -0,0 +1,993 @@
+// File: market_data.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::models::corporate_action::{CorporateAction, CorporateActionKind};
+use crate::models::portfolio::MonthlyPayment;
+use crate::services::price_history::PriceBar;
+use crate::services::yahoo::YahooClient;
+use crate::utils::json_store::{self, Unparsable};
+use crate::utils::settings::{Config, MarketDataSource, Mode};
+
+/// File the market data of each symbol is cached in
+pub const MARKET_DATA_CACHE_FILE: &str = "market_data.json";
+
+/// Market data used in Demo mode
+pub const DEMO_MARKET_DATA_DIR: &str = "demo_data/market_data";
+
+/// Upper bound of a request to an HTTP market data provider
+const HTTP_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Error, Debug)]
+pub enum MarketDataError {
+    #[error("Market data request failed: {0}")]
+    RequestFailed(String),
+
+    #[error("Failed to parse market data: {0}")]
+    ParseError(String),
+
+    #[error("{0} is not supported by this market data source")]
+    Unsupported(String),
+}
+
+/// Latest price of a symbol
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Quote {
+    pub name: Option<String>,
+    pub currency: Option<String>,
+    pub price: Option<f64>,
+}
+
+/// Dividend figures of a symbol, in the currency of its quote
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DividendData {
+    /// Annual dividend per share
+    pub rate: Option<f64>,
+    /// Dividend yield in percent
+    pub yield_percent: Option<f64>,
+    pub ex_date: Option<DateTime<Utc>>,
+    pub payment_date: Option<DateTime<Utc>>,
+    /// Amount per share of the next announced dividend
+    pub next_amount: Option<f64>,
+    /// Most recent dividends, oldest first
+    pub recent: Vec<MonthlyPayment>,
+    /// Every dividend per share on record, oldest first
+    #[serde(default)]
+    pub history: Vec<MonthlyPayment>,
+    /// The dividends are the amounts paid at the time rather than adjusted
+    /// for later splits, as Yahoo Finance does
+    #[serde(default)]
+    pub as_paid: bool,
+}
+
+/// What the company does and where it is based
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub sector: Option<String>,
+    pub industry: Option<String>,
+    pub country: Option<String>,
+}
+
+/// Everything the portfolio needs to know about one symbol
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MarketData {
+    pub quote: Quote,
+    pub dividends: DividendData,
+    pub profile: Profile,
+    /// Past and announced corporate actions, oldest first
+    #[serde(default)]
+    pub corporate_actions: Vec<CorporateAction>,
+}
+
+impl MarketData {
+    /// Reads the info of a symbol in the shape printed by `stock_info.py`
+    /// (the `Ticker.info` of yfinance plus `last_4_dividends`,
+    /// `dividend_history` and `split_history`). Returns `None` for an
+    /// `error` entry.
+    pub fn from_info(info: &Value) -> Option<Self> {
+        if !info.is_object() || info.get("error").is_some() {
+            return None;
+        }
+        let text = |key: &str| info.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        let number = |key: &str| info.get(key).and_then(|v| v.as_f64());
+        // Dates are always integer timestamps
+        let timestamp = |key: &str| {
+            info.get(key)
+                .and_then(|v| v.as_i64())
+                .and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0))
+        };
+
+        // Objects of amounts keyed by date, returned oldest first
+        let payments = |key: &str| {
+            let mut payments: Vec<MonthlyPayment> = info
+                .get(key)
+                .and_then(|v| v.as_object())
+                .map(|obj| {
+                    obj.iter()
+                        .filter_map(|(date_str, value)| {
+                            let date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d").ok()?;
+                            value.as_f64().map(|amount| MonthlyPayment { date, amount })
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            payments.sort_by_key(|p| p.date);
+            payments
+        };
+        let recent = payments("last_4_dividends");
+        // Older sources only report the recent dividends
+        let history = Some(payments("dividend_history"))
+            .filter(|history| !history.is_empty())
+            .unwrap_or_else(|| recent.clone());
+
+        // Announced actions, the regular dividend gives the next amount
+        let mut next_amount = None;
+        let mut corporate_actions = Vec::new();
+        for entry in info
+            .get("corporateActions")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            match announced_action(entry) {
+                Announced::Dividend(amount) => {
+                    next_amount = next_amount.or(amount);
+                }
+                Announced::Action(action) => corporate_actions.push(action),
+                Announced::Unknown => {}
+            }
+        }
+        // Splits that took place, as a ratio such as 4.0 or "4:1"
+        if let Some(splits) = info.get("split_history").and_then(|v| v.as_object()) {
+            for (date_str, ratio) in splits {
+                let Ok(date) = NaiveDate::parse_from_str(date_str, "%Y-%m-%d") else {
+                    continue;
+                };
+                let ratio = match ratio {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                if let Some(kind) = CorporateActionKind::parse_split(&ratio) {
+                    corporate_actions.push(CorporateAction { date, kind });
+                }
+            }
+        }
+        corporate_actions.sort_by_key(|a| a.date);
+        corporate_actions.dedup();
+
+        Some(MarketData {
+            quote: Quote {
+                name: text("longName").or_else(|| text("shortName")),
+                currency: text("currency"),
+                price: number("currentPrice").or_else(|| number("regularMarketPrice")),
+            },
+            dividends: DividendData {
+                rate: number("dividendRate"),
+                yield_percent: number("dividendYield"),
+                ex_date: timestamp("exDividendDate"),
+                payment_date: timestamp("dividendDate"),
+                next_amount,
+                recent,
+                history,
+                as_paid: info
+                    .get("dividends_as_paid")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false),
+            },
+            profile: Profile {
+                sector: text("sector"),
+                industry: text("industry"),
+                country: text("country"),
+            },
+            corporate_actions,
+        })
+    }
+}
+
+/// Entry of the `corporateActions` of a quote
+enum Announced {
+    Dividend(Option<f64>),
+    Action(CorporateAction),
+    Unknown,
+}
+
+/// Reads an announced corporate action. Entries without an event type are
+/// regular dividends, as in older outputs of `stock_info.py`.
+fn announced_action(entry: &Value) -> Announced {
+    let meta = &entry["meta"];
+    let number = |key: &str| {
+        let value = &meta[key];
+        value.as_f64().or_else(|| {
+            value
+                .as_str()
+                .and_then(|s| s.trim().trim_end_matches('%').parse::<f64>().ok())
+        })
+    };
+    let event_type = meta["eventType"].as_str().unwrap_or("DIVIDEND");
+    let header = entry["header"].as_str().unwrap_or_default();
+    let date = meta["dateEpochMs"]
+        .as_i64()
+        .and_then(DateTime::<Utc>::from_timestamp_millis)
+        .map(|d| d.date_naive());
+
+    let kind = match event_type {
+        "DIVIDEND" if !header.to_lowercase().contains("special") => {
+            return Announced::Dividend(number("amount"))
+        }
+        "DIVIDEND" | "SPECIAL_DIVIDEND" => {
+            number("amount").map(|amount| CorporateActionKind::SpecialDividend { amount })
+        }
+        // A stock dividend of 500% gives five new shares for every share
+        "STOCK_DIVIDEND" => number("percent")
+            .and_then(|percent| CorporateActionKind::split(1.0, 1.0 + percent / 100.0)),
+        "SPLIT" | "STOCK_SPLIT" | "REVERSE_SPLIT" => {
+            match (number("numerator"), number("denominator")) {
+                (Some(to), Some(from)) => CorporateActionKind::split(from, to),
+                _ => meta["splitRatio"]
+                    .as_str()
+                    .and_then(CorporateActionKind::parse_split),
+            }
+        }
+        "SPINOFF" | "SPIN_OFF" => Some(CorporateActionKind::SpinOff {
+            symbol: meta["symbol"].as_str().map(str::to_string),
+            retained: number("retained"),
+        }),
+        _ => None,
+    };
+    match (date, kind) {
+        (Some(date), Some(kind)) => Announced::Action(CorporateAction { date, kind }),
+        _ => Announced::Unknown,
+    }
+}
+
+/// Answer of a provider: the data of the symbols it knows, and the error
+/// it reported for others. Symbols in neither map were not found.
+#[derive(Debug, Default)]
+pub struct MarketDataBatch {
+    pub data: HashMap<String, MarketData>,
+    pub errors: HashMap<String, String>,
+}
+
+/// Reads a JSON object of infos keyed by symbol, collecting error entries
+fn parse_infos(infos: &Value) -> Result<MarketDataBatch, MarketDataError> {
+    let infos = infos
+        .as_object()
+        .ok_or_else(|| MarketDataError::ParseError("expected an object keyed by symbol".into()))?;
+
+    let mut batch = MarketDataBatch::default();
+    for (symbol, info) in infos {
+        match MarketData::from_info(info) {
+            Some(data) => {
+                batch.data.insert(symbol.clone(), data);
+            }
+            None => {
+                let error = info["error"]
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| info.to_string());
+                eprintln!("No market data for {}: {}", symbol, error);
+                batch.errors.insert(symbol.clone(), error);
+            }
+        }
+    }
+    Ok(batch)
+}
+
+/// Daily prices returned by a provider, and the errors of the symbols it
+/// has none for
+#[derive(Debug, Default)]
+pub struct HistoryBatch {
+    pub bars: HashMap<String, Vec<PriceBar>>,
+    pub errors: HashMap<String, String>,
+}
+
+/// Source of quotes, dividends and company profiles. Symbols are Yahoo
+/// Finance tickers; symbols without data are left out of the result.
+#[async_trait]
+pub trait MarketDataProvider: Send + Sync {
+    async fn fetch(&self, symbols: &[String]) -> Result<MarketDataBatch, MarketDataError>;
+
+    /// Daily bars of each symbol from its start day up to today, oldest
+    /// first. Providers without price history keep this default.
+    async fn fetch_history(
+        &self,
+        _requests: &[(String, NaiveDate)],
+    ) -> Result<HistoryBatch, MarketDataError> {
+        Err(MarketDataError::Unsupported("Price history".to_string()))
+    }
+}
+
+/// Market data of one symbol with the time it was fetched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedMarketData {
+    pub fetched_at: DateTime<Utc>,
+    /// Lifetime set when the data was fetched
+    pub ttl: Duration,
+    pub data: MarketData,
+}
+
+impl CachedMarketData {
+    /// The entry expires after its own TTL, or earlier if the configured
+    /// TTL has been shortened since
+    pub fn is_stale(&self, ttl: Duration, now: DateTime<Utc>) -> bool {
+        let age = (now - self.fetched_at).to_std().unwrap_or_default();
+        age >= self.ttl.min(ttl)
+    }
+}
+
+/// Market data kept on disk per symbol. Only symbols that are missing or
+/// whose data has expired are fetched again; stale data is still used when
+/// the provider fails.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MarketDataCache {
+    /// Provider the entries were fetched from, see `source_key`
+    pub source: String,
+    pub entries: HashMap<String, CachedMarketData>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl MarketDataCache {
+    /// Loads the cache from `path`. A missing file, or one written in an
+    /// older format, gives an empty cache.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut cache: MarketDataCache =
+            json_store::load(&path, "market data cache", Unparsable::Ignore);
+        cache.path = path;
+        cache
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        json_store::save(&self.path, self)
+    }
+
+    /// Symbols that have no data from `source` or whose data has expired
+    pub fn stale_symbols(
+        &self,
+        source: &str,
+        symbols: &[String],
+        ttl: Duration,
+        now: DateTime<Utc>,
+    ) -> Vec<String> {
+        let mut stale: Vec<String> = symbols
+            .iter()
+            .filter(|symbol| {
+                let fresh = matches!(
+                    self.entries.get(*symbol),
+                    Some(entry) if !entry.is_stale(ttl, now)
+                );
+                self.source != source || !fresh
+            })
+            .cloned()
+            .collect();
+        stale.sort();
+        stale.dedup();
+        stale
+    }
+
+    /// Marks every entry as expired, so the next refresh fetches all
+    /// symbols again. The old data stays as a fallback.
+    pub fn expire_all(&mut self) {
+        for entry in self.entries.values_mut() {
+            entry.ttl = Duration::ZERO;
+        }
+    }
+
+    /// Data of `symbol` that is still used although it has expired,
+    /// because fetching it again failed
+    pub fn stale_entry(
+        &self,
+        symbol: &str,
+        ttl: Duration,
+        now: DateTime<Utc>,
+    ) -> Option<&CachedMarketData> {
+        self.entries
+            .get(symbol)
+            .filter(|entry| entry.is_stale(ttl, now))
+    }
+
+    /// Fetches the missing and stale symbols from `provider`. Returns the
+    /// data of every symbol that has any, and the errors of the symbols
+    /// that could not be fetched.
+    pub async fn refresh(
+        &mut self,
+        provider: &dyn MarketDataProvider,
+        source: &str,
+        symbols: &[String],
+        ttl: Duration,
+        now: DateTime<Utc>,
+    ) -> MarketDataBatch {
+        let stale = self.stale_symbols(source, symbols, ttl, now);
+        let mut errors = HashMap::new();
+        if !stale.is_empty() {
+            println!(
+                "Fetching market data of {} of {} symbols",
+                stale.len(),
+                symbols.len()
+            );
+            match provider.fetch(&stale).await {
+                Ok(fetched) => {
+                    if self.source != source {
+                        self.entries.clear();
+                        self.source = source.to_string();
+                    }
+                    for symbol in &stale {
+                        if !fetched.data.contains_key(symbol) {
+                            let error = fetched.errors.get(symbol).cloned().unwrap_or_else(|| {
+                                "No data returned by the market data source".to_string()
+                            });
+                            errors.insert(symbol.clone(), error);
+                        }
+                    }
+                    for (symbol, data) in fetched.data {
+                        self.entries.insert(
+                            symbol,
+                            CachedMarketData {
+                                fetched_at: now,
+                                ttl,
+                                data,
+                            },
+                        );
+                    }
+                    if let Err(e) = self.save() {
+                        eprintln!("Failed to save market data: {}", e);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to fetch market data, using cached data: {}", e);
+                    for symbol in &stale {
+                        errors.insert(symbol.clone(), e.to_string());
+                    }
+                }
+            }
+        }
+
+        let data = if self.source == source {
+            symbols
+                .iter()
+                .filter_map(|symbol| {
+                    let entry = self.entries.get(symbol)?;
+                    Some((symbol.clone(), entry.data.clone()))
+                })
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        MarketDataBatch { data, errors }
+    }
+}
+
+/// Identifies the configured provider, so that switching providers does not
+/// keep serving data of the previous one
+pub fn source_key(source: &MarketDataSource) -> String {
+    match source {
+        MarketDataSource::Yahoo => source.to_string(),
+        _ => format!("{}:{}", source, source.location()),
+    }
+}
+
+/// Provider selected in the config. Demo mode always reads the bundled
+/// static data.
+pub fn provider(config: &Config) -> Box<dyn MarketDataProvider> {
+    if config.mode == Mode::Demo {
+        return Box::new(DirectoryProvider::new(DEMO_MARKET_DATA_DIR));
+    }
+    match &config.market_data {
+        MarketDataSource::Yahoo => Box::new(YahooClient::default()),
+        MarketDataSource::Python(script) => Box::new(PythonProvider::new(script)),
+        MarketDataSource::Directory(dir) => Box::new(DirectoryProvider::new(dir)),
+        MarketDataSource::Http(url) => Box::new(HttpProvider::new(url)),
+    }
+}
+
+#[async_trait]
+impl MarketDataProvider for YahooClient {
+    async fn fetch(&self, symbols: &[String]) -> Result<MarketDataBatch, MarketDataError> {
+        let infos = self
+            .fetch_info(symbols)
+            .await
+            .map_err(|e| MarketDataError::RequestFailed(e.to_string()))?;
+        parse_infos(&infos)
+    }
+
+    async fn fetch_history(
+        &self,
+        requests: &[(String, NaiveDate)],
+    ) -> Result<HistoryBatch, MarketDataError> {
+        let results = self
+            .fetch_daily_bars(requests)
+            .await
+            .map_err(|e| MarketDataError::RequestFailed(e.to_string()))?;
+
+        let mut batch = HistoryBatch::default();
+        for (symbol, result) in results {
+            match result {
+                Ok(bars) => {
+                    batch.bars.insert(symbol, bars);
+                }
+                Err(e) => {
+                    batch.errors.insert(symbol, e.to_string());
+                }
+            }
+        }
+        Ok(batch)
+    }
+}
+
+/// Runs a Python script such as `stock_info.py` with the comma separated
+/// symbols as its argument and reads the JSON it prints
+pub struct PythonProvider {
+    script: String,
+}
+
+impl PythonProvider {
+    pub fn new(script: &str) -> Self {
+        Self {
+            script: script.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl MarketDataProvider for PythonProvider {
+    async fn fetch(&self, symbols: &[String]) -> Result<MarketDataBatch, MarketDataError> {
+        let output = tokio::process::Command::new("python3")
+            .arg(&self.script)
+            .arg(symbols.join(","))
+            .output()
+            .await
+            .map_err(|e| {
+                MarketDataError::RequestFailed(format!("Failed to run {}: {}", self.script, e))
+            })?;
+        if !output.status.success() {
+            return Err(MarketDataError::RequestFailed(format!(
+                "{} exited with {:?}: {}",
+                self.script,
+                output.status.code(),
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        let infos: Value = serde_json::from_slice(&output.stdout)
+            .map_err(|e| MarketDataError::ParseError(e.to_string()))?;
+        parse_infos(&infos)
+    }
+}
+
+/// Reads market data from static JSON files, for offline use and tests.
+/// `<symbol>.json` holds the info of one symbol; any other JSON file is an
+/// object of infos keyed by symbol, like the output of `stock_info.py`.
+/// Daily prices are read from `history/<symbol>.json`, an array of bars.
+pub struct DirectoryProvider {
+    dir: PathBuf,
+}
+
+impl DirectoryProvider {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl MarketDataProvider for DirectoryProvider {
+    async fn fetch(&self, symbols: &[String]) -> Result<MarketDataBatch, MarketDataError> {
+        let entries = std::fs::read_dir(&self.dir).map_err(|e| {
+            MarketDataError::RequestFailed(format!("{}: {}", self.dir.display(), e))
+        })?;
+
+        let mut infos = serde_json::Map::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default()
+                .to_string();
+            let content = std::fs::read_to_string(&path).map_err(|e| {
+                MarketDataError::RequestFailed(format!("{}: {}", path.display(), e))
+            })?;
+            let value: Value = serde_json::from_str(&content)
+                .map_err(|e| MarketDataError::ParseError(format!("{}: {}", path.display(), e)))?;
+
+            if symbols.contains(&stem) {
+                infos.insert(stem, value);
+            } else if let Value::Object(map) = value {
+                for (symbol, info) in map {
+                    if symbols.contains(&symbol) {
+                        infos.entry(symbol).or_insert(info);
+                    }
+                }
+            }
+        }
+        parse_infos(&Value::Object(infos))
+    }
+
+    async fn fetch_history(
+        &self,
+        requests: &[(String, NaiveDate)],
+    ) -> Result<HistoryBatch, MarketDataError> {
+        let mut batch = HistoryBatch::default();
+        for (symbol, from) in requests {
+            let path = self.dir.join("history").join(format!("{}.json", symbol));
+            let bars = std::fs::read_to_string(&path)
+                .map_err(|e| format!("{}: {}", path.display(), e))
+                .and_then(|content| {
+                    serde_json::from_str::<Vec<PriceBar>>(&content)
+                        .map_err(|e| format!("{}: {}", path.display(), e))
+                });
+            match bars {
+                Ok(bars) => {
+                    let bars = bars.into_iter().filter(|bar| bar.date >= *from).collect();
+                    batch.bars.insert(symbol.clone(), bars);
+                }
+                Err(error) => {
+                    batch.errors.insert(symbol.clone(), error);
+                }
+            }
+        }
+        Ok(batch)
+    }
+}
+
+/// Queries a web service with `GET <url>?symbols=A,B`. The service answers
+/// with an object of infos keyed by symbol, the same JSON `stock_info.py`
+/// prints, so another data vendor only needs a thin adapter. Daily prices
+/// are requested with `GET <url>?history=A&from=2024-01-31` and come back
+/// as an array of bars.
+pub struct HttpProvider {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpProvider {
+    pub fn new(url: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            url: url.to_string(),
+        }
+    }
+
+    /// Daily bars of `symbol` from `from` onwards
+    async fn history(
+        &self,
+        symbol: &str,
+        from: NaiveDate,
+    ) -> Result<Vec<PriceBar>, MarketDataError> {
+        let response = self
+            .client
+            .get(&self.url)
+            .query(&[("history", symbol.to_string()), ("from", from.to_string())])
+            .send()
+            .await
+            .map_err(|e| MarketDataError::RequestFailed(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(MarketDataError::RequestFailed(format!(
+                "{} returned status code: {}",
+                self.url,
+                response.status()
+            )));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| MarketDataError::ParseError(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl MarketDataProvider for HttpProvider {
+    async fn fetch(&self, symbols: &[String]) -> Result<MarketDataBatch, MarketDataError> {
+        let response = self
+            .client
+            .get(&self.url)
+            .query(&[("symbols", symbols.join(","))])
+            .send()
+            .await
+            .map_err(|e| MarketDataError::RequestFailed(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(MarketDataError::RequestFailed(format!(
+                "{} returned status code: {}",
+                self.url,
+                response.status()
+            )));
+        }
+
+        let infos: Value = response
+            .json()
+            .await
+            .map_err(|e| MarketDataError::ParseError(e.to_string()))?;
+        parse_infos(&infos)
+    }
+
+    async fn fetch_history(
+        &self,
+        requests: &[(String, NaiveDate)],
+    ) -> Result<HistoryBatch, MarketDataError> {
+        let mut batch = HistoryBatch::default();
+        for (symbol, from) in requests {
+            match self.history(symbol, *from).await {
+                Ok(bars) => {
+                    batch.bars.insert(symbol.clone(), bars);
+                }
+                Err(e) => {
+                    batch.errors.insert(symbol.clone(), e.to_string());
+                }
+            }
+        }
+        Ok(batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::spawn_test_server;
+    use axum::{extract::Query, routing::get, Json, Router};
+    use serde_json::json;
+
+    fn info(symbol: &str) -> Value {
+        json!({
+            "symbol": symbol,
+            "longName": format!("{} Inc", symbol),
+            "currency": "USD",
+            "currentPrice": 100.5,
+            "dividendRate": 1.0,
+            "dividendYield": 0.99,
+            "exDividendDate": 1739145600,
+            "dividendDate": 1739404800,
+            "corporateActions": [{ "meta": { "amount": "0.25" } }],
+            "last_4_dividends": { "2024-11-08": 0.25, "2024-08-12": 0.25 },
+            "dividend_history": { "2024-11-08": 0.25, "2024-08-12": 0.25, "2023-11-10": 0.24 },
+            "sector": "Technology",
+            "country": "United States"
+        })
+    }
+
+    #[test]
+    fn test_market_data_from_info() {
+        let data = MarketData::from_info(&info("AAPL")).unwrap();
+        assert_eq!(data.quote.name.as_deref(), Some("AAPL Inc"));
+        assert_eq!(data.quote.price, Some(100.5));
+        assert_eq!(data.dividends.rate, Some(1.0));
+        assert_eq!(data.dividends.next_amount, Some(0.25));
+        assert_eq!(
+            data.dividends.ex_date,
+            DateTime::<Utc>::from_timestamp(1739145600, 0)
+        );
+        assert_eq!(
+            data.dividends.recent[0].date,
+            NaiveDate::from_ymd_opt(2024, 8, 12).unwrap()
+        );
+        assert_eq!(data.dividends.history.len(), 3);
+        assert_eq!(data.profile.sector.as_deref(), Some("Technology"));
+        assert!(MarketData::from_info(&json!({ "error": "HTTPError: 404" })).is_none());
+    }
+
+    #[test]
+    fn test_corporate_actions_from_info() {
+        let mut value = info("BYDDY");
+        value["corporateActions"] = json!([
+            { "header": "Stock Dividend", "meta": {
+                "eventType": "STOCK_DIVIDEND", "dateEpochMs": 1753848000000_i64, "percent": "500%" } },
+            { "header": "Dividend", "meta": {
+                "eventType": "DIVIDEND", "dateEpochMs": 1753243200000_i64, "amount": "0.225" } },
+            { "header": "Special Dividend", "meta": {
+                "eventType": "DIVIDEND", "dateEpochMs": 1753243200000_i64, "amount": "1.5" } }
+        ]);
+        value["split_history"] = json!({ "2020-08-31": 4.0, "2015-01-02": "1:10" });
+        let data = MarketData::from_info(&value).unwrap();
+
+        assert_eq!(data.dividends.next_amount, Some(0.225));
+        let kinds: Vec<CorporateActionKind> = data
+            .corporate_actions
+            .iter()
+            .map(|a| a.kind.clone())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                CorporateActionKind::ReverseSplit { ratio: 0.1 },
+                CorporateActionKind::Split { ratio: 4.0 },
+                CorporateActionKind::SpecialDividend { amount: 1.5 },
+                CorporateActionKind::Split { ratio: 6.0 },
+            ]
+        );
+    }
+
+    /// Provider answering every symbol except `FAIL`, recording the
+    /// symbols of each request
+    #[derive(Default)]
+    struct StubProvider {
+        requests: std::sync::Mutex<Vec<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl MarketDataProvider for StubProvider {
+        async fn fetch(&self, symbols: &[String]) -> Result<MarketDataBatch, MarketDataError> {
+            self.requests.lock().unwrap().push(symbols.to_vec());
+            parse_infos(&Value::Object(
+                symbols
+                    .iter()
+                    .filter(|s| *s != "FAIL")
+                    .map(|s| (s.clone(), info(s)))
+                    .collect(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_fetches_only_missing_and_stale_symbols() {
+        let path = std::env::temp_dir().join(format!("market_cache_{}.json", std::process::id()));
+        let provider = StubProvider::default();
+        let ttl = Duration::from_secs(3600);
+        let now: DateTime<Utc> = "2025-03-05T12:00:00Z".parse().unwrap();
+        let symbols = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let mut cache = MarketDataCache::load(&path);
+        let data = cache
+            .refresh(&provider, "Yahoo", &symbols(&["AAPL", "FAIL"]), ttl, now)
+            .await;
+        assert_eq!(data.data.len(), 1);
+        assert!(data.errors["FAIL"].contains("No data returned"));
+
+        // A new ticker and the failed one are fetched, AAPL is still fresh
+        let mut cache = MarketDataCache::load(&path);
+        let later = now + chrono::Duration::minutes(30);
+        let data = cache
+            .refresh(
+                &provider,
+                "Yahoo",
+                &symbols(&["AAPL", "KO", "FAIL"]),
+                ttl,
+                later,
+            )
+            .await;
+        assert_eq!(data.data.len(), 2);
+        assert!(cache.stale_entry("AAPL", ttl, later).is_none());
+
+        // Expired entries are fetched again
+        let much_later = now + chrono::Duration::hours(2);
+        cache
+            .refresh(
+                &provider,
+                "Yahoo",
+                &symbols(&["AAPL", "KO"]),
+                ttl,
+                much_later,
+            )
+            .await;
+
+        // Nothing is due, until everything is expired by hand
+        cache
+            .refresh(
+                &provider,
+                "Yahoo",
+                &symbols(&["AAPL", "KO"]),
+                ttl,
+                much_later,
+            )
+            .await;
+        cache.expire_all();
+        cache
+            .refresh(&provider, "Yahoo", &symbols(&["KO"]), ttl, much_later)
+            .await;
+
+        // Another provider does not reuse the entries
+        assert_eq!(
+            cache.stale_symbols("Http:http://x", &symbols(&["KO"]), ttl, much_later),
+            vec!["KO"]
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            *provider.requests.lock().unwrap(),
+            vec![
+                symbols(&["AAPL", "FAIL"]),
+                symbols(&["FAIL", "KO"]),
+                symbols(&["AAPL", "KO"]),
+                symbols(&["KO"]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_directory_provider() {
+        let dir = std::env::temp_dir().join(format!("market_data_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("AAPL.json"), info("AAPL").to_string()).unwrap();
+        std::fs::write(
+            dir.join("output.json"),
+            json!({ "MSFT": info("MSFT"), "AAPL": { "error": "stale" }, "KO": info("KO") })
+                .to_string(),
+        )
+        .unwrap();
+
+        let symbols = vec!["AAPL".to_string(), "MSFT".to_string(), "TSLA".to_string()];
+        let data = DirectoryProvider::new(&dir)
+            .fetch(&symbols)
+            .await
+            .unwrap()
+            .data;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut found: Vec<_> = data.keys().cloned().collect();
+        found.sort();
+        assert_eq!(found, vec!["AAPL", "MSFT"]);
+        assert_eq!(data["MSFT"].quote.name.as_deref(), Some("MSFT Inc"));
+    }
+
+    #[tokio::test]
+    async fn test_demo_data_is_readable() {
+        let symbols = vec!["TSLA".to_string(), "BATS.L".to_string()];
+        let data = DirectoryProvider::new(DEMO_MARKET_DATA_DIR)
+            .fetch(&symbols)
+            .await
+            .unwrap()
+            .data;
+        assert_eq!(data.len(), 2);
+        assert_eq!(data["BATS.L"].dividends.yield_percent, Some(6.94));
+        assert_eq!(
+            data["TSLA"].profile.sector.as_deref(),
+            Some("Consumer Cyclical")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_provider() {
+        let app = Router::new().route(
+            "/quotes",
+            get(|Query(query): Query<HashMap<String, String>>| async move {
+                let infos: serde_json::Map<String, Value> = query["symbols"]
+                    .split(',')
+                    .map(|s| (s.to_string(), info(s)))
+                    .collect();
+                Json(Value::Object(infos))
+            }),
+        );
+        let addr = spawn_test_server(app);
+
+        let provider = HttpProvider::new(&format!("http://{}/quotes", addr));
+        let data = provider
+            .fetch(&["AAPL".to_string(), "KO".to_string()])
+            .await
+            .unwrap()
+            .data;
+        assert_eq!(data.len(), 2);
+        assert_eq!(data["KO"].dividends.yield_percent, Some(0.99));
+
+        let missing = HttpProvider::new(&format!("http://{}/missing", addr));
+        assert!(missing.fetch(&["AAPL".to_string()]).await.is_err());
+    }
+}
@@ -16,5 +16,12 @@
 //
 // USE THIS SOFTWARE AT YOUR OWN RISK.
 
+pub mod export;
+pub mod instruments;
+pub mod market_data;
 pub mod orchestrator;
+pub mod price_history;
+pub mod rate_limit;
 pub mod trading212;
+pub mod value_history;
+pub mod yahoo;
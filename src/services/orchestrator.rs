@@ -16,54 +16,37 @@
 //
 // USE THIS SOFTWARE AT YOUR OWN RISK.
 
-use crate::services::trading212::{InstrumentMetadata, RequestType, Trading212Client};
+use crate::models::market::MarketHours;
+use crate::services::trading212::{RequestType, Trading212Client};
 use crate::utils::currency::CurrencyConverter;
 use crate::utils::settings::Config;
 use crate::utils::settings::Mode;
-use serde_json;
 
 pub struct Orchestrator {
     pub currency_converter: CurrencyConverter,
-    pub instrument_metadata: Vec<InstrumentMetadata>,
+    pub market_hours: MarketHours,
 }
 
 impl Orchestrator {
     pub async fn new(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
-        // Initialize Trading212 client for metadata
-        let instrument_metadata = if config.mode == Mode::Demo {
-            // Try to load from saved file
-            if let Ok(file) = std::fs::File::open("./demo_data/demo_instruments.json") {
-                let reader = std::io::BufReader::new(file);
-                match serde_json::from_reader(reader) {
-                    Ok(metadata) => {
-                        println!("Loaded instruments metadata from demo_instruments.json");
-                        metadata
-                    }
+        // Trading schedules are only used to show whether markets are open,
+        // so a failure here should not prevent the portfolio from loading
+        let market_hours = if config.mode == Mode::Demo {
+            MarketHours::default()
+        } else {
+            match Trading212Client::new(RequestType::ExchangesMetadata, config) {
+                Ok(client) => match client.get_exchanges_metadata().await {
+                    Ok(exchanges) => MarketHours::new(exchanges),
                     Err(e) => {
-                        return Err(format!("Failed to parse demo instruments data: {}", e).into());
+                        eprintln!("Failed to get exchanges metadata: {}", e);
+                        MarketHours::default()
                     }
-                }
-            } else {
-                return Err("No demo instruments data available".into());
-            }
-        } else {
-            // Live mode - proceed with Trading212 API
-            let trading212_client =
-                Trading212Client::new(RequestType::InstrumentsMetadata, config)?;
-
-            // Get instrument metadata
-            let metadata = trading212_client.get_instruments_metadata().await?;
-
-            // Save the data for future use in Demo mode
-            if let Ok(file) = std::fs::File::create("demo_instruments.json") {
-                let writer = std::io::BufWriter::new(file);
-                if let Err(e) = serde_json::to_writer_pretty(writer, &metadata) {
-                    eprintln!("Failed to save instruments metadata: {}", e);
-                } else {
-                    println!("Saved instruments metadata to demo_instruments.json");
+                },
+                Err(e) => {
+                    eprintln!("Failed to initialize Trading212 client: {}", e);
+                    MarketHours::default()
                 }
             }
-            metadata
         };
 
         // Create currency converter with fixed rates
@@ -72,7 +55,7 @@ impl Orchestrator {
 
         Ok(Self {
             currency_converter,
-            instrument_metadata,
+            market_hours,
         })
     }
 }
@@ -0,0 +1,409 @@
+// File: price_history.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use chrono::{Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::models::corporate_action::CorporateAction;
+use crate::services::market_data::MarketDataProvider;
+use crate::utils::json_store::{self, Unparsable};
+
+/// File the daily prices of each symbol are stored in
+pub const PRICE_HISTORY_FILE: &str = "price_history.json";
+
+/// How far back the prices of a newly held symbol are fetched
+pub const DEFAULT_HISTORY_MONTHS: u32 = 5 * 12;
+
+/// Prices of one trading day, in the currency of the quote
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PriceBar {
+    pub date: NaiveDate,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// Dividend per share going ex on this day, when the provider reports
+    /// dividends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dividend: Option<f64>,
+}
+
+/// Daily prices of one symbol
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PriceSeries {
+    /// Earliest date that has been requested, the first bar can be later
+    /// when the symbol was listed afterwards
+    pub from: Option<NaiveDate>,
+    /// Day of the last successful update
+    pub updated_on: Option<NaiveDate>,
+    /// The bars are adjusted for the corporate actions up to this day.
+    /// Prices are adjusted by the provider up to the day they are fetched,
+    /// later actions are applied to the stored bars.
+    #[serde(default)]
+    pub adjusted_to: Option<NaiveDate>,
+    /// The bars were fetched with their dividends. Series stored before
+    /// dividends were kept are fetched again once.
+    #[serde(default)]
+    pub with_dividends: bool,
+    /// Bars by date, oldest first
+    pub bars: Vec<PriceBar>,
+}
+
+impl PriceSeries {
+    /// First day that has to be fetched so the series covers `start` up to
+    /// `today`, or `None` if it is complete. The last stored bar is fetched
+    /// again, it may have been taken before the market closed.
+    pub fn missing_from(&self, start: NaiveDate, today: NaiveDate) -> Option<NaiveDate> {
+        match (self.from, self.updated_on, self.bars.last()) {
+            (Some(from), Some(updated_on), last) if from <= start && self.with_dividends => {
+                if updated_on >= today {
+                    None
+                } else {
+                    Some(last.map_or(from, |bar| bar.date))
+                }
+            }
+            _ => Some(start),
+        }
+    }
+
+    /// Adds `bars`, replacing stored bars of the same days
+    pub fn merge(&mut self, bars: Vec<PriceBar>) {
+        let mut by_date: BTreeMap<NaiveDate, PriceBar> =
+            self.bars.drain(..).map(|bar| (bar.date, bar)).collect();
+        by_date.extend(bars.into_iter().map(|bar| (bar.date, bar)));
+        self.bars = by_date.into_values().collect();
+    }
+
+    /// Scales the bars before each action that took effect since the last
+    /// adjustment, so they compare with the prices after it. Returns whether
+    /// any bar changed.
+    pub fn apply_actions(&mut self, actions: &[CorporateAction], today: NaiveDate) -> bool {
+        let Some(adjusted_to) = self.adjusted_to.or(self.updated_on) else {
+            self.adjusted_to = Some(today);
+            return false;
+        };
+        let mut changed = false;
+        for action in actions
+            .iter()
+            .filter(|a| a.date > adjusted_to && a.date <= today)
+        {
+            let factor = action.per_share_factor();
+            changed |= factor != 1.0;
+            for bar in self.bars.iter_mut().filter(|bar| bar.date < action.date) {
+                bar.open *= factor;
+                bar.high *= factor;
+                bar.low *= factor;
+                bar.close *= factor;
+                if let Some(dividend) = &mut bar.dividend {
+                    *dividend *= factor;
+                }
+            }
+        }
+        self.adjusted_to = Some(today);
+        changed
+    }
+
+    /// Close of the last trading day on or before `date`
+    pub fn close_on(&self, date: NaiveDate) -> Option<f64> {
+        let index = self.bars.partition_point(|bar| bar.date <= date);
+        index.checked_sub(1).map(|i| self.bars[i].close)
+    }
+}
+
+/// Daily prices kept on disk per symbol. Each update only fetches the days
+/// since the last stored bar.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PriceHistory {
+    /// Provider the prices were fetched from, see `market_data::source_key`
+    pub source: String,
+    pub series: HashMap<String, PriceSeries>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl PriceHistory {
+    /// Loads the history from `path`. A missing or unreadable file gives an
+    /// empty history.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut history: PriceHistory =
+            json_store::load(&path, "price history", Unparsable::Ignore);
+        history.path = path;
+        history
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        json_store::save(&self.path, self)
+    }
+
+    /// Stored bars of `symbol`, oldest first
+    pub fn bars(&self, symbol: &str) -> &[PriceBar] {
+        self.series
+            .get(symbol)
+            .map(|series| series.bars.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Adjusts the stored bars for new corporate `actions` and fetches the
+    /// days each of `symbols` is missing between `start` and `today`.
+    /// Returns the errors of the symbols that could not be updated; their
+    /// stored bars are kept.
+    pub async fn refresh(
+        &mut self,
+        provider: &dyn MarketDataProvider,
+        source: &str,
+        symbols: &[String],
+        actions: &HashMap<String, Vec<CorporateAction>>,
+        start: NaiveDate,
+        today: NaiveDate,
+    ) -> HashMap<String, String> {
+        if self.source != source {
+            self.series.clear();
+            self.source = source.to_string();
+        }
+        let mut adjusted = false;
+        for (symbol, series) in &mut self.series {
+            if let Some(actions) = actions.get(symbol) {
+                adjusted |= series.apply_actions(actions, today);
+            }
+        }
+
+        let mut requests: Vec<(String, NaiveDate)> = symbols
+            .iter()
+            .filter_map(|symbol| {
+                let from = self
+                    .series
+                    .get(symbol)
+                    .map_or(Some(start), |series| series.missing_from(start, today))?;
+                Some((symbol.clone(), from))
+            })
+            .collect();
+        requests.sort();
+        requests.dedup();
+        if requests.is_empty() {
+            if adjusted {
+                if let Err(e) = self.save() {
+                    eprintln!("Failed to save price history: {}", e);
+                }
+            }
+            return HashMap::new();
+        }
+
+        println!("Fetching price history of {} symbols", requests.len());
+        let fetched = match provider.fetch_history(&requests).await {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                eprintln!("Failed to fetch price history: {}", e);
+                return requests
+                    .into_iter()
+                    .map(|(symbol, _)| (symbol, e.to_string()))
+                    .collect();
+            }
+        };
+
+        let mut errors = fetched.errors;
+        for (symbol, from) in requests {
+            let Some(bars) = fetched.bars.get(&symbol) else {
+                errors
+                    .entry(symbol)
+                    .or_insert_with(|| "No price history returned".to_string());
+                continue;
+            };
+            let series = self.series.entry(symbol).or_default();
+            series.adjusted_to.get_or_insert(today);
+            series.merge(bars.clone());
+            series.from = Some(series.from.map_or(from, |f| f.min(from)));
+            series.updated_on = Some(today);
+            series.with_dividends = true;
+        }
+        for (symbol, error) in &errors {
+            eprintln!("No price history for {}: {}", symbol, error);
+        }
+        if let Err(e) = self.save() {
+            eprintln!("Failed to save price history: {}", e);
+        }
+        errors
+    }
+}
+
+/// First day fetched for a symbol without stored prices
+pub fn default_start(today: NaiveDate) -> NaiveDate {
+    today
+        .checked_sub_months(Months::new(DEFAULT_HISTORY_MONTHS))
+        .unwrap_or(today)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::corporate_action::CorporateActionKind;
+    use crate::services::market_data::{
+        HistoryBatch, MarketDataBatch, MarketDataError, MarketDataProvider,
+    };
+    use async_trait::async_trait;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn bar(day: &str, close: f64) -> PriceBar {
+        PriceBar {
+            date: date(day),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            dividend: None,
+        }
+    }
+
+    /// Provider returning a bar for every requested start day, recording
+    /// the requests
+    #[derive(Default)]
+    struct StubProvider {
+        requests: std::sync::Mutex<Vec<Vec<(String, NaiveDate)>>>,
+    }
+
+    #[async_trait]
+    impl MarketDataProvider for StubProvider {
+        async fn fetch(&self, _symbols: &[String]) -> Result<MarketDataBatch, MarketDataError> {
+            Ok(MarketDataBatch::default())
+        }
+
+        async fn fetch_history(
+            &self,
+            requests: &[(String, NaiveDate)],
+        ) -> Result<HistoryBatch, MarketDataError> {
+            self.requests.lock().unwrap().push(requests.to_vec());
+            let mut batch = HistoryBatch::default();
+            for (symbol, from) in requests {
+                let close = if symbol == "AAPL" { 150.0 } else { 10.0 };
+                batch.bars.insert(
+                    symbol.clone(),
+                    vec![PriceBar {
+                        date: *from,
+                        ..bar("2025-01-01", close)
+                    }],
+                );
+            }
+            Ok(batch)
+        }
+    }
+
+    #[test]
+    fn test_merge_replaces_days() {
+        let mut series = PriceSeries::default();
+        series.merge(vec![bar("2025-03-04", 1.0), bar("2025-03-03", 2.0)]);
+        series.merge(vec![bar("2025-03-04", 3.0), bar("2025-03-05", 4.0)]);
+        let closes: Vec<f64> = series.bars.iter().map(|b| b.close).collect();
+        assert_eq!(closes, vec![2.0, 3.0, 4.0]);
+        assert_eq!(series.close_on(date("2025-03-08")), Some(4.0));
+        assert_eq!(series.close_on(date("2025-03-01")), None);
+    }
+
+    #[test]
+    fn test_apply_actions_once() {
+        let mut series = PriceSeries {
+            updated_on: Some(date("2025-03-03")),
+            bars: vec![bar("2025-03-03", 400.0), bar("2025-03-05", 100.0)],
+            ..PriceSeries::default()
+        };
+        // The bar of the split day was fetched after the split
+        let actions = vec![CorporateAction {
+            date: date("2025-03-04"),
+            kind: CorporateActionKind::Split { ratio: 4.0 },
+        }];
+        assert!(series.apply_actions(&actions, date("2025-03-05")));
+        assert!(!series.apply_actions(&actions, date("2025-03-06")));
+        let closes: Vec<f64> = series.bars.iter().map(|b| b.close).collect();
+        assert_eq!(closes, vec![100.0, 100.0]);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_is_incremental() {
+        let path = std::env::temp_dir().join(format!("price_history_{}.json", std::process::id()));
+        let provider = StubProvider::default();
+        let start = date("2024-01-01");
+        let symbols = vec!["AAPL".to_string()];
+        let actions = HashMap::new();
+
+        let mut history = PriceHistory::load(&path);
+        let errors = history
+            .refresh(
+                &provider,
+                "Yahoo",
+                &symbols,
+                &actions,
+                start,
+                date("2025-03-03"),
+            )
+            .await;
+        assert!(errors.is_empty());
+
+        // Nothing is due on the same day
+        let mut history = PriceHistory::load(&path);
+        assert_eq!(history.bars("AAPL").len(), 1);
+        history
+            .refresh(
+                &provider,
+                "Yahoo",
+                &symbols,
+                &actions,
+                start,
+                date("2025-03-03"),
+            )
+            .await;
+
+        // The next day continues from the last bar, an earlier start
+        // fetches the whole range again
+        history
+            .refresh(
+                &provider,
+                "Yahoo",
+                &symbols,
+                &actions,
+                start,
+                date("2025-03-04"),
+            )
+            .await;
+        history
+            .refresh(
+                &provider,
+                "Yahoo",
+                &symbols,
+                &actions,
+                date("2023-01-01"),
+                date("2025-03-04"),
+            )
+            .await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            *provider.requests.lock().unwrap(),
+            vec![
+                vec![("AAPL".to_string(), start)],
+                vec![("AAPL".to_string(), start)],
+                vec![("AAPL".to_string(), date("2023-01-01"))],
+            ]
+        );
+        assert_eq!(history.bars("AAPL").len(), 2);
+    }
+}
@@ -0,0 +1,273 @@
+// File: rate_limit.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::DateTime;
+use once_cell::sync::Lazy;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+/// Rate limit state shared by every `Trading212Client`, keyed by endpoint
+/// and account (see `limit_key`). Clients are created per request type, so
+/// the state has to outlive them.
+pub static RATE_LIMITER: Lazy<RateLimiter> = Lazy::new(RateLimiter::default);
+
+/// Maximum number of retries of a request that hit a 429 or a 5xx response.
+/// Requests that are not idempotent, such as export requests, are only
+/// retried on a 429.
+pub const MAX_RETRIES: u32 = 4;
+
+/// Delay before the first retry when the API gives no reset time
+pub const BASE_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Upper bound of any single wait, so a bad header cannot stall a refresh
+const MAX_WAIT: Duration = Duration::from_secs(120);
+
+/// Throttle state of a single endpoint, taken from the `x-ratelimit-*`
+/// headers of its last response
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EndpointLimit {
+    pub limit: Option<u32>,
+    pub remaining: Option<u32>,
+    /// Unix time (seconds) at which the quota of the endpoint is restored
+    pub reset: Option<u64>,
+}
+
+impl EndpointLimit {
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let parse = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+        };
+
+        let limit = EndpointLimit {
+            limit: parse("x-ratelimit-limit").map(|v| v as u32),
+            remaining: parse("x-ratelimit-remaining").map(|v| v as u32),
+            reset: parse("x-ratelimit-reset"),
+        };
+        if limit == EndpointLimit::default() {
+            None
+        } else {
+            Some(limit)
+        }
+    }
+
+    /// Time left until the quota is restored, if the quota is used up
+    pub fn wait_time(&self, now: u64) -> Option<Duration> {
+        match (self.remaining, self.reset) {
+            (Some(0), Some(reset)) if reset > now => {
+                Some(Duration::from_secs(reset - now).min(MAX_WAIT))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    endpoints: Mutex<HashMap<String, EndpointLimit>>,
+}
+
+impl RateLimiter {
+    /// Time the caller has to wait before calling `endpoint` again
+    pub fn wait_time(&self, endpoint: &str) -> Option<Duration> {
+        let endpoints = self.endpoints.lock().unwrap();
+        endpoints
+            .get(endpoint)
+            .and_then(|limit| limit.wait_time(unix_now()))
+    }
+
+    /// Records the rate limit headers of a response of `endpoint`
+    pub fn update(&self, endpoint: &str, headers: &HeaderMap) {
+        if let Some(limit) = EndpointLimit::from_headers(headers) {
+            if limit.remaining == Some(0) {
+                println!(
+                    "Rate limit reached for {} (limit {}), resets in {}s",
+                    endpoint,
+                    limit.limit.map(|l| l.to_string()).unwrap_or("?".into()),
+                    limit
+                        .reset
+                        .map(|r| r.saturating_sub(unix_now()))
+                        .unwrap_or(0)
+                );
+            }
+            self.endpoints
+                .lock()
+                .unwrap()
+                .insert(endpoint.to_string(), limit);
+        }
+    }
+
+    /// How long to wait before retrying a throttled or failed request.
+    /// The `Retry-After` header of the response comes first, then the reset
+    /// time of the endpoint, otherwise the delay doubles with every attempt,
+    /// starting at `base`.
+    pub fn retry_delay(
+        &self,
+        endpoint: &str,
+        headers: &HeaderMap,
+        attempt: u32,
+        base: Duration,
+    ) -> Duration {
+        retry_after(headers, unix_now())
+            .or_else(|| self.wait_time(endpoint))
+            .unwrap_or_else(|| backoff(base, attempt))
+    }
+}
+
+/// Delay asked for by a `Retry-After` header, given either in seconds or
+/// as an HTTP date
+pub fn retry_after(headers: &HeaderMap, now: u64) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    let seconds = match value.parse::<u64>() {
+        Ok(seconds) => seconds,
+        Err(_) => {
+            let at = DateTime::parse_from_rfc2822(value).ok()?.timestamp();
+            u64::try_from(at).ok()?.saturating_sub(now)
+        }
+    };
+    Some(Duration::from_secs(seconds).min(MAX_WAIT))
+}
+
+/// Exponential backoff from `base` for the given retry attempt, starting
+/// at zero
+pub fn backoff(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_WAIT)
+}
+
+/// Key used to throttle a request URL. Pie and export ids are replaced so
+/// that every detail request shares the limit of its endpoint.
+pub fn endpoint_key(url: &str) -> String {
+    let path = url
+        .split_once("://")
+        .map(|(_, rest)| rest.find('/').map(|i| &rest[i..]).unwrap_or("/"))
+        .unwrap_or(url);
+    let path = path.split('?').next().unwrap_or(path);
+
+    path.split('/')
+        .map(|segment| {
+            if !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()) {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Key the limit of `endpoint` is tracked under for the account with
+/// `api_key`. Trading 212 counts the requests of every API key separately,
+/// so one account using up its quota must not hold back another. The key is
+/// hashed so that it never shows up in the logs.
+pub fn limit_key(endpoint: &str, api_key: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    api_key.hash(&mut hasher);
+    format!("{}#{:016x}", endpoint, hasher.finish())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_endpoint_key() {
+        assert_eq!(
+            endpoint_key("https://live.trading212.com/api/v0/equity/pies/1234"),
+            "/api/v0/equity/pies/{id}"
+        );
+        assert_eq!(
+            endpoint_key("https://live.trading212.com/api/v0/history/orders?cursor=5&limit=50"),
+            "/api/v0/history/orders"
+        );
+    }
+
+    #[test]
+    fn test_limits_are_kept_per_account() {
+        let limiter = RateLimiter::default();
+        let endpoint = "/api/v0/equity/pies/{id}";
+        let (isa, invest) = (
+            limit_key(endpoint, "isa-key"),
+            limit_key(endpoint, "invest-key"),
+        );
+        assert_ne!(isa, invest);
+        assert!(!isa.contains("isa-key"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        let reset = (unix_now() + 30).to_string();
+        headers.insert("x-ratelimit-reset", HeaderValue::from_str(&reset).unwrap());
+        limiter.update(&isa, &headers);
+        assert!(limiter.wait_time(&isa).is_some());
+        assert!(limiter.wait_time(&invest).is_none());
+    }
+
+    #[test]
+    fn test_wait_time_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", HeaderValue::from_static("6"));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("1000"));
+
+        let limit = EndpointLimit::from_headers(&headers).unwrap();
+        assert_eq!(limit.limit, Some(6));
+        assert_eq!(limit.wait_time(990), Some(Duration::from_secs(10)));
+        assert_eq!(limit.wait_time(1000), None);
+        assert!(EndpointLimit::from_headers(&HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers, 0), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers, 0), Some(Duration::from_secs(7)));
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(
+            retry_after(&headers, 1_445_412_470),
+            Some(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        assert_eq!(backoff(BASE_BACKOFF, 0), Duration::from_secs(2));
+        assert_eq!(backoff(BASE_BACKOFF, 2), Duration::from_secs(8));
+        assert_eq!(backoff(BASE_BACKOFF, 30), MAX_WAIT);
+    }
+}
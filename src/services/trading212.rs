@@ -17,13 +17,28 @@
 // USE THIS SOFTWARE AT YOUR OWN RISK.
 
 use crate::{
-    models::portfolio::{DividendPrediction, Position},
+    models::{
+        dividend::{DividendGrowth, DividendPayment},
+        market::MarketStatus,
+        order::{Order, OrderSide},
+        pie::{DividendCashAction, Pie, PieInstrument},
+        portfolio::{DividendPrediction, Position},
+        transaction::{CashTransaction, TransactionKind},
+    },
+    services::{
+        market_data::Profile,
+        rate_limit::{endpoint_key, limit_key, BASE_BACKOFF, MAX_RETRIES, RATE_LIMITER},
+    },
     utils::settings::Config,
 };
+use chrono::{DateTime, Utc};
 use reqwest::header::{HeaderMap, HeaderValue};
-use serde::{Deserialize, Serialize};
-use std::env;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
 use thiserror::Error;
+use tokio::time::sleep;
 
 #[derive(Error, Debug)]
 pub enum Trading212Error {
@@ -51,6 +66,217 @@ struct Trading212Position {
     currency: Option<String>,
 }
 
+/// Maximum page size accepted by the paginated history endpoints
+const HISTORY_PAGE_LIMIT: u32 = 50;
+
+/// Envelope used by the paginated `/history/*` endpoints. `next_page_path`
+/// is a path relative to the API host and is `None` on the last page.
+#[derive(Debug, Deserialize)]
+struct PaginatedResponse<T> {
+    items: Vec<T>,
+    #[serde(rename = "nextPagePath")]
+    next_page_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Trading212Tax {
+    #[serde(default)]
+    quantity: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Trading212Order {
+    id: i64,
+    ticker: String,
+    #[serde(rename = "type")]
+    order_type: Option<String>,
+    status: Option<String>,
+    #[serde(rename = "orderedQuantity")]
+    ordered_quantity: Option<f64>,
+    #[serde(rename = "filledQuantity")]
+    filled_quantity: Option<f64>,
+    #[serde(rename = "fillPrice")]
+    fill_price: Option<f64>,
+    #[serde(rename = "filledValue")]
+    filled_value: Option<f64>,
+    #[serde(default)]
+    taxes: Vec<Trading212Tax>,
+    #[serde(rename = "dateCreated")]
+    date_created: Option<DateTime<Utc>>,
+    #[serde(rename = "dateExecuted")]
+    date_executed: Option<DateTime<Utc>>,
+}
+
+impl From<Trading212Order> for Order {
+    fn from(o: Trading212Order) -> Self {
+        let filled_quantity = o.filled_quantity.unwrap_or(0.0);
+        let side = if filled_quantity < 0.0 || o.ordered_quantity.unwrap_or(0.0) < 0.0 {
+            OrderSide::Sell
+        } else {
+            OrderSide::Buy
+        };
+        let quantity = filled_quantity.abs();
+        let fill_price = o.fill_price.unwrap_or(0.0);
+        let filled_value = o.filled_value.map(f64::abs).unwrap_or(0.0);
+
+        Order {
+            id: o.id,
+            ticker: o.ticker,
+            order_type: o.order_type.unwrap_or_default(),
+            status: o.status.unwrap_or_default(),
+            side,
+            quantity,
+            fill_price,
+            filled_value,
+            fees: o.taxes.iter().map(|t| t.quantity.abs()).sum(),
+            fx_rate: Order::derive_fx_rate(quantity, fill_price, filled_value),
+            date_created: o.date_created,
+            date_executed: o.date_executed,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Trading212Dividend {
+    ticker: String,
+    reference: Option<String>,
+    quantity: f64,
+    amount: f64,
+    #[serde(rename = "grossAmountPerShare")]
+    gross_amount_per_share: Option<f64>,
+    #[serde(rename = "paidOn")]
+    paid_on: DateTime<Utc>,
+    #[serde(rename = "type")]
+    dividend_type: Option<String>,
+}
+
+impl From<Trading212Dividend> for DividendPayment {
+    fn from(d: Trading212Dividend) -> Self {
+        DividendPayment {
+            ticker: d.ticker,
+            reference: d.reference.unwrap_or_default(),
+            quantity: d.quantity,
+            amount: d.amount,
+            gross_amount_per_share: d.gross_amount_per_share.unwrap_or(0.0),
+            paid_on: d.paid_on,
+            dividend_type: d.dividend_type.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Trading212Transaction {
+    #[serde(rename = "type")]
+    kind: String,
+    amount: f64,
+    reference: Option<String>,
+    #[serde(rename = "dateTime")]
+    date_time: DateTime<Utc>,
+}
+
+impl From<Trading212Transaction> for CashTransaction {
+    fn from(t: Trading212Transaction) -> Self {
+        CashTransaction {
+            kind: TransactionKind::from(t.kind.as_str()),
+            amount: t.amount,
+            reference: t.reference.unwrap_or_default(),
+            date_time: t.date_time,
+        }
+    }
+}
+
+/// Invested value, current value and result of a pie or of one of its
+/// instruments, in the account currency
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Trading212PieResult {
+    #[serde(rename = "priceAvgInvestedValue", default)]
+    invested_value: f64,
+    #[serde(rename = "priceAvgValue", default)]
+    value: f64,
+    #[serde(rename = "priceAvgResult", default)]
+    result: f64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Trading212PieDividends {
+    #[serde(default)]
+    gained: f64,
+    #[serde(default)]
+    reinvested: f64,
+    #[serde(rename = "inCash", default)]
+    in_cash: f64,
+}
+
+/// Entry of the `/equity/pies` list
+#[derive(Debug, Clone, Deserialize)]
+pub struct PieSummary {
+    pub id: i64,
+    #[serde(default)]
+    cash: f64,
+    #[serde(rename = "dividendDetails", default)]
+    dividend_details: Trading212PieDividends,
+    #[serde(default)]
+    result: Trading212PieResult,
+    status: Option<String>,
+}
+
+impl PieSummary {
+    /// Copies the status and totals of the list entry to `pie`, so that
+    /// details fetched earlier show the current values
+    pub fn apply_to(&self, pie: &mut Pie) {
+        pie.status = self.status.clone().unwrap_or_default();
+        pie.cash = self.cash;
+        pie.invested = self.result.invested_value;
+        pie.value = self.result.value;
+        pie.result = self.result.result;
+        pie.dividend_gained = self.dividend_details.gained;
+        pie.dividend_reinvested = self.dividend_details.reinvested;
+        pie.dividend_in_cash = self.dividend_details.in_cash;
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Trading212PieInstrument {
+    ticker: String,
+    #[serde(rename = "currentShare", default)]
+    current_share: f64,
+    #[serde(rename = "expectedShare", default)]
+    expected_share: f64,
+    #[serde(rename = "ownedQuantity", default)]
+    owned_quantity: f64,
+    #[serde(default)]
+    result: Trading212PieResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct Trading212PieSettings {
+    name: String,
+    #[serde(rename = "dividendCashAction")]
+    dividend_cash_action: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Trading212PieDetails {
+    #[serde(default)]
+    instruments: Vec<Trading212PieInstrument>,
+    settings: Trading212PieSettings,
+}
+
+impl From<Trading212PieInstrument> for PieInstrument {
+    fn from(i: Trading212PieInstrument) -> Self {
+        PieInstrument {
+            ticker: i.ticker,
+            current_share: i.current_share,
+            expected_share: i.expected_share,
+            owned_quantity: i.owned_quantity,
+            invested: i.result.invested_value,
+            value: i.result.value,
+            result: i.result.result,
+            position: None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExportRequest {
     #[serde(rename = "dataIncluded")]
@@ -61,7 +287,7 @@ pub struct ExportRequest {
     pub time_to: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DataIncluded {
     #[serde(rename = "includeDividends")]
     pub include_dividends: bool,
@@ -94,10 +320,73 @@ pub struct ExportInfo {
     pub time_to: String,
 }
 
+/// Cash breakdown of the account as returned by `/equity/account/cash`.
+/// All amounts are in the account currency.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountCash {
+    pub free: f64,
+    pub total: f64,
+    pub ppl: f64,
+    pub result: f64,
+    pub invested: f64,
+    #[serde(rename = "pieCash", default)]
+    pub pie_cash: f64,
+    #[serde(default)]
+    pub blocked: Option<f64>,
+}
+
+impl AccountCash {
+    /// Cash that is held in the account but not invested in any position.
+    pub fn uninvested(&self) -> f64 {
+        self.free + self.blocked.unwrap_or(0.0) + self.pie_cash
+    }
+
+    /// Adds up the balances of several accounts
+    pub fn sum<'a>(cash: impl Iterator<Item = &'a AccountCash>) -> Self {
+        cash.fold(Self::default(), |acc, c| Self {
+            free: acc.free + c.free,
+            total: acc.total + c.total,
+            ppl: acc.ppl + c.ppl,
+            result: acc.result + c.result,
+            invested: acc.invested + c.invested,
+            pie_cash: acc.pie_cash + c.pie_cash,
+            blocked: match (acc.blocked, c.blocked) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+            },
+        })
+    }
+
+    /// Returns a copy with every amount multiplied by `factor`, used to
+    /// move the figures from the account currency into the base currency.
+    pub fn converted(&self, factor: f64) -> Self {
+        Self {
+            free: self.free * factor,
+            total: self.total * factor,
+            ppl: self.ppl * factor,
+            result: self.result * factor,
+            invested: self.invested * factor,
+            pie_cash: self.pie_cash * factor,
+            blocked: self.blocked.map(|b| b * factor),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountInfo {
+    #[serde(rename = "currencyCode")]
+    pub currency_code: String,
+    pub id: i64,
+}
+
 pub struct Trading212Client {
     pub client: reqwest::Client,
+    pub host: String,
     pub base_url: String,
     pub headers: HeaderMap,
+    /// Delay before the first retry of a 429 or 5xx response
+    pub retry_backoff: Duration,
+    api_key: String,
 }
 
 #[derive(PartialEq)]
@@ -106,6 +395,12 @@ pub enum RequestType {
     DividendsPaid,
     Export,
     InstrumentsMetadata,
+    AccountCash,
+    AccountInfo,
+    OrderHistory,
+    Transactions,
+    Pies,
+    ExchangesMetadata,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -124,6 +419,31 @@ pub struct InstrumentMetadata {
     pub ticker: String,
     #[serde(rename = "type")]
     pub instrument_type: String,
+    #[serde(rename = "workingScheduleId", default)]
+    pub working_schedule_id: Option<i64>,
+}
+
+/// Opening, closing or break event of a trading schedule
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TimeEvent {
+    pub date: DateTime<Utc>,
+    #[serde(rename = "type")]
+    pub event_type: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WorkingSchedule {
+    pub id: i64,
+    #[serde(rename = "timeEvents", default)]
+    pub time_events: Vec<TimeEvent>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Exchange {
+    pub id: i64,
+    pub name: String,
+    #[serde(rename = "workingSchedules", default)]
+    pub working_schedules: Vec<WorkingSchedule>,
 }
 
 impl Trading212Client {
@@ -133,40 +453,25 @@ impl Trading212Client {
             None => return Err(Trading212Error::MissingApiKey),
         };
 
-        #[allow(unused_assignments)]
-        let mut base_url = "".to_string();
+        config
+            .api_environment
+            .validate()
+            .map_err(|e| Trading212Error::RequestFailed(e.to_string()))?;
+        let host = config.api_environment.base_url();
 
-        let target = env::var("T212_TARGET").unwrap_or_else(|_| "live".to_string());
-        match rqst_type {
-            RequestType::Portfolio => {
-                base_url = if target == "live" {
-                    "https://live.trading212.com/api/v0/equity/portfolio".to_string()
-                } else {
-                    "https://demo.trading212.com/api/v0/equity/portfolio".to_string()
-                };
-            }
-            RequestType::DividendsPaid => {
-                base_url = if target == "live" {
-                    "https://live.trading212.com/api/v0/history/dividends".to_string()
-                } else {
-                    "https://demo.trading212.com/api/v0/history/dividends".to_string()
-                };
-            }
-            RequestType::Export => {
-                base_url = if target == "live" {
-                    "https://live.trading212.com/api/v0/history/exports".to_string()
-                } else {
-                    "https://demo.trading212.com/api/v0/history/exports".to_string()
-                };
-            }
-            RequestType::InstrumentsMetadata => {
-                base_url = if target == "live" {
-                    "https://live.trading212.com/api/v0/equity/metadata/instruments".to_string()
-                } else {
-                    "https://demo.trading212.com/api/v0/equity/metadata/instruments".to_string()
-                };
-            }
-        }
+        let path = match rqst_type {
+            RequestType::Portfolio => "/api/v0/equity/portfolio",
+            RequestType::DividendsPaid => "/api/v0/history/dividends",
+            RequestType::Export => "/api/v0/history/exports",
+            RequestType::InstrumentsMetadata => "/api/v0/equity/metadata/instruments",
+            RequestType::AccountCash => "/api/v0/equity/account/cash",
+            RequestType::AccountInfo => "/api/v0/equity/account/info",
+            RequestType::OrderHistory => "/api/v0/equity/history/orders",
+            RequestType::Transactions => "/api/v0/history/transactions",
+            RequestType::Pies => "/api/v0/equity/pies",
+            RequestType::ExchangesMetadata => "/api/v0/equity/metadata/exchanges",
+        };
+        let base_url = format!("{}{}", host, path);
 
         let mut headers = HeaderMap::new();
         headers.insert(
@@ -183,20 +488,79 @@ impl Trading212Client {
 
         Ok(Self {
             client,
+            host: host.to_string(),
             base_url,
             headers,
+            retry_backoff: BASE_BACKOFF,
+            api_key,
         })
     }
 
-    pub async fn get_open_positions(&self) -> Result<Vec<Position>, Trading212Error> {
-        // Live mode - make API request
-        let response = self
-            .client
-            .get(&self.base_url)
+    /// Sets the delay before the first retry, which doubles with every
+    /// further attempt
+    pub fn with_retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Sends a request through the shared rate limiter. Waits while the
+    /// endpoint is throttled and retries 429 responses, and 5xx responses of
+    /// idempotent requests, with backoff. A POST that failed on the server
+    /// may still have taken effect, so it is not sent again.
+    async fn send(&self, request: RequestBuilder) -> Result<Response, Trading212Error> {
+        let request = request
             .headers(self.headers.clone())
-            .send()
-            .await
+            .build()
             .map_err(|e| Trading212Error::RequestFailed(e.to_string()))?;
+        let endpoint = endpoint_key(request.url().as_str());
+        let key = limit_key(&endpoint, &self.api_key);
+
+        let mut attempt = 0;
+        loop {
+            if let Some(wait) = RATE_LIMITER.wait_time(&key) {
+                println!(
+                    "Throttling {}: waiting {}s for the rate limit to reset",
+                    endpoint,
+                    wait.as_secs()
+                );
+                sleep(wait).await;
+            }
+
+            let attempt_request = request.try_clone().ok_or_else(|| {
+                Trading212Error::RequestFailed("Request body cannot be retried".to_string())
+            })?;
+            let response = self
+                .client
+                .execute(attempt_request)
+                .await
+                .map_err(|e| Trading212Error::RequestFailed(e.to_string()))?;
+            RATE_LIMITER.update(&key, response.headers());
+
+            let status = response.status();
+            let retryable = status == StatusCode::TOO_MANY_REQUESTS
+                || (status.is_server_error() && request.method().is_idempotent());
+            if !retryable || attempt >= MAX_RETRIES {
+                return Ok(response);
+            }
+
+            let delay =
+                RATE_LIMITER.retry_delay(&key, response.headers(), attempt, self.retry_backoff);
+            attempt += 1;
+            println!(
+                "{} returned {}, retrying in {:.1}s (attempt {}/{})",
+                endpoint,
+                status,
+                delay.as_secs_f64(),
+                attempt,
+                MAX_RETRIES
+            );
+            sleep(delay).await;
+        }
+    }
+
+    pub async fn get_open_positions(&self) -> Result<Vec<Position>, Trading212Error> {
+        // Live mode - make API request
+        let response = self.send(self.client.get(&self.base_url)).await?;
 
         if !response.status().is_success() {
             return Err(Trading212Error::RequestFailed(format!(
@@ -231,6 +595,17 @@ impl Trading212Client {
                 yf_ticker: String::new(),
                 wht: 0.0,
                 div_prediction: DividendPrediction::default(),
+                account: String::new(),
+                exchange: String::new(),
+                working_schedule_id: None,
+                market_status: MarketStatus::default(),
+                data_issues: Vec::new(),
+                dividend_history: Vec::new(),
+                dividend_growth: DividendGrowth::default(),
+                corporate_actions: Vec::new(),
+                gbp_rate: None,
+                profile: Profile::default(),
+                instrument_type: String::new(),
             })
             .collect();
 
@@ -243,13 +618,8 @@ impl Trading212Client {
     ) -> Result<ExportResponse, Trading212Error> {
         println!("Sending export request to: {}", self.base_url);
         let response = self
-            .client
-            .post(&self.base_url)
-            .headers(self.headers.clone())
-            .json(request)
-            .send()
-            .await
-            .map_err(|e| Trading212Error::RequestFailed(e.to_string()))?;
+            .send(self.client.post(&self.base_url).json(request))
+            .await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -274,13 +644,7 @@ impl Trading212Client {
         report_id: i64,
     ) -> Result<Option<ExportInfo>, Trading212Error> {
         println!("Checking export status at: {}", self.base_url);
-        let response = self
-            .client
-            .get(&self.base_url)
-            .headers(self.headers.clone())
-            .send()
-            .await
-            .map_err(|e| Trading212Error::RequestFailed(e.to_string()))?;
+        let response = self.send(self.client.get(&self.base_url)).await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -334,13 +698,7 @@ impl Trading212Client {
     ) -> Result<Vec<InstrumentMetadata>, Trading212Error> {
         // Live mode - make API request
         println!("Sending export request to: {}", self.base_url);
-        let response = self
-            .client
-            .get(&self.base_url)
-            .headers(self.headers.clone())
-            .send()
-            .await
-            .map_err(|e| Trading212Error::RequestFailed(e.to_string()))?;
+        let response = self.send(self.client.get(&self.base_url)).await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -362,4 +720,315 @@ impl Trading212Client {
 
         Ok(metadata)
     }
+
+    pub async fn get_account_cash(&self) -> Result<AccountCash, Trading212Error> {
+        let response = self.send(self.client.get(&self.base_url)).await?;
+
+        if !response.status().is_success() {
+            return Err(Trading212Error::RequestFailed(format!(
+                "API returned status code: {}",
+                response.status()
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| Trading212Error::ParseError(e.to_string()))
+    }
+
+    pub async fn get_account_info(&self) -> Result<AccountInfo, Trading212Error> {
+        let response = self.send(self.client.get(&self.base_url)).await?;
+
+        if !response.status().is_success() {
+            return Err(Trading212Error::RequestFailed(format!(
+                "API returned status code: {}",
+                response.status()
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| Trading212Error::ParseError(e.to_string()))
+    }
+
+    /// Fetches every page of a paginated history endpoint, following the
+    /// `nextPagePath` cursor returned by the API until the last page.
+    async fn get_all_pages<T: DeserializeOwned>(
+        &self,
+        query: &[(&str, String)],
+    ) -> Result<Vec<T>, Trading212Error> {
+        self.get_pages_until(query, |_| false).await
+    }
+
+    /// Fetches the pages of a paginated history endpoint, newest first,
+    /// until the last page or the first page with an item that `reached`
+    /// is true for.
+    async fn get_pages_until<T: DeserializeOwned>(
+        &self,
+        query: &[(&str, String)],
+        reached: impl Fn(&T) -> bool,
+    ) -> Result<Vec<T>, Trading212Error> {
+        let mut items = Vec::new();
+        let mut request = self
+            .client
+            .get(&self.base_url)
+            .query(&[("limit", HISTORY_PAGE_LIMIT.to_string())])
+            .query(query);
+
+        loop {
+            let response = self.send(request).await?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let error_text = response
+                    .text()
+                    .await
+                    .unwrap_or_else(|_| "Unknown error".to_string());
+                return Err(Trading212Error::RequestFailed(format!(
+                    "API returned status code: {} - {}",
+                    status, error_text
+                )));
+            }
+
+            let page: PaginatedResponse<T> = response
+                .json()
+                .await
+                .map_err(|e| Trading212Error::ParseError(e.to_string()))?;
+            let done = page.items.iter().any(&reached);
+            items.extend(page.items);
+            if done {
+                break;
+            }
+
+            match page.next_page_path {
+                Some(path) if !path.is_empty() => {
+                    request = self.client.get(format!("{}{}", self.host, path));
+                }
+                _ => break,
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Fetches the complete order history, optionally restricted to one ticker.
+    pub async fn get_order_history(
+        &self,
+        ticker: Option<&str>,
+    ) -> Result<Vec<Order>, Trading212Error> {
+        let mut query = Vec::new();
+        if let Some(ticker) = ticker {
+            query.push(("ticker", ticker.to_string()));
+        }
+
+        let orders: Vec<Trading212Order> = self.get_all_pages(&query).await?;
+        Ok(orders.into_iter().map(Order::from).collect())
+    }
+
+    /// Fetches the orders placed since the newest of the `known` orders.
+    /// The history is returned newest first, so paging stops at the first
+    /// page that reaches a known order.
+    pub async fn get_order_history_since(
+        &self,
+        known: &HashSet<i64>,
+    ) -> Result<Vec<Order>, Trading212Error> {
+        let orders: Vec<Trading212Order> = self
+            .get_pages_until(&[], |order: &Trading212Order| known.contains(&order.id))
+            .await?;
+        Ok(orders.into_iter().map(Order::from).collect())
+    }
+
+    /// Fetches every deposit, withdrawal, fee and transfer of the account
+    pub async fn get_transactions(&self) -> Result<Vec<CashTransaction>, Trading212Error> {
+        self.get_transactions_since(&HashSet::new()).await
+    }
+
+    /// Fetches the transactions newer than the ones whose `key` is in
+    /// `known`, stopping at the first page that holds one of them
+    pub async fn get_transactions_since(
+        &self,
+        known: &HashSet<String>,
+    ) -> Result<Vec<CashTransaction>, Trading212Error> {
+        let transactions: Vec<Trading212Transaction> = self
+            .get_pages_until(&[], |t: &Trading212Transaction| {
+                known.contains(&CashTransaction::from(t.clone()).key())
+            })
+            .await?;
+        Ok(transactions
+            .into_iter()
+            .map(CashTransaction::from)
+            .collect())
+    }
+
+    /// Fetches every dividend paid into the account
+    pub async fn get_dividend_history(&self) -> Result<Vec<DividendPayment>, Trading212Error> {
+        let dividends: Vec<Trading212Dividend> = self.get_all_pages(&[]).await?;
+        Ok(dividends.into_iter().map(DividendPayment::from).collect())
+    }
+
+    /// Fetches the list of pies with their totals
+    pub async fn get_pies(&self) -> Result<Vec<PieSummary>, Trading212Error> {
+        let response = self.send(self.client.get(&self.base_url)).await?;
+
+        if !response.status().is_success() {
+            return Err(Trading212Error::RequestFailed(format!(
+                "API returned status code: {}",
+                response.status()
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| Trading212Error::ParseError(e.to_string()))
+    }
+
+    /// Fetches the settings and instruments of a pie from the list
+    pub async fn get_pie(&self, summary: &PieSummary) -> Result<Pie, Trading212Error> {
+        let response = self
+            .send(self.client.get(format!("{}/{}", self.base_url, summary.id)))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(Trading212Error::RequestFailed(format!(
+                "API returned status code: {}",
+                response.status()
+            )));
+        }
+
+        let details: Trading212PieDetails = response
+            .json()
+            .await
+            .map_err(|e| Trading212Error::ParseError(e.to_string()))?;
+
+        let mut pie = Pie {
+            id: summary.id,
+            name: details.settings.name,
+            status: String::new(),
+            cash: 0.0,
+            invested: 0.0,
+            value: 0.0,
+            result: 0.0,
+            dividend_gained: 0.0,
+            dividend_reinvested: 0.0,
+            dividend_in_cash: 0.0,
+            dividend_cash_action: DividendCashAction::from(
+                details
+                    .settings
+                    .dividend_cash_action
+                    .as_deref()
+                    .unwrap_or_default(),
+            ),
+            instruments: details
+                .instruments
+                .into_iter()
+                .map(PieInstrument::from)
+                .collect(),
+        };
+        summary.apply_to(&mut pie);
+        Ok(pie)
+    }
+
+    /// Fetches the exchanges with the trading schedules of the coming days
+    pub async fn get_exchanges_metadata(&self) -> Result<Vec<Exchange>, Trading212Error> {
+        let response = self.send(self.client.get(&self.base_url)).await?;
+
+        if !response.status().is_success() {
+            return Err(Trading212Error::RequestFailed(format!(
+                "API returned status code: {}",
+                response.status()
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| Trading212Error::ParseError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::spawn_test_server;
+    use crate::utils::settings::ApiEnvironment;
+    use axum::{
+        http::StatusCode as HttpStatus,
+        routing::{get, post},
+        Router,
+    };
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    /// Serves the positions and export endpoints, answering the first
+    /// `failures` requests with `status`. Returns the config and the request
+    /// count.
+    fn spawn_flaky_server(status: HttpStatus, failures: u32) -> (Config, Arc<AtomicU32>) {
+        let requests = Arc::new(AtomicU32::new(0));
+        let counter = requests.clone();
+        let handler = move || {
+            let counter = counter.clone();
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                    (status, "[]")
+                } else {
+                    (HttpStatus::OK, "[]")
+                }
+            }
+        };
+        let app = Router::new()
+            .route("/api/v0/equity/portfolio", get(handler.clone()))
+            .route("/api/v0/history/exports", post(handler));
+        let addr = spawn_test_server(app);
+
+        let config = Config {
+            api_key: Some("mock-key".to_string()),
+            api_environment: ApiEnvironment::Custom(format!("http://{}", addr)),
+            ..Config::default()
+        };
+        (config, requests)
+    }
+
+    fn client(config: &Config) -> Trading212Client {
+        Trading212Client::new(RequestType::Portfolio, config)
+            .unwrap()
+            .with_retry_backoff(Duration::from_millis(1))
+    }
+
+    #[tokio::test]
+    async fn test_send_retries_rate_limited_request() {
+        let (config, requests) = spawn_flaky_server(HttpStatus::TOO_MANY_REQUESTS, 1);
+        let positions = client(&config).get_open_positions().await.unwrap();
+        assert!(positions.is_empty());
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_send_gives_up_after_max_retries() {
+        let (config, requests) = spawn_flaky_server(HttpStatus::SERVICE_UNAVAILABLE, u32::MAX);
+        assert!(client(&config).get_open_positions().await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), MAX_RETRIES + 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_does_not_retry_failed_post() {
+        let (config, requests) = spawn_flaky_server(HttpStatus::INTERNAL_SERVER_ERROR, 1);
+        let client = Trading212Client::new(RequestType::Export, &config)
+            .unwrap()
+            .with_retry_backoff(Duration::from_millis(1));
+        let request = ExportRequest {
+            data_included: DataIncluded {
+                include_dividends: true,
+                include_interest: false,
+                include_orders: false,
+                include_transactions: false,
+            },
+            time_from: "2024-01-01T00:00:00Z".to_string(),
+            time_to: "2024-12-31T23:59:59Z".to_string(),
+        };
+        assert!(client.request_export(&request).await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
 }
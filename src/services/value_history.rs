@@ -0,0 +1,135 @@
+// File: value_history.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::models::benchmark::ValuePoint;
+use crate::models::portfolio::AccountSummary;
+use crate::utils::json_store::{self, Unparsable};
+
+/// File the daily values of the accounts are stored in
+pub const VALUE_HISTORY_FILE: &str = "value_history.json";
+
+/// Total value in GBP of each account at the last update of every day.
+/// Trading 212 has no history of the account value, so it is only known
+/// from the day the app first ran.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ValueHistory {
+    pub accounts: HashMap<String, BTreeMap<NaiveDate, f64>>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl ValueHistory {
+    /// Loads the history from `path`. A missing file gives an empty history.
+    /// The values cannot be fetched again, so a file that does not parse is
+    /// moved aside instead of being overwritten by the next save.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut history: ValueHistory = json_store::load(&path, "value history", Unparsable::Keep);
+        history.path = path;
+        history
+    }
+
+    /// Writes the history to a temporary file first and moves it into place,
+    /// so an interrupted write leaves the previous file intact
+    pub fn save(&self) -> std::io::Result<()> {
+        json_store::save(&self.path, self)
+    }
+
+    /// Stores the value of every account on `date`, replacing an earlier
+    /// update of the same day. Accounts that failed to update are skipped.
+    pub fn record(&mut self, date: NaiveDate, accounts: &[AccountSummary]) {
+        for account in accounts.iter().filter(|a| a.error.is_none()) {
+            self.accounts
+                .entry(account.name.clone())
+                .or_default()
+                .insert(date, account.total_value);
+        }
+    }
+
+    /// Value of `accounts` together at the last update on or before `date`,
+    /// `None` when one of them has no value yet by then
+    pub fn value_on(&self, accounts: &[String], date: NaiveDate) -> Option<f64> {
+        accounts
+            .iter()
+            .map(|name| {
+                let values = self.accounts.get(name)?;
+                values.range(..=date).next_back().map(|(_, value)| *value)
+            })
+            .sum()
+    }
+
+    /// Values of `accounts` together on each day any of them was updated,
+    /// from the first day all of them have a value
+    pub fn values(&self, accounts: &[String]) -> Vec<ValuePoint> {
+        let dates: BTreeSet<NaiveDate> = accounts
+            .iter()
+            .filter_map(|name| self.accounts.get(name))
+            .flat_map(|values| values.keys().copied())
+            .collect();
+        dates
+            .into_iter()
+            .filter_map(|date| {
+                Some(ValuePoint {
+                    date,
+                    value: self.value_on(accounts, date)?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unreadable_history_is_kept() {
+        let dir = std::env::temp_dir().join(format!("value_history_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(VALUE_HISTORY_FILE);
+        std::fs::write(&path, "{\"accounts\": {\"ISA\": {\"2025-01-0").unwrap();
+
+        let mut history = ValueHistory::load(&path);
+        assert!(history.accounts.is_empty());
+        history
+            .accounts
+            .entry("ISA".to_string())
+            .or_default()
+            .insert(NaiveDate::from_ymd_opt(2025, 1, 2).unwrap(), 100.0);
+        history.save().unwrap();
+
+        let files: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        let reloaded = ValueHistory::load(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().any(|name| name.ends_with(".corrupt")));
+        assert_eq!(
+            reloaded.value_on(&["ISA".to_string()], NaiveDate::MAX),
+            Some(100.0)
+        );
+    }
+}
@@ -0,0 +1,724 @@
+// File: yahoo.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::stream::{self, StreamExt};
+use reqwest::header::{HeaderMap, HeaderValue, COOKIE, SET_COOKIE, USER_AGENT};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::services::price_history::PriceBar;
+
+/// Page that hands out the session cookie the API endpoints require
+pub const YAHOO_COOKIE_URL: &str = "https://fc.yahoo.com";
+/// Host of the Yahoo Finance API endpoints
+pub const YAHOO_API_URL: &str = "https://query2.finance.yahoo.com";
+
+/// Largest number of symbols fetched at the same time
+pub const MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Number of symbols per batch quote request
+const QUOTE_BATCH_SIZE: usize = 50;
+
+/// Modules of the quote summary merged into the info of a symbol, the same
+/// ones yfinance flattens into `Ticker.info`
+const SUMMARY_MODULES: &str =
+    "summaryDetail,calendarEvents,price,assetProfile,defaultKeyStatistics,financialData";
+
+/// History range searched for dividends, the whole history is kept to judge
+/// how reliable the dividend is
+const DIVIDEND_RANGE: &str = "max";
+
+/// Number of recent dividends reported per symbol
+const RECENT_DIVIDENDS: usize = 4;
+
+/// Yahoo rejects requests that do not look like they come from a browser
+const BROWSER_USER_AGENT: &str =
+    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0 Safari/537.36";
+
+#[derive(Error, Debug)]
+pub enum YahooError {
+    #[error("Yahoo request failed: {0}")]
+    RequestFailed(String),
+
+    #[error("Failed to parse Yahoo response: {0}")]
+    ParseError(String),
+
+    #[error("Yahoo returned no data for {0}")]
+    NotFound(String),
+}
+
+/// Session with Yahoo Finance: the cookie and crumb every API call has to
+/// carry
+#[derive(Debug, Clone, Default)]
+struct Session {
+    cookie: Option<String>,
+    crumb: String,
+}
+
+/// Client of the Yahoo Finance endpoints used by yfinance. `fetch_info`
+/// returns the same JSON the `stock_info.py` script used to print, so the
+/// result can be stored and parsed exactly like before.
+pub struct YahooClient {
+    client: reqwest::Client,
+    cookie_url: String,
+    api_url: String,
+}
+
+impl Default for YahooClient {
+    fn default() -> Self {
+        Self::new(YAHOO_COOKIE_URL, YAHOO_API_URL)
+    }
+}
+
+impl YahooClient {
+    pub fn new(cookie_url: &str, api_url: &str) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static(BROWSER_USER_AGENT));
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            client,
+            cookie_url: cookie_url.trim_end_matches('/').to_string(),
+            api_url: api_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Fetches the info of every symbol, keyed by symbol. A symbol that
+    /// fails gets an `error` entry instead, so one bad ticker does not hide
+    /// the others.
+    pub async fn fetch_info(&self, symbols: &[String]) -> Result<Value, YahooError> {
+        let session = self.session().await?;
+
+        let mut quotes = Map::new();
+        for batch in symbols.chunks(QUOTE_BATCH_SIZE) {
+            match self.quotes(&session, batch).await {
+                Ok(batch_quotes) => quotes.extend(batch_quotes),
+                Err(e) => eprintln!("[ERROR] Quotes of {}: {}", batch.join(","), e),
+            }
+        }
+
+        // The futures are built up front, a closure passed to the stream
+        // makes the future of the refresh task lose its `Send` bound
+        let requests: Vec<_> = symbols
+            .iter()
+            .map(|symbol| self.symbol_info_or_error(&session, symbol, quotes.get(symbol)))
+            .collect();
+        let infos: Vec<(String, Value)> = stream::iter(requests)
+            .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+            .collect()
+            .await;
+
+        Ok(Value::Object(infos.into_iter().collect()))
+    }
+
+    /// Daily bars of each symbol from its start day up to today. Each
+    /// symbol has its own result, so one bad ticker does not hide the others.
+    pub async fn fetch_daily_bars(
+        &self,
+        requests: &[(String, NaiveDate)],
+    ) -> Result<Vec<(String, Result<Vec<PriceBar>, YahooError>)>, YahooError> {
+        let session = self.session().await?;
+        let requests: Vec<_> = requests
+            .iter()
+            .map(|(symbol, from)| self.symbol_bars(&session, symbol, *from))
+            .collect();
+        Ok(stream::iter(requests)
+            .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+            .collect()
+            .await)
+    }
+
+    async fn symbol_bars(
+        &self,
+        session: &Session,
+        symbol: &str,
+        from: NaiveDate,
+    ) -> (String, Result<Vec<PriceBar>, YahooError>) {
+        let period1 = from
+            .and_hms_opt(0, 0, 0)
+            .map(|d| d.and_utc().timestamp())
+            .unwrap_or_default()
+            .to_string();
+        let period2 = Utc::now().timestamp().to_string();
+        let result = self
+            .get(
+                session,
+                &format!("/v8/finance/chart/{}", symbol),
+                &[
+                    ("period1", period1.as_str()),
+                    ("period2", period2.as_str()),
+                    ("interval", "1d"),
+                    ("events", "div"),
+                ],
+            )
+            .await
+            .and_then(|body| daily_bars(&body["chart"], symbol));
+        (symbol.to_string(), result)
+    }
+
+    async fn symbol_info_or_error(
+        &self,
+        session: &Session,
+        symbol: &str,
+        quote: Option<&Value>,
+    ) -> (String, Value) {
+        let info = match self.symbol_info(session, symbol, quote.cloned()).await {
+            Ok(info) => info,
+            Err(e) => {
+                eprintln!("[ERROR] Symbol {}: {}", symbol, e);
+                json!({ "error": e.to_string() })
+            }
+        };
+        (symbol.to_string(), info)
+    }
+
+    /// Info of a single symbol: the flattened quote summary, overlaid with
+    /// the fields of the batch quote, plus its dividends
+    async fn symbol_info(
+        &self,
+        session: &Session,
+        symbol: &str,
+        quote: Option<Value>,
+    ) -> Result<Value, YahooError> {
+        let mut info = self.quote_summary(session, symbol).await?;
+        if let Some(Value::Object(quote)) = quote {
+            for (key, value) in quote {
+                info.entry(key).or_insert(value);
+            }
+        }
+
+        let (dividends, splits) = match self.events(session, symbol).await {
+            Ok(events) => events,
+            Err(e) => {
+                eprintln!("[ERROR] Dividends of {}: {}", symbol, e);
+                (BTreeMap::new(), BTreeMap::new())
+            }
+        };
+        let recent: Map<String, Value> = dividends
+            .iter()
+            .rev()
+            .take(RECENT_DIVIDENDS)
+            .map(|(date, amount)| (date.format("%Y-%m-%d").to_string(), json!(amount)))
+            .collect();
+        info.insert("last_4_dividends".to_string(), Value::Object(recent));
+        let history: Map<String, Value> = dividends
+            .iter()
+            .map(|(date, amount)| (date.format("%Y-%m-%d").to_string(), json!(amount)))
+            .collect();
+        info.insert("dividend_history".to_string(), Value::Object(history));
+        let splits: Map<String, Value> = splits
+            .into_iter()
+            .map(|(date, ratio)| (date.format("%Y-%m-%d").to_string(), json!(ratio)))
+            .collect();
+        info.insert("split_history".to_string(), Value::Object(splits));
+
+        Ok(Value::Object(info))
+    }
+
+    /// Gets the session cookie and the crumb that has to accompany it
+    async fn session(&self) -> Result<Session, YahooError> {
+        // The cookie page answers with an error status, only its cookie matters
+        let response = self
+            .client
+            .get(&self.cookie_url)
+            .send()
+            .await
+            .map_err(|e| YahooError::RequestFailed(e.to_string()))?;
+        let cookie = response
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .filter_map(|v| v.split(';').next())
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join("; ");
+        let cookie = Some(cookie).filter(|c| !c.is_empty());
+
+        let mut request = self
+            .client
+            .get(format!("{}/v1/test/getcrumb", self.api_url));
+        if let Some(cookie) = &cookie {
+            request = request.header(COOKIE, cookie);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| YahooError::RequestFailed(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(YahooError::RequestFailed(format!(
+                "Crumb request returned status code: {}",
+                response.status()
+            )));
+        }
+        let crumb = response
+            .text()
+            .await
+            .map_err(|e| YahooError::ParseError(e.to_string()))?
+            .trim()
+            .to_string();
+        if crumb.is_empty() || crumb.contains('<') {
+            return Err(YahooError::ParseError(format!("Invalid crumb: {}", crumb)));
+        }
+
+        Ok(Session { cookie, crumb })
+    }
+
+    async fn get(
+        &self,
+        session: &Session,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Value, YahooError> {
+        let mut request = self
+            .client
+            .get(format!("{}{}", self.api_url, path))
+            .query(query)
+            .query(&[("crumb", session.crumb.as_str())]);
+        if let Some(cookie) = &session.cookie {
+            request = request.header(COOKIE, cookie);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| YahooError::RequestFailed(e.to_string()))?;
+
+        // Unknown symbols come back as 404 with an error body, which is
+        // reported by the callers
+        let status = response.status();
+        let body: Value = response.json().await.map_err(|e| {
+            YahooError::ParseError(format!("{} (status code {})", e, status.as_u16()))
+        })?;
+        Ok(body)
+    }
+
+    /// Quote summary of `symbol`, with the modules flattened into one
+    /// object and `{raw, fmt}` values reduced to their raw value
+    async fn quote_summary(
+        &self,
+        session: &Session,
+        symbol: &str,
+    ) -> Result<Map<String, Value>, YahooError> {
+        let body = self
+            .get(
+                session,
+                &format!("/v10/finance/quoteSummary/{}", symbol),
+                &[("modules", SUMMARY_MODULES), ("formatted", "false")],
+            )
+            .await?;
+        let result = first_result(&body["quoteSummary"], symbol)?;
+
+        let mut info = Map::new();
+        if let Value::Object(modules) = result {
+            for module in modules.values() {
+                if let Value::Object(fields) = module {
+                    for (key, value) in fields {
+                        info.entry(key.clone()).or_insert_with(|| raw_value(value));
+                    }
+                }
+            }
+        }
+        // yfinance reports the yield in percent, the rest of the app relies on it
+        if let Some(Value::Number(div_yield)) = info.get("dividendYield") {
+            if let Some(div_yield) = div_yield.as_f64() {
+                info.insert("dividendYield".to_string(), json!(div_yield * 100.0));
+            }
+        }
+        info.insert("symbol".to_string(), json!(symbol));
+        Ok(info)
+    }
+
+    /// Batch quotes of `symbols`, keyed by symbol. These carry the
+    /// announced corporate actions.
+    async fn quotes(
+        &self,
+        session: &Session,
+        symbols: &[String],
+    ) -> Result<Map<String, Value>, YahooError> {
+        let body = self
+            .get(
+                session,
+                "/v7/finance/quote",
+                &[("symbols", &symbols.join(",")), ("formatted", "false")],
+            )
+            .await?;
+        let results = body["quoteResponse"]["result"]
+            .as_array()
+            .ok_or_else(|| YahooError::ParseError("Missing quoteResponse".to_string()))?;
+
+        Ok(results
+            .iter()
+            .filter_map(|quote| {
+                let symbol = quote["symbol"].as_str()?.to_string();
+                Some((symbol, quote.clone()))
+            })
+            .collect())
+    }
+
+    /// Every dividend paid by `symbol` and every split, by ex-date. Splits
+    /// are ratios such as `4:1`.
+    async fn events(
+        &self,
+        session: &Session,
+        symbol: &str,
+    ) -> Result<(BTreeMap<NaiveDate, f64>, BTreeMap<NaiveDate, String>), YahooError> {
+        let body = self
+            .get(
+                session,
+                &format!("/v8/finance/chart/{}", symbol),
+                &[
+                    ("range", DIVIDEND_RANGE),
+                    ("interval", "1mo"),
+                    ("events", "div,splits"),
+                ],
+            )
+            .await?;
+        let result = first_result(&body["chart"], symbol)?;
+        let gmt_offset = result["meta"]["gmtoffset"].as_i64().unwrap_or(0);
+
+        let mut dividends = BTreeMap::new();
+        if let Some(events) = result["events"]["dividends"].as_object() {
+            for event in events.values() {
+                let (Some(date), Some(amount)) = (event["date"].as_i64(), event["amount"].as_f64())
+                else {
+                    continue;
+                };
+                // Dates are midnight at the exchange, not in UTC
+                if let Some(date) = DateTime::from_timestamp(date + gmt_offset, 0) {
+                    dividends.insert(date.date_naive(), amount);
+                }
+            }
+        }
+
+        let mut splits = BTreeMap::new();
+        if let Some(events) = result["events"]["splits"].as_object() {
+            for event in events.values() {
+                let ratio = match (event["numerator"].as_f64(), event["denominator"].as_f64()) {
+                    (Some(numerator), Some(denominator)) => {
+                        format!("{}:{}", numerator, denominator)
+                    }
+                    _ => match event["splitRatio"].as_str() {
+                        Some(ratio) => ratio.to_string(),
+                        None => continue,
+                    },
+                };
+                if let Some(date) = event["date"]
+                    .as_i64()
+                    .and_then(|date| DateTime::from_timestamp(date + gmt_offset, 0))
+                {
+                    splits.insert(date.date_naive(), ratio);
+                }
+            }
+        }
+        Ok((dividends, splits))
+    }
+}
+
+/// First entry of the `result` array of a Yahoo response, or the error
+/// Yahoo reported for it
+fn first_result<'a>(response: &'a Value, symbol: &str) -> Result<&'a Value, YahooError> {
+    if let Some(error) = response.get("error").filter(|e| !e.is_null()) {
+        let description = error["description"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string());
+        return Err(YahooError::RequestFailed(description));
+    }
+    response["result"]
+        .get(0)
+        .ok_or_else(|| YahooError::NotFound(symbol.to_string()))
+}
+
+/// Reads the daily bars of a chart response, with the dividends going ex
+/// on each day. Days without a complete set of prices, such as holidays,
+/// are left out.
+fn daily_bars(response: &Value, symbol: &str) -> Result<Vec<PriceBar>, YahooError> {
+    let result = first_result(response, symbol)?;
+    let gmt_offset = result["meta"]["gmtoffset"].as_i64().unwrap_or(0);
+    let quote = &result["indicators"]["quote"][0];
+    let prices = |key: &str, i: usize| quote[key].get(i).and_then(|v| v.as_f64());
+    let day_of = |ts: i64| DateTime::from_timestamp(ts + gmt_offset, 0).map(|d| d.date_naive());
+
+    let mut dividends: HashMap<NaiveDate, f64> = HashMap::new();
+    if let Some(events) = result["events"]["dividends"].as_object() {
+        for event in events.values() {
+            if let (Some(date), Some(amount)) = (
+                event["date"].as_i64().and_then(day_of),
+                event["amount"].as_f64(),
+            ) {
+                *dividends.entry(date).or_default() += amount;
+            }
+        }
+    }
+
+    let timestamps = result["timestamp"].as_array().cloned().unwrap_or_default();
+    let mut bars: BTreeMap<NaiveDate, PriceBar> = BTreeMap::new();
+    for (i, timestamp) in timestamps.iter().enumerate() {
+        let Some(date) = timestamp.as_i64().and_then(day_of) else {
+            continue;
+        };
+        let (Some(open), Some(high), Some(low), Some(close)) = (
+            prices("open", i),
+            prices("high", i),
+            prices("low", i),
+            prices("close", i),
+        ) else {
+            continue;
+        };
+        bars.insert(
+            date,
+            PriceBar {
+                date,
+                open,
+                high,
+                low,
+                close,
+                dividend: dividends.get(&date).copied(),
+            },
+        );
+    }
+    Ok(bars.into_values().collect())
+}
+
+/// Reduces a formatted `{raw, fmt}` value to its raw value
+fn raw_value(value: &Value) -> Value {
+    match value.get("raw") {
+        Some(raw) => raw.clone(),
+        None => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::spawn_test_server;
+    use axum::{
+        extract::{Path, Query},
+        http::{HeaderMap as AxumHeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+
+    const CRUMB: &str = "test-crumb";
+
+    fn has_session(headers: &AxumHeaderMap, query: &HashMap<String, String>) -> bool {
+        let cookie = headers
+            .get("cookie")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        cookie.contains("A3=session") && query.get("crumb").map(String::as_str) == Some(CRUMB)
+    }
+
+    /// Stand-in for Yahoo Finance serving one known symbol, `AAPL`
+    fn yahoo_router() -> Router {
+        Router::new()
+            .route(
+                "/cookie",
+                get(|| async {
+                    (
+                        StatusCode::NOT_FOUND,
+                        [("set-cookie", "A3=session; Domain=.yahoo.com; HttpOnly")],
+                    )
+                }),
+            )
+            .route(
+                "/v1/test/getcrumb",
+                get(|headers: AxumHeaderMap| async move {
+                    if headers.get("cookie").is_some() {
+                        (StatusCode::OK, CRUMB).into_response()
+                    } else {
+                        StatusCode::UNAUTHORIZED.into_response()
+                    }
+                }),
+            )
+            .route(
+                "/v10/finance/quoteSummary/:symbol",
+                get(
+                    |Path(symbol): Path<String>,
+                     Query(query): Query<HashMap<String, String>>,
+                     headers: AxumHeaderMap| async move {
+                        if !has_session(&headers, &query) {
+                            return StatusCode::UNAUTHORIZED.into_response();
+                        }
+                        if symbol != "AAPL" {
+                            return (
+                                StatusCode::NOT_FOUND,
+                                Json(json!({ "quoteSummary": { "result": null, "error": {
+                                    "code": "Not Found",
+                                    "description": format!("Quote not found for symbol: {}", symbol)
+                                } } })),
+                            )
+                                .into_response();
+                        }
+                        Json(json!({ "quoteSummary": { "error": null, "result": [{
+                            "summaryDetail": {
+                                "dividendRate": 1.04,
+                                "dividendYield": { "raw": 0.0045, "fmt": "0.45%" },
+                                "exDividendDate": 1739145600,
+                                "currency": "USD"
+                            },
+                            "calendarEvents": { "dividendDate": 1739404800 },
+                            "assetProfile": { "sector": "Technology", "country": "United States" }
+                        }] } }))
+                        .into_response()
+                    },
+                ),
+            )
+            .route(
+                "/v7/finance/quote",
+                get(
+                    |Query(query): Query<HashMap<String, String>>, headers: AxumHeaderMap| async move {
+                        if !has_session(&headers, &query) {
+                            return StatusCode::UNAUTHORIZED.into_response();
+                        }
+                        let results: Vec<Value> = query["symbols"]
+                            .split(',')
+                            .filter(|s| *s == "AAPL")
+                            .map(|s| json!({
+                                "symbol": s,
+                                "corporateActions": [{ "header": "Dividend",
+                                    "meta": { "eventType": "DIVIDEND", "amount": "0.26" } }]
+                            }))
+                            .collect();
+                        Json(json!({ "quoteResponse": { "result": results, "error": null } }))
+                            .into_response()
+                    },
+                ),
+            )
+            .route(
+                "/v8/finance/chart/:symbol",
+                get(|Path(symbol): Path<String>| async move {
+                    let dividends: Map<String, Value> = [
+                        ("2024-02-09", 0.24),
+                        ("2024-05-10", 0.25),
+                        ("2024-08-12", 0.25),
+                        ("2024-11-08", 0.25),
+                        ("2025-02-10", 0.25),
+                    ]
+                    .iter()
+                    .map(|(date, amount)| {
+                        // Midnight in New York
+                        let ts = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                            .unwrap()
+                            .and_hms_opt(5, 0, 0)
+                            .unwrap()
+                            .and_utc()
+                            .timestamp();
+                        (ts.to_string(), json!({ "amount": amount, "date": ts }))
+                    })
+                    .collect();
+                    // Market open in New York, the last day is a holiday
+                    let days = [1741098600, 1741185000, 1741271400];
+                    Json(json!({ "chart": { "error": null, "result": [{
+                        "meta": { "symbol": symbol, "gmtoffset": -18000 },
+                        "timestamp": days,
+                        "indicators": { "quote": [{
+                            "open": [235.1, 236.0, null],
+                            "high": [236.5, 237.2, null],
+                            "low": [233.8, 234.9, null],
+                            "close": [235.7, 235.3, null]
+                        }] },
+                        "events": { "dividends": dividends, "splits": {
+                            "1598880600": { "date": 1598880600, "numerator": 4, "denominator": 1, "splitRatio": "4:1" }
+                        } }
+                    }] } }))
+                }),
+            )
+    }
+
+    fn spawn_yahoo() -> YahooClient {
+        let addr = spawn_test_server(yahoo_router());
+        YahooClient::new(
+            &format!("http://{}/cookie", addr),
+            &format!("http://{}", addr),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_fetch_info_matches_script_output() {
+        let client = spawn_yahoo();
+        let info = client
+            .fetch_info(&["AAPL".to_string(), "MISSING.L".to_string()])
+            .await
+            .unwrap();
+
+        let aapl = &info["AAPL"];
+        assert_eq!(aapl["dividendRate"].as_f64(), Some(1.04));
+        assert!((aapl["dividendYield"].as_f64().unwrap() - 0.45).abs() < 1e-9);
+        assert_eq!(aapl["exDividendDate"].as_i64(), Some(1739145600));
+        assert_eq!(aapl["dividendDate"].as_i64(), Some(1739404800));
+        assert_eq!(aapl["sector"], "Technology");
+        assert_eq!(aapl["corporateActions"][0]["meta"]["amount"], "0.26");
+
+        let recent = aapl["last_4_dividends"].as_object().unwrap();
+        assert_eq!(
+            recent.keys().collect::<Vec<_>>(),
+            vec!["2024-05-10", "2024-08-12", "2024-11-08", "2025-02-10"]
+        );
+        assert_eq!(aapl["dividend_history"].as_object().unwrap().len(), 5);
+        assert_eq!(aapl["split_history"]["2020-08-31"], "4:1");
+
+        assert!(info["MISSING.L"]["error"]
+            .as_str()
+            .unwrap()
+            .contains("Quote not found"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_daily_bars() {
+        let client = spawn_yahoo();
+        let from = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        let results = client
+            .fetch_daily_bars(&[("AAPL".to_string(), from)])
+            .await
+            .unwrap();
+
+        let (symbol, bars) = &results[0];
+        assert_eq!(symbol, "AAPL");
+        let bars = bars.as_ref().unwrap();
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0].date, NaiveDate::from_ymd_opt(2025, 3, 4).unwrap());
+        assert_eq!(bars[1].close, 235.3);
+    }
+
+    #[test]
+    fn test_daily_bars_with_dividends() {
+        // Market open in London, the dividend goes ex on the second day
+        let chart = json!({ "error": null, "result": [{
+            "meta": { "gmtoffset": 0 },
+            "timestamp": [1741078800, 1741165200],
+            "indicators": { "quote": [{
+                "open": [10.0, 9.9], "high": [10.1, 10.0],
+                "low": [9.9, 9.8], "close": [10.0, 9.9]
+            }] },
+            "events": { "dividends": {
+                "1741165200": { "amount": 0.1, "date": 1741165200 }
+            } }
+        }] });
+        let bars = daily_bars(&chart, "VUSA.L").unwrap();
+        assert_eq!(bars[0].dividend, None);
+        assert_eq!(bars[1].dividend, Some(0.1));
+    }
+}
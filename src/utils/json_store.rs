@@ -0,0 +1,104 @@
+// File: json_store.rs
+// Copyright (c) 2025 Anand Sureshkumar
+// This file is part of T212 Portfolio Analytics.
+// Licensed for personal and educational use only. Commercial use prohibited.
+// See the LICENSE file for details.
+//
+// Permission is granted to use, copy, and modify this code for personal, non-commercial, or educational purposes.
+//
+// Commercial use of this code, in whole or in part, is strictly prohibited without explicit written permission.
+// For commercial licensing or other inquiries, contact: anandsuresh9988@gmail.com
+//
+// Disclaimer:
+// This software interacts with external services (e.g., Trading 212 API) using user-provided credentials.
+// The author is not responsible for any security vulnerabilities, data breaches, account lockouts,
+// financial losses, or other issues arising from the use of this software.
+//
+// USE THIS SOFTWARE AT YOUR OWN RISK.
+
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// What to do with a file that exists but does not parse
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unparsable {
+    /// Start over, for caches that are filled again by the next refresh
+    Ignore,
+    /// Move the file aside so that the next save cannot overwrite data that
+    /// cannot be fetched again
+    Keep,
+}
+
+/// Loads a value stored as JSON at `path`. A missing file gives the
+/// default value, as does a file that does not parse, which is handled as
+/// `unparsable` says. `what` names the file in the log.
+pub fn load<T: DeserializeOwned + Default>(path: &Path, what: &str, unparsable: Unparsable) -> T {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return T::default();
+    };
+    match serde_json::from_str(&content) {
+        Ok(value) => value,
+        Err(e) if unparsable == Unparsable::Keep => {
+            let mut kept = path.to_path_buf().into_os_string();
+            kept.push(format!(".{}.corrupt", chrono::Utc::now().timestamp()));
+            eprintln!(
+                "The {} {} does not parse ({}), keeping it as {}",
+                what,
+                path.display(),
+                e,
+                kept.to_string_lossy()
+            );
+            if let Err(e) = std::fs::rename(path, &kept) {
+                eprintln!("Failed to keep {} {}: {}", what, path.display(), e);
+            }
+            T::default()
+        }
+        Err(e) => {
+            eprintln!("Ignoring {} {}: {}", what, path.display(), e);
+            T::default()
+        }
+    }
+}
+
+/// Saves `value` as JSON to `path`. The JSON is written to a temporary file
+/// next to it first and then renamed over it, so a crash during the write
+/// leaves the previous file intact.
+pub fn save<T: Serialize>(path: &Path, value: &T) -> std::io::Result<()> {
+    let mut tmp = path.to_path_buf().into_os_string();
+    tmp.push(".tmp");
+    let file = std::fs::File::create(&tmp)?;
+    let mut writer = std::io::BufWriter::new(file);
+    serde_json::to_writer(&mut writer, value)?;
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_save_replaces_the_file() {
+        let dir = std::env::temp_dir().join(format!("json_store_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("store.json");
+        std::fs::write(&path, "{\"a\": ").unwrap();
+
+        let mut store: BTreeMap<String, u32> = load(&path, "test store", Unparsable::Ignore);
+        assert!(store.is_empty());
+        store.insert("a".to_string(), 1);
+        save(&path, &store).unwrap();
+
+        let files = std::fs::read_dir(&dir).unwrap().count();
+        let reloaded: BTreeMap<String, u32> = load(&path, "test store", Unparsable::Ignore);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files, 1);
+        assert_eq!(reloaded, store);
+    }
+}
@@ -17,5 +17,6 @@
 // USE THIS SOFTWARE AT YOUR OWN RISK.
 
 pub mod currency;
+pub mod json_store;
 pub mod settings;
 pub mod symbol_mapper;
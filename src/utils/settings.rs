@@ -30,9 +30,22 @@ use super::currency::Currency;
 
 /// Default portfolio update interval in seconds (1 hour)
 const DEFAULT_PORTFOLIO_UPDATE_TIME_S: u64 = 60 * 60;
+/// Default lifetime of the cached instrument metadata in seconds (1 day)
+const DEFAULT_INSTRUMENT_METADATA_TTL_S: u64 = 24 * 60 * 60;
+/// Default lifetime of the cached market data of a symbol in seconds (12 hours)
+const DEFAULT_MARKET_DATA_TTL_S: u64 = 12 * 60 * 60;
 /// Configuration file name
 const CONFIG_FILE: &str = "config.json";
 
+/// Longest period Trading 212 accepts for a single export request
+pub const MAX_EXPORT_CHUNK_DAYS: u32 = 365;
+
+/// Upper bound of the export history, to keep the number of requests sane
+const MAX_EXPORT_HISTORY_YEARS: u32 = 20;
+
+/// Longest lifetime of a cache that can be set in the settings (one year)
+pub const MAX_CACHE_TTL_HOURS: u64 = 366 * 24;
+
 /// Custom error types for configuration operations
 ///
 /// This enum defines the possible errors that can occur when
@@ -45,6 +58,24 @@ pub enum ConfigError {
     /// Error when JSON serialization/deserialization fails
     #[error("Serialization error: {0}")]
     Serialization(#[from] SerdeError),
+    /// Error when the configured API environment is not usable
+    #[error("Invalid API environment: {0}")]
+    InvalidApiEnvironment(String),
+    /// Error when the configured accounts are not usable
+    #[error("Invalid accounts: {0}")]
+    InvalidAccounts(String),
+    /// Error when the export range or data sets are not usable
+    #[error("Invalid export settings: {0}")]
+    InvalidExportSettings(String),
+    /// Error when the configured market data source is not usable
+    #[error("Invalid market data source: {0}")]
+    InvalidMarketDataSource(String),
+    /// Error when a concentration limit is not a percentage
+    #[error("Invalid concentration limits: {0}")]
+    InvalidConcentrationLimits(String),
+    /// Error when a cache lifetime is out of range
+    #[error("Invalid cache lifetime: {0}")]
+    InvalidCacheTtl(String),
 }
 
 /// Application running mode
@@ -81,6 +112,372 @@ impl Default for Mode {
     }
 }
 
+/// Trading 212 API environment
+///
+/// This enum selects the server the Trading 212 client talks to. Besides
+/// the two official environments, any server implementing the same API
+/// (e.g. a local stand-in used for testing) can be set as a custom base URL.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub enum ApiEnvironment {
+    /// Real money account
+    #[default]
+    Live,
+    /// Practice (paper trading) account
+    Practice,
+    /// Custom base URL, e.g. `http://127.0.0.1:8080`
+    Custom(String),
+}
+
+impl std::fmt::Display for ApiEnvironment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiEnvironment::Live => write!(f, "Live"),
+            ApiEnvironment::Practice => write!(f, "Practice"),
+            ApiEnvironment::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl ApiEnvironment {
+    /// Builds the environment from the values of the settings form
+    ///
+    /// # Arguments
+    /// - `name` - "Live", "Practice" or "Custom"
+    /// - `base_url` - base URL used when `name` is "Custom"
+    ///
+    /// # Returns
+    /// - `Ok(ApiEnvironment)` if the environment is valid
+    /// - `Err(ConfigError::InvalidApiEnvironment)` otherwise
+    pub fn from_form(name: &str, base_url: Option<&str>) -> Result<Self, ConfigError> {
+        let environment = match name {
+            "Live" => ApiEnvironment::Live,
+            "Practice" => ApiEnvironment::Practice,
+            "Custom" => ApiEnvironment::Custom(
+                base_url
+                    .unwrap_or_default()
+                    .trim()
+                    .trim_end_matches('/')
+                    .to_string(),
+            ),
+            other => {
+                return Err(ConfigError::InvalidApiEnvironment(format!(
+                    "unknown environment '{}'",
+                    other
+                )))
+            }
+        };
+        environment.validate()?;
+        Ok(environment)
+    }
+
+    /// Environment selected by the `T212_TARGET` variable of older
+    /// versions: `live` for the live server, anything else for practice
+    pub fn from_target(target: &str) -> Self {
+        if target == "live" {
+            ApiEnvironment::Live
+        } else {
+            ApiEnvironment::Practice
+        }
+    }
+
+    /// Returns the base URL of the API, without a trailing slash
+    pub fn base_url(&self) -> &str {
+        match self {
+            ApiEnvironment::Live => "https://live.trading212.com",
+            ApiEnvironment::Practice => "https://demo.trading212.com",
+            ApiEnvironment::Custom(url) => url.trim_end_matches('/'),
+        }
+    }
+
+    /// Checks that a custom base URL is an absolute http(s) URL without
+    /// a path, query or fragment
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let ApiEnvironment::Custom(url) = self else {
+            return Ok(());
+        };
+
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| ConfigError::InvalidApiEnvironment(format!("'{}': {}", url, e)))?;
+        if parsed.scheme() != "http" && parsed.scheme() != "https" {
+            return Err(ConfigError::InvalidApiEnvironment(format!(
+                "'{}' must use http or https",
+                url
+            )));
+        }
+        if parsed.host_str().is_none() {
+            return Err(ConfigError::InvalidApiEnvironment(format!(
+                "'{}' has no host",
+                url
+            )));
+        }
+        if parsed.path() != "/" || parsed.query().is_some() || parsed.fragment().is_some() {
+            return Err(ConfigError::InvalidApiEnvironment(format!(
+                "'{}' must not contain a path or query",
+                url
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Where quotes, dividends and company profiles come from
+///
+/// Yahoo Finance is queried directly by default. The other sources keep the
+/// JSON shape of `stock_info.py`, so data can be prepared offline or served
+/// by another vendor behind a small adapter.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub enum MarketDataSource {
+    /// Yahoo Finance API
+    #[default]
+    Yahoo,
+    /// Path of a Python script printing the data of the symbols given as
+    /// its first argument, e.g. `stock_info.py`
+    Python(String),
+    /// Directory of static JSON files
+    Directory(String),
+    /// URL of a service answering `GET <url>?symbols=A,B`
+    Http(String),
+}
+
+impl std::fmt::Display for MarketDataSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MarketDataSource::Yahoo => write!(f, "Yahoo"),
+            MarketDataSource::Python(_) => write!(f, "Python"),
+            MarketDataSource::Directory(_) => write!(f, "Directory"),
+            MarketDataSource::Http(_) => write!(f, "Http"),
+        }
+    }
+}
+
+impl MarketDataSource {
+    /// Builds the source from the values of the settings form
+    ///
+    /// # Arguments
+    /// - `name` - "Yahoo", "Python", "Directory" or "Http"
+    /// - `location` - script path, directory or URL of the other sources
+    ///
+    /// # Returns
+    /// - `Ok(MarketDataSource)` if the source is valid
+    /// - `Err(ConfigError::InvalidMarketDataSource)` otherwise
+    pub fn from_form(name: &str, location: Option<&str>) -> Result<Self, ConfigError> {
+        let location = location.unwrap_or_default().trim().to_string();
+        let source = match name {
+            "Yahoo" => MarketDataSource::Yahoo,
+            "Python" => MarketDataSource::Python(location),
+            "Directory" => MarketDataSource::Directory(location),
+            "Http" => MarketDataSource::Http(location),
+            other => {
+                return Err(ConfigError::InvalidMarketDataSource(format!(
+                    "unknown source '{}'",
+                    other
+                )))
+            }
+        };
+        source.validate()?;
+        Ok(source)
+    }
+
+    /// Script path, directory or URL of the source, empty for Yahoo
+    pub fn location(&self) -> &str {
+        match self {
+            MarketDataSource::Yahoo => "",
+            MarketDataSource::Python(location)
+            | MarketDataSource::Directory(location)
+            | MarketDataSource::Http(location) => location,
+        }
+    }
+
+    /// Checks that the source has a location, and that an HTTP source has
+    /// an absolute http(s) URL
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if *self == MarketDataSource::Yahoo {
+            return Ok(());
+        }
+        let location = self.location();
+        if location.trim().is_empty() {
+            return Err(ConfigError::InvalidMarketDataSource(format!(
+                "{} source needs a location",
+                self
+            )));
+        }
+        if let MarketDataSource::Http(url) = self {
+            let parsed = reqwest::Url::parse(url)
+                .map_err(|e| ConfigError::InvalidMarketDataSource(format!("'{}': {}", url, e)))?;
+            if parsed.scheme() != "http" && parsed.scheme() != "https" {
+                return Err(ConfigError::InvalidMarketDataSource(format!(
+                    "'{}' must use http or https",
+                    url
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Kind of a Trading 212 account
+///
+/// Income of an ISA is tax-free, so its totals are always reported
+/// separately from the other accounts.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub enum AccountKind {
+    /// General investment account
+    #[default]
+    Invest,
+    /// Stocks and shares ISA
+    Isa,
+}
+
+impl std::fmt::Display for AccountKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccountKind::Invest => write!(f, "Invest"),
+            AccountKind::Isa => write!(f, "ISA"),
+        }
+    }
+}
+
+impl AccountKind {
+    /// Returns true if the income of the account is not taxed
+    pub fn is_tax_free(&self) -> bool {
+        *self == AccountKind::Isa
+    }
+}
+
+/// A named Trading 212 account with its own API key
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct Account {
+    /// Name shown in the account switcher, unique among the accounts
+    pub name: String,
+    /// Invest or ISA
+    #[serde(default)]
+    pub kind: AccountKind,
+    /// API key of the account
+    pub api_key: String,
+}
+
+impl Account {
+    /// Name of the directory the files of the account named `name` are kept
+    /// in. Characters that are not allowed in file names are replaced.
+    pub fn dir_name(name: &str) -> String {
+        name.chars()
+            .map(|c| match c {
+                c if c.is_alphanumeric() || c == '-' || c == '_' => c,
+                _ => '_',
+            })
+            .collect()
+    }
+}
+
+/// Range and content of the CSV exports requested from Trading 212
+///
+/// Trading 212 limits the period of a single export, so longer histories
+/// are requested in chunks of `chunk_days` and merged afterwards.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ExportSettings {
+    /// Years of history to export, counted back from today
+    pub history_years: u32,
+    /// Longest period covered by a single export request, in days
+    pub chunk_days: u32,
+    pub include_dividends: bool,
+    pub include_interest: bool,
+    pub include_orders: bool,
+    pub include_transactions: bool,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            history_years: 1,
+            chunk_days: MAX_EXPORT_CHUNK_DAYS,
+            include_dividends: true,
+            include_interest: false,
+            include_orders: false,
+            include_transactions: false,
+        }
+    }
+}
+
+impl ExportSettings {
+    /// Checks that the range is within the supported limits and that at
+    /// least one data set is included
+    ///
+    /// # Returns
+    /// - `Ok(())` if the settings are valid
+    /// - `Err(ConfigError::InvalidExportSettings)` otherwise
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(1..=MAX_EXPORT_HISTORY_YEARS).contains(&self.history_years) {
+            return Err(ConfigError::InvalidExportSettings(format!(
+                "history must be between 1 and {} years",
+                MAX_EXPORT_HISTORY_YEARS
+            )));
+        }
+        if !(1..=MAX_EXPORT_CHUNK_DAYS).contains(&self.chunk_days) {
+            return Err(ConfigError::InvalidExportSettings(format!(
+                "chunk size must be between 1 and {} days",
+                MAX_EXPORT_CHUNK_DAYS
+            )));
+        }
+        if !(self.include_dividends
+            || self.include_interest
+            || self.include_orders
+            || self.include_transactions)
+        {
+            return Err(ConfigError::InvalidExportSettings(
+                "at least one data set must be included".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Largest share of the holdings, in percent, a single position or group
+/// may have before the allocation page flags it. A limit of 0 turns the
+/// check off.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ConcentrationLimits {
+    pub position: f64,
+    pub sector: f64,
+    pub industry: f64,
+    pub country: f64,
+}
+
+impl Default for ConcentrationLimits {
+    fn default() -> Self {
+        Self {
+            position: 10.0,
+            sector: 30.0,
+            industry: 20.0,
+            country: 70.0,
+        }
+    }
+}
+
+impl ConcentrationLimits {
+    /// Checks that every limit is a percentage
+    ///
+    /// # Returns
+    /// - `Ok(())` if the limits are valid
+    /// - `Err(ConfigError::InvalidConcentrationLimits)` otherwise
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let limits = [
+            ("position", self.position),
+            ("sector", self.sector),
+            ("industry", self.industry),
+            ("country", self.country),
+        ];
+        for (name, limit) in limits {
+            if !(0.0..=100.0).contains(&limit) {
+                return Err(ConfigError::InvalidConcentrationLimits(format!(
+                    "{} limit must be between 0 and 100%",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Config structure for the application
 ///
 /// This struct holds all the configuration settings that control
@@ -91,10 +488,45 @@ pub struct Config {
     pub mode: Mode,
     /// API key for Trading 212 authentication (optional for demo mode)
     pub api_key: Option<String>,
+    /// Named accounts, each with its own API key. When empty, `api_key` is
+    /// used as a single Invest account.
+    #[serde(default)]
+    pub accounts: Vec<Account>,
+    /// Account shown by the account switcher, `None` for all accounts
+    #[serde(default)]
+    pub selected_account: Option<String>,
+    /// Trading 212 API environment used in live mode
+    #[serde(default)]
+    pub api_environment: ApiEnvironment,
     /// Default currency for portfolio calculations
     pub currency: Currency,
     /// Interval between portfolio updates in seconds
     pub portfolio_update_interval: Duration,
+    /// Skip the regular portfolio update while the markets of all
+    /// positions are closed
+    #[serde(default)]
+    pub refresh_only_when_market_open: bool,
+    /// How long the downloaded instrument metadata is reused before it is
+    /// fetched again
+    #[serde(default = "default_instrument_metadata_ttl")]
+    pub instrument_metadata_ttl: Duration,
+    /// Source of quotes, dividends and company profiles in live mode
+    #[serde(default)]
+    pub market_data: MarketDataSource,
+    /// How long the market data of a symbol is reused before it is
+    /// fetched again
+    #[serde(default = "default_market_data_ttl")]
+    pub market_data_ttl: Duration,
+    /// Range and data sets of the CSV exports
+    #[serde(default)]
+    pub export: ExportSettings,
+    /// Market data symbols of the indices or ETFs the portfolio is
+    /// compared with, such as `VWRL.L` or `^GSPC`
+    #[serde(default)]
+    pub benchmarks: Vec<String>,
+    /// Limits above which the allocation page flags a concentration
+    #[serde(default)]
+    pub concentration: ConcentrationLimits,
 }
 
 impl Default for Config {
@@ -102,8 +534,18 @@ impl Default for Config {
         Self {
             mode: Mode::default(),
             api_key: None,
+            accounts: Vec::new(),
+            selected_account: None,
+            api_environment: ApiEnvironment::default(),
             currency: Currency::default(),
             portfolio_update_interval: default_timeout(),
+            refresh_only_when_market_open: false,
+            instrument_metadata_ttl: default_instrument_metadata_ttl(),
+            market_data: MarketDataSource::default(),
+            market_data_ttl: default_market_data_ttl(),
+            export: ExportSettings::default(),
+            benchmarks: Vec::new(),
+            concentration: ConcentrationLimits::default(),
         }
     }
 }
@@ -119,7 +561,114 @@ fn default_timeout() -> Duration {
     Duration::from_secs(DEFAULT_PORTFOLIO_UPDATE_TIME_S)
 }
 
+/// Returns the default lifetime of the cached instrument metadata
+fn default_instrument_metadata_ttl() -> Duration {
+    Duration::from_secs(DEFAULT_INSTRUMENT_METADATA_TTL_S)
+}
+
+/// Returns the default lifetime of the cached market data of a symbol
+fn default_market_data_ttl() -> Duration {
+    Duration::from_secs(DEFAULT_MARKET_DATA_TTL_S)
+}
+
+/// Converts a cache lifetime entered in hours in the settings
+///
+/// # Returns
+/// - `Ok(Duration)` if `hours` is at most `MAX_CACHE_TTL_HOURS`
+/// - `Err(ConfigError::InvalidCacheTtl)` otherwise
+pub fn cache_ttl_from_hours(hours: u64) -> Result<Duration, ConfigError> {
+    if hours > MAX_CACHE_TTL_HOURS {
+        return Err(ConfigError::InvalidCacheTtl(format!(
+            "must be at most {} hours",
+            MAX_CACHE_TTL_HOURS
+        )));
+    }
+    Ok(Duration::from_secs(hours * 60 * 60))
+}
+
 impl Config {
+    /// Returns the accounts used in live mode
+    ///
+    /// Configs written before multiple accounts were supported only have
+    /// `api_key`, which is treated as a single Invest account.
+    pub fn live_accounts(&self) -> Vec<Account> {
+        if !self.accounts.is_empty() {
+            return self.accounts.clone();
+        }
+        match &self.api_key {
+            Some(key) if !key.trim().is_empty() => vec![Account {
+                name: AccountKind::Invest.to_string(),
+                kind: AccountKind::Invest,
+                api_key: key.clone(),
+            }],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the accounts of the current view: the selected account, or
+    /// every account when none (or an unknown one) is selected
+    pub fn selected_accounts(&self) -> Vec<Account> {
+        let accounts = self.live_accounts();
+        match &self.selected_account {
+            Some(name) if accounts.iter().any(|a| &a.name == name) => {
+                accounts.into_iter().filter(|a| &a.name == name).collect()
+            }
+            _ => accounts,
+        }
+    }
+
+    /// Returns a copy of the config that authenticates as `account`
+    ///
+    /// Everything that talks to Trading 212 reads `api_key`, so this is
+    /// how a request is made on behalf of one account.
+    pub fn for_account(&self, account: &Account) -> Config {
+        Config {
+            api_key: Some(account.api_key.clone()),
+            ..self.clone()
+        }
+    }
+
+    /// Checks that every account has a unique, non-empty name and an API key.
+    /// Names must also differ in their directory names, ignoring case, so
+    /// that the accounts never share export files.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the accounts are valid
+    /// - `Err(ConfigError::InvalidAccounts)` otherwise
+    pub fn validate_accounts(accounts: &[Account]) -> Result<(), ConfigError> {
+        for (i, account) in accounts.iter().enumerate() {
+            if account.name.trim().is_empty() {
+                return Err(ConfigError::InvalidAccounts(format!(
+                    "account {} has no name",
+                    i + 1
+                )));
+            }
+            if account.api_key.trim().is_empty() {
+                return Err(ConfigError::InvalidAccounts(format!(
+                    "account '{}' has no API key",
+                    account.name
+                )));
+            }
+            if accounts[..i].iter().any(|a| a.name == account.name) {
+                return Err(ConfigError::InvalidAccounts(format!(
+                    "account name '{}' is used more than once",
+                    account.name
+                )));
+            }
+            let dir = Account::dir_name(&account.name).to_lowercase();
+            if let Some(other) = accounts[..i]
+                .iter()
+                .find(|a| Account::dir_name(&a.name).to_lowercase() == dir)
+            {
+                return Err(ConfigError::InvalidAccounts(format!(
+                    "account names '{}' and '{}' are too similar, they differ only in case or punctuation",
+                    other.name, account.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
     /// Saves the current configuration to the config file
     ///
     /// This method serializes the Config struct to JSON and writes it
@@ -147,7 +696,9 @@ impl Config {
     ///
     /// This method reads the configuration file and deserializes it
     /// into a Config struct. If the file doesn't exist, a default
-    /// configuration is created and saved.
+    /// configuration is created and saved. A config without an API
+    /// environment takes it from the `T212_TARGET` variable of older
+    /// versions.
     ///
     /// # Returns
     /// - `Ok(Config)` containing the loaded configuration
@@ -160,16 +711,48 @@ impl Config {
     /// println!("Mode: {}", config.mode);
     /// ```
     pub fn load_config() -> Result<Self, ConfigError> {
+        let target = std::env::var("T212_TARGET").ok();
         if !Path::new(CONFIG_FILE).exists() {
-            let config = Config::default();
+            let mut config = Config::default();
+            config.migrate_target(false, target.as_deref());
             let _ = config.save_config();
+            return Ok(config);
         }
 
         let file = File::open(CONFIG_FILE)?;
         let reader = BufReader::new(file);
-        let config = serde_json::from_reader(reader)?;
+        let value: serde_json::Value = serde_json::from_reader(reader)?;
+        let has_environment = value.get("api_environment").is_some();
+        let mut config: Config = serde_json::from_value(value)?;
+        if config.migrate_target(has_environment, target.as_deref()) {
+            let _ = config.save_config();
+        }
         Ok(config)
     }
+
+    /// Seeds the API environment from the `T212_TARGET` variable when the
+    /// config has none yet. A saved environment is kept, with a warning if
+    /// the variable asks for another one. Returns true if the config changed.
+    fn migrate_target(&mut self, has_environment: bool, target: Option<&str>) -> bool {
+        let Some(target) = target else {
+            return false;
+        };
+        if has_environment {
+            if ApiEnvironment::from_target(target) != self.api_environment {
+                eprintln!(
+                    "T212_TARGET is no longer used, the {} API environment set under Settings applies",
+                    self.api_environment
+                );
+            }
+            return false;
+        }
+        self.api_environment = ApiEnvironment::from_target(target);
+        println!(
+            "Using the {} API environment from T212_TARGET, it can be changed under Settings",
+            self.api_environment
+        );
+        true
+    }
 }
 
 #[cfg(test)]
@@ -183,6 +766,7 @@ mod tests {
         let config = Config::default();
         assert_eq!(config.mode, Mode::Demo);
         assert_eq!(config.api_key, None);
+        assert_eq!(config.api_environment, ApiEnvironment::Live);
         assert_eq!(config.currency, Currency::GBP);
         assert_eq!(
             config.portfolio_update_interval,
@@ -196,8 +780,25 @@ mod tests {
         let config = Config {
             mode: Mode::Live,
             api_key: Some("test_key".to_string()),
+            accounts: Vec::new(),
+            selected_account: None,
+            api_environment: ApiEnvironment::Custom("http://127.0.0.1:8080".to_string()),
             currency: Currency::USD,
             portfolio_update_interval: Duration::from_secs(1800),
+            refresh_only_when_market_open: true,
+            instrument_metadata_ttl: Duration::from_secs(3600),
+            market_data: MarketDataSource::Directory("market_data".to_string()),
+            market_data_ttl: Duration::from_secs(600),
+            export: ExportSettings {
+                history_years: 5,
+                include_orders: true,
+                ..ExportSettings::default()
+            },
+            benchmarks: vec!["VWRL.L".to_string(), "^GSPC".to_string()],
+            concentration: ConcentrationLimits {
+                sector: 25.0,
+                ..ConcentrationLimits::default()
+            },
         };
 
         // Serialize to JSON
@@ -208,11 +809,29 @@ mod tests {
 
         assert_eq!(deserialized.mode, Mode::Live);
         assert_eq!(deserialized.api_key, Some("test_key".to_string()));
+        assert_eq!(
+            deserialized.api_environment.base_url(),
+            "http://127.0.0.1:8080"
+        );
         assert_eq!(deserialized.currency, Currency::USD);
         assert_eq!(
             deserialized.portfolio_update_interval,
             Duration::from_secs(1800)
         );
+        assert!(deserialized.refresh_only_when_market_open);
+        assert_eq!(
+            deserialized.instrument_metadata_ttl,
+            Duration::from_secs(3600)
+        );
+        assert_eq!(
+            deserialized.market_data,
+            MarketDataSource::Directory("market_data".to_string())
+        );
+        assert_eq!(deserialized.market_data_ttl, Duration::from_secs(600));
+        assert_eq!(deserialized.export.history_years, 5);
+        assert!(deserialized.export.include_orders);
+        assert_eq!(deserialized.benchmarks, vec!["VWRL.L", "^GSPC"]);
+        assert_eq!(deserialized.concentration.sector, 25.0);
     }
 
     /// Test Config save and load functionality
@@ -230,8 +849,22 @@ mod tests {
         let test_config = Config {
             mode: Mode::Live,
             api_key: Some("test_api_key".to_string()),
+            accounts: vec![Account {
+                name: "ISA".to_string(),
+                kind: AccountKind::Isa,
+                api_key: "isa_key".to_string(),
+            }],
+            selected_account: Some("ISA".to_string()),
+            api_environment: ApiEnvironment::Practice,
             currency: Currency::EUR,
             portfolio_update_interval: Duration::from_secs(1200),
+            refresh_only_when_market_open: false,
+            instrument_metadata_ttl: default_instrument_metadata_ttl(),
+            market_data: MarketDataSource::default(),
+            market_data_ttl: default_market_data_ttl(),
+            export: ExportSettings::default(),
+            benchmarks: Vec::new(),
+            concentration: ConcentrationLimits::default(),
         };
 
         // Test save
@@ -253,6 +886,9 @@ mod tests {
         let loaded = loaded_config.unwrap();
         assert_eq!(loaded.mode, Mode::Live);
         assert_eq!(loaded.api_key, Some("test_api_key".to_string()));
+        assert_eq!(loaded.api_environment, ApiEnvironment::Practice);
+        assert_eq!(loaded.accounts[0].kind, AccountKind::Isa);
+        assert_eq!(loaded.selected_account, Some("ISA".to_string()));
         assert_eq!(loaded.currency, Currency::EUR);
         assert_eq!(loaded.portfolio_update_interval, Duration::from_secs(1200));
 
@@ -263,4 +899,158 @@ mod tests {
             let _ = fs::remove_file(CONFIG_FILE);
         }
     }
+
+    /// Test that configs saved before the API environment existed still load
+    #[test]
+    fn test_config_without_api_environment() {
+        let json = r#"{"mode":"Live","api_key":null,"currency":"GBP","portfolio_update_interval":{"secs":60,"nanos":0}}"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.api_environment, ApiEnvironment::Live);
+        assert!(!config.refresh_only_when_market_open);
+        assert_eq!(
+            config.instrument_metadata_ttl,
+            default_instrument_metadata_ttl()
+        );
+    }
+
+    /// Test that T212_TARGET of older versions only seeds a missing environment
+    #[test]
+    fn test_migrate_target() {
+        let mut config = Config::default();
+        assert!(!config.migrate_target(false, None));
+        assert_eq!(config.api_environment, ApiEnvironment::Live);
+
+        assert!(config.migrate_target(false, Some("demo")));
+        assert_eq!(config.api_environment, ApiEnvironment::Practice);
+
+        // A saved environment wins over the variable
+        assert!(!config.migrate_target(true, Some("live")));
+        assert_eq!(config.api_environment, ApiEnvironment::Practice);
+    }
+
+    /// Test validation of the API environment from the settings form
+    #[test]
+    fn test_api_environment_from_form() {
+        assert_eq!(
+            ApiEnvironment::from_form("Practice", None)
+                .unwrap()
+                .base_url(),
+            "https://demo.trading212.com"
+        );
+        assert_eq!(
+            ApiEnvironment::from_form("Custom", Some(" http://localhost:8080/ "))
+                .unwrap()
+                .base_url(),
+            "http://localhost:8080"
+        );
+        assert!(ApiEnvironment::from_form("Custom", None).is_err());
+        assert!(ApiEnvironment::from_form("Custom", Some("ftp://localhost")).is_err());
+        assert!(ApiEnvironment::from_form("Custom", Some("http://localhost/api/v0")).is_err());
+        assert!(ApiEnvironment::from_form("Staging", None).is_err());
+    }
+
+    /// Test validation of the market data source from the settings form
+    #[test]
+    fn test_market_data_source_from_form() {
+        assert_eq!(
+            MarketDataSource::from_form("Yahoo", Some("ignored")).unwrap(),
+            MarketDataSource::Yahoo
+        );
+        assert_eq!(
+            MarketDataSource::from_form("Http", Some(" http://localhost:9000/quotes "))
+                .unwrap()
+                .location(),
+            "http://localhost:9000/quotes"
+        );
+        assert!(MarketDataSource::from_form("Directory", Some(" ")).is_err());
+        assert!(MarketDataSource::from_form("Http", Some("localhost:9000")).is_err());
+        assert!(MarketDataSource::from_form("Bloomberg", None).is_err());
+    }
+
+    /// Test the legacy single API key and the account selection
+    #[test]
+    fn test_live_and_selected_accounts() {
+        let mut config = Config {
+            api_key: Some("legacy_key".to_string()),
+            ..Config::default()
+        };
+        let accounts = config.live_accounts();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].api_key, "legacy_key");
+
+        config.accounts = vec![
+            Account {
+                name: "Invest".to_string(),
+                kind: AccountKind::Invest,
+                api_key: "invest_key".to_string(),
+            },
+            Account {
+                name: "ISA".to_string(),
+                kind: AccountKind::Isa,
+                api_key: "isa_key".to_string(),
+            },
+        ];
+        assert_eq!(config.selected_accounts().len(), 2);
+
+        config.selected_account = Some("ISA".to_string());
+        let selected = config.selected_accounts();
+        assert_eq!(selected.len(), 1);
+        assert!(selected[0].kind.is_tax_free());
+        assert_eq!(
+            config.for_account(&selected[0]).api_key,
+            Some("isa_key".to_string())
+        );
+
+        // An account that no longer exists falls back to all accounts
+        config.selected_account = Some("Old".to_string());
+        assert_eq!(config.selected_accounts().len(), 2);
+    }
+
+    /// Test validation of the accounts from the settings form
+    #[test]
+    fn test_validate_accounts() {
+        let account = |name: &str, key: &str| Account {
+            name: name.to_string(),
+            kind: AccountKind::Invest,
+            api_key: key.to_string(),
+        };
+        assert!(Config::validate_accounts(&[account("Invest", "a"), account("ISA", "b")]).is_ok());
+        assert!(Config::validate_accounts(&[account("", "a")]).is_err());
+        assert!(Config::validate_accounts(&[account("Invest", " ")]).is_err());
+        assert!(Config::validate_accounts(&[account("ISA", "a"), account("ISA", "b")]).is_err());
+        assert!(
+            Config::validate_accounts(&[account("My ISA", "a"), account("my_isa", "b")]).is_err()
+        );
+    }
+
+    /// Test validation of the export range and data sets
+    #[test]
+    fn test_validate_export_settings() {
+        assert!(ExportSettings::default().validate().is_ok());
+        let invalid = [
+            ExportSettings {
+                history_years: 0,
+                ..ExportSettings::default()
+            },
+            ExportSettings {
+                chunk_days: MAX_EXPORT_CHUNK_DAYS + 1,
+                ..ExportSettings::default()
+            },
+            ExportSettings {
+                include_dividends: false,
+                ..ExportSettings::default()
+            },
+        ];
+        for settings in invalid {
+            assert!(settings.validate().is_err(), "{:?}", settings);
+        }
+    }
+
+    #[test]
+    fn test_cache_ttl_from_hours() {
+        assert_eq!(cache_ttl_from_hours(2).unwrap(), Duration::from_secs(7200));
+        assert!(cache_ttl_from_hours(MAX_CACHE_TTL_HOURS).is_ok());
+        assert!(cache_ttl_from_hours(MAX_CACHE_TTL_HOURS + 1).is_err());
+        assert!(cache_ttl_from_hours(u64::MAX).is_err());
+    }
 }
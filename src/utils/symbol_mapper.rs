@@ -78,6 +78,12 @@ pub fn extract_symbol(t212_ticker: &str) -> (String, StockInfo) {
     )
 }
 
+/// True if the ticker has an entry in symbol_mapper.json, rather than a
+/// Yahoo Finance ticker guessed by `extract_symbol`
+pub fn is_mapped(t212_ticker: &str) -> bool {
+    STOCKS_LUT.contains_key(t212_ticker)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,5 +100,7 @@ mod tests {
         let (ticker, info) = extract_symbol("PHNXl_EQ");
         assert_eq!(ticker, "PHNXl_EQ");
         assert_eq!(info.yf_ticker, "PHNX.L");
+        assert!(!is_mapped("PHNXl_EQ"));
+        assert!(is_mapped("AAPL_US"));
     }
 }
@@ -18,17 +18,19 @@
 
 use askama::Template;
 use axum::{
-    extract::Form,
+    extract::Path,
+    extract::Query,
     extract::State,
-    http::StatusCode,
+    extract::{DefaultBodyLimit, Form, Multipart},
+    http::{header, HeaderMap, StatusCode},
     response::{Html, IntoResponse},
     routing::{get, post},
     Router,
 };
 
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -39,13 +41,41 @@ use tokio::time::{sleep, Duration};
 
 use crate::{
     models::{
-        dividend::DividendInfo,
-        portfolio::{download_export_if_needed, Portfolio, Position},
+        allocation::{self, Allocation, AllocationDimension},
+        benchmark::{self, ReturnPeriod},
+        corporate_action::{per_share_factor, CorporateAction},
+        dividend::{fetch_dividend_history, DividendInfo, DividendPayment},
+        history::{DividendEvent, HistoryEvent},
+        market::MarketStatus,
+        order::{
+            summarize_by_ticker, Order, OrderFilter, OrderHistoryCache, OrderSide,
+            TickerOrderSummary,
+        },
+        pie::{Pie, PieCache},
+        portfolio::{AccountSummary, Portfolio, Position},
+        transaction::{CashLedger, CashLedgerCache, MonthlyCashflow, TransactionKind},
+    },
+    services::{
+        export::{
+            read_history, validate_upload, ExportJob, ExportJobManager, ExportJobStatus,
+            EXPORT_JOBS_FILE,
+        },
+        instruments::{InstrumentCache, INSTRUMENTS_CACHE_FILE},
+        market_data::{MarketDataCache, MARKET_DATA_CACHE_FILE},
+        orchestrator::Orchestrator,
+        price_history::{PriceHistory, PRICE_HISTORY_FILE},
+        value_history::{ValueHistory, VALUE_HISTORY_FILE},
+    },
+    utils::settings::{
+        cache_ttl_from_hours, Account, AccountKind, ApiEnvironment, ConcentrationLimits, Config,
+        ExportSettings, MarketDataSource, Mode, MAX_EXPORT_CHUNK_DAYS,
     },
-    services::orchestrator::Orchestrator,
-    utils::settings::{Config, Mode},
 };
 
+/// Largest request accepted by the export upload, several years of exports
+/// easily exceed the default limit of 2 MB
+const MAX_UPLOAD_BYTES: usize = 50 * 1024 * 1024;
+
 pub struct UpComingDivPaymetsPred {
     pub symbol: String,
     pub payment_date: String,
@@ -57,6 +87,50 @@ pub struct UpComingDivPaymetsPred {
     pub net_dividend: f64,
 }
 
+/// Dividend growth of one holding, formatted for the dividends page
+pub struct DividendGrowthRow {
+    pub symbol: String,
+    pub payments: usize,
+    pub first_payment: String,
+    pub cagr_1y: String,
+    pub cagr_3y: String,
+    pub cagr_5y: String,
+    pub cagr_10y: String,
+    pub consecutive_increases: u32,
+    /// Years of the cuts with the change in percent
+    pub cuts: Vec<String>,
+}
+
+impl DividendGrowthRow {
+    fn new(position: &Position) -> Self {
+        let growth = &position.dividend_growth;
+        let percent = |value: Option<f64>| {
+            value
+                .map(|v| format!("{:.2}%", v))
+                .unwrap_or_else(|| "-".to_string())
+        };
+        Self {
+            symbol: position.ticker.clone(),
+            payments: position.dividend_history.len(),
+            first_payment: position
+                .dividend_history
+                .first()
+                .map(|p| p.date.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            cagr_1y: percent(growth.cagr_1y),
+            cagr_3y: percent(growth.cagr_3y),
+            cagr_5y: percent(growth.cagr_5y),
+            cagr_10y: percent(growth.cagr_10y),
+            consecutive_increases: growth.consecutive_increases,
+            cuts: growth
+                .cuts
+                .iter()
+                .map(|cut| format!("{} ({:.1}%)", cut.year, cut.percent()))
+                .collect(),
+        }
+    }
+}
+
 #[derive(Template)]
 #[template(path = "dividends.html")]
 pub struct DividendsTemplate {
@@ -65,6 +139,8 @@ pub struct DividendsTemplate {
     pub div_yield: String,
     pub yield_on_cost: String,
     pub upcoming_payments: Vec<UpComingDivPaymetsPred>,
+    pub growth: Vec<DividendGrowthRow>,
+    pub accounts: Vec<AccountSummary>,
     pub settings: Config,
 }
 
@@ -72,10 +148,8 @@ pub struct DividendsTemplate {
 #[template(path = "payout.html")]
 pub struct PayoutTemplate {
     pub records: Vec<DividendRecord>,
-    pub total_dividends: String,
-    pub total_wht: String,
-    pub ticker_summary: Vec<TickerSummary>,
-    pub monthly_div_summary: Vec<(String, f64)>,
+    /// One summary per selected account
+    pub accounts: Vec<AccountPayoutSummary>,
     pub settings: Config,
 }
 
@@ -85,11 +159,158 @@ pub struct PortfolioTemplate {
     pub positions: Vec<Position>,
     pub total_invested: String,
     pub total_current_value: String,
+    pub holdings_value: String,
     pub total_pl: String,
+    pub free_cash: String,
+    pub blocked_cash: String,
+    pub cash_invested: String,
+    pub cash_result: String,
+    pub account_total: String,
+    pub cash_weight: String,
     pub last_updated: String,
+    pub accounts: Vec<AccountSummary>,
+    /// Number of positions with data-quality problems
+    pub data_issue_count: usize,
+    pub settings: Config,
+}
+
+#[derive(Template)]
+#[template(path = "orders.html")]
+pub struct OrdersTemplate {
+    pub orders: Vec<Order>,
+    pub ticker_summary: Vec<TickerOrderSummary>,
+    pub filter_ticker: String,
+    pub filter_from: String,
+    pub filter_to: String,
+    pub total_fees: String,
     pub settings: Config,
 }
 
+#[derive(Template)]
+#[template(path = "cashflow.html")]
+pub struct CashflowTemplate {
+    pub monthly: Vec<MonthlyCashflow>,
+    pub total_deposits: String,
+    pub total_withdrawals: String,
+    pub total_fees: String,
+    pub total_interest: String,
+    /// Interest is only known from the CSV exports
+    pub show_interest: bool,
+    pub net_contributions: String,
+    pub portfolio_value: String,
+    pub market_growth: String,
+    pub market_growth_percent: String,
+    pub settings: Config,
+}
+
+#[derive(Template)]
+#[template(path = "benchmarks.html")]
+pub struct BenchmarksTemplate {
+    /// Labels of the compared periods, in the order of the returns
+    pub periods: Vec<&'static str>,
+    pub portfolio: BenchmarkRow,
+    pub benchmarks: Vec<BenchmarkRow>,
+    pub net_contributions: String,
+    pub first_contribution: String,
+    /// Values over time in the format read by the chart script
+    pub chart_data: String,
+    pub settings: Config,
+}
+
+/// Returns of the portfolio, or of a benchmark bought with the same
+/// contributions
+pub struct BenchmarkRow {
+    pub name: String,
+    /// Value today, "-" when unknown
+    pub value: String,
+    pub returns: Vec<ReturnCell>,
+    /// Why the benchmark could not be compared
+    pub error: Option<String>,
+}
+
+pub struct ReturnCell {
+    pub percent: String,
+    /// Return of the portfolio minus the return of the benchmark, in
+    /// percentage points. Empty for the portfolio itself.
+    pub difference: String,
+}
+
+fn format_return(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| format!("{:.2}%", v))
+}
+
+#[derive(Template)]
+#[template(path = "allocation.html")]
+pub struct AllocationTemplate {
+    pub allocations: Vec<Allocation>,
+    /// Groups and positions above their concentration limit
+    pub concentrations: Vec<String>,
+    pub holdings_value: String,
+    pub annual_income: String,
+    /// Weight of each group per dimension, in the format read by the chart
+    /// script
+    pub chart_data: String,
+    pub settings: Config,
+}
+
+#[derive(Template)]
+#[template(path = "pies.html")]
+pub struct PiesTemplate {
+    pub pies: Vec<Pie>,
+    pub total_invested: String,
+    pub total_value: String,
+    pub total_result: String,
+    pub total_cash: String,
+    pub settings: Config,
+}
+
+#[derive(Template)]
+#[template(path = "exports.html")]
+pub struct ExportsTemplate {
+    pub jobs: Vec<ExportJob>,
+    pub refreshing: bool,
+    pub settings: Config,
+}
+
+#[derive(Template)]
+#[template(path = "position.html")]
+pub struct PositionTemplate {
+    pub ticker: String,
+    pub yf_ticker: String,
+    /// Holdings of the ticker, one per account
+    pub holdings: Vec<Position>,
+    pub quantity: String,
+    pub average_price: String,
+    pub current_price: String,
+    pub currency: String,
+    /// Closes, average cost and buys in the format read by the chart script
+    pub chart_data: String,
+    pub corporate_actions: Vec<CorporateAction>,
+    pub has_history: bool,
+    pub settings: Config,
+}
+
+#[derive(Template)]
+#[template(path = "data_health.html")]
+pub struct DataHealthTemplate {
+    pub rows: Vec<DataHealthRow>,
+    pub positions_checked: usize,
+    pub error_count: usize,
+    pub settings: Config,
+}
+
+/// One problem with the data of a position
+#[derive(Debug, Clone)]
+pub struct DataHealthRow {
+    pub account: String,
+    pub ticker: String,
+    pub yf_ticker: String,
+    pub kind: String,
+    pub details: String,
+    pub suggestion: String,
+    pub is_error: bool,
+}
+
 #[derive(Template)]
 #[template(path = "settings.html")]
 pub struct SettingsTemplate {
@@ -99,6 +320,7 @@ pub struct SettingsTemplate {
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct DividendRecord {
+    pub account: String,
     pub date: String,
     pub isin: String,
     pub ticker: String,
@@ -107,7 +329,109 @@ pub struct DividendRecord {
     pub price: String,
     pub currency: String,
     pub total: String,
+    /// Empty when the source does not report the withholding tax
     pub withholding_tax: String,
+    /// Sign of the account currency `total` and the tax are in
+    pub sign: String,
+}
+
+impl DividendRecord {
+    /// Withholding tax with the currency sign, or `n/a` when it is unknown
+    pub fn wht_display(&self) -> String {
+        match self.withholding_tax.parse::<f64>() {
+            Ok(wht) => format!("{}{:.2}", self.sign, wht),
+            Err(_) => "n/a".to_string(),
+        }
+    }
+}
+
+/// Sign amounts in `currency` are shown with
+fn currency_sign(currency: &str) -> String {
+    match currency {
+        "" | "GBP" => "£".to_string(),
+        "EUR" => "€".to_string(),
+        "USD" => "$".to_string(),
+        other => format!("{} ", other),
+    }
+}
+
+/// Withholding tax of `records` together, with the currency `sign`. It is
+/// `n/a` when one of them does not report it, since a partial sum would
+/// understate the tax.
+fn total_withholding_tax<'a>(
+    records: impl IntoIterator<Item = &'a DividendRecord>,
+    sign: &str,
+) -> String {
+    let mut total = 0.0;
+    for record in records {
+        match record.withholding_tax.parse::<f64>() {
+            Ok(wht) => total += wht,
+            Err(_) => return "n/a".to_string(),
+        }
+    }
+    format!("{}{:.2}", sign, total)
+}
+
+/// Dividends received by one account, in the account currency. ISA income
+/// is tax-free and the accounts may hold different currencies, so the
+/// accounts are never added up on the payouts page.
+#[derive(Debug, Clone)]
+pub struct AccountPayoutSummary {
+    pub account: String,
+    pub tax_free: bool,
+    pub sign: String,
+    pub total: String,
+    pub wht: String,
+    pub ticker_summary: Vec<TickerSummary>,
+    pub monthly_div_summary: Vec<(String, f64)>,
+    /// An export of the account is being generated, its records may be out
+    /// of date
+    pub refreshing: bool,
+}
+
+impl AccountPayoutSummary {
+    fn new(account: &Account, sign: String, records: &[DividendRecord], refreshing: bool) -> Self {
+        let total: f64 = records
+            .iter()
+            .filter_map(|r| r.total.parse::<f64>().ok())
+            .sum();
+
+        // Group by ticker
+        let mut ticker_map: HashMap<String, Vec<&DividendRecord>> = HashMap::new();
+        for record in records {
+            ticker_map
+                .entry(record.ticker.clone())
+                .or_default()
+                .push(record);
+        }
+        let mut ticker_summary: Vec<(f64, TickerSummary)> = ticker_map
+            .into_iter()
+            .map(|(ticker, ticker_records)| {
+                let total: f64 = ticker_records
+                    .iter()
+                    .filter_map(|r| r.total.parse::<f64>().ok())
+                    .sum();
+                let summary = TickerSummary {
+                    ticker,
+                    total: format!("{:.2}", total),
+                    wht: total_withholding_tax(ticker_records, &sign),
+                };
+                (total, summary)
+            })
+            .collect();
+        ticker_summary.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        Self {
+            account: account.name.clone(),
+            tax_free: account.kind.is_tax_free(),
+            total: format!("{:.2}", total),
+            wht: total_withholding_tax(records, &sign),
+            ticker_summary: ticker_summary.into_iter().map(|(_, s)| s).collect(),
+            monthly_div_summary: calculate_monthly_dividends(records),
+            sign,
+            refreshing,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -137,57 +461,116 @@ pub fn calculate_monthly_dividends(records: &[DividendRecord]) -> Vec<(String, f
 
     result
 }
+
+impl From<DividendEvent> for DividendRecord {
+    fn from(dividend: DividendEvent) -> Self {
+        DividendRecord {
+            account: String::new(),
+            date: dividend.time.format("%Y-%m-%d").to_string(),
+            isin: dividend.instrument.isin,
+            ticker: dividend.instrument.ticker,
+            name: dividend.instrument.name,
+            quantity: format!("{:.4}", dividend.quantity),
+            price: format!("{:.4}", dividend.price_per_share),
+            currency: dividend.price_currency,
+            total: format!("{:.2}", dividend.total),
+            withholding_tax: format!("{:.2}", dividend.withholding_tax),
+            sign: currency_sign(""),
+        }
+    }
+}
+
+impl From<DividendPayment> for DividendRecord {
+    fn from(payment: DividendPayment) -> Self {
+        DividendRecord {
+            account: String::new(),
+            date: payment.paid_on.format("%Y-%m-%d %H:%M:%S").to_string(),
+            isin: String::new(),
+            ticker: payment.ticker,
+            name: String::new(),
+            quantity: format!("{:.4}", payment.quantity),
+            price: format!("{:.4}", payment.gross_amount_per_share),
+            currency: String::new(),
+            total: format!("{:.2}", payment.amount),
+            // The dividends API only reports the net amount in the account
+            // currency and the gross amount in the instrument currency,
+            // without the FX rate between them
+            withholding_tax: String::new(),
+            sign: currency_sign(""),
+        }
+    }
+}
+
+/// Loads the dividend payouts of `account` from the `/history/dividends`
+/// API. The CSV exports of the account are only used as a fallback when the
+/// API request fails.
 pub async fn get_latest_dividend_records(
     config: &Config,
+    account: &Account,
+    exports: &ExportJobManager,
 ) -> Result<Vec<DividendRecord>, Box<dyn std::error::Error>> {
-    download_export_if_needed(config).await?;
-
-    // Find the latest export file
-    let entries = std::fs::read_dir(".")?;
-    let latest_export = entries
-        .filter_map(|entry| {
-            let entry = entry.ok()?;
-            let path = entry.path();
-            if path.is_file() {
-                let filename = path.file_name()?.to_str()?;
-                if filename.starts_with("export_") && filename.ends_with(".csv") {
-                    return Some(path);
-                }
-            }
-            None
-        })
-        .max_by_key(|path| path.metadata().ok().and_then(|m| m.modified().ok()))
-        .ok_or("No export files found")?;
-
-    let content = std::fs::read_to_string(&latest_export)?;
-    let mut rdr = csv::Reader::from_reader(content.as_bytes());
-    let mut records = Vec::new();
-
-    for result in rdr.records() {
-        let record = result?;
-        if record.len() >= 13 {
-            let date = if let Ok(dt) = NaiveDateTime::parse_from_str(&record[1], "%d/%m/%Y %H:%M") {
-                dt.format("%Y-%m-%d").to_string()
-            } else {
-                record[1].to_string()
-            };
+    match fetch_dividend_history(&config.for_account(account)).await {
+        Ok(payments) => {
+            // Imported exports may reach further back than the API
+            let oldest = payments.iter().map(|p| p.paid_on).min();
+            let imported = imported_history_before(exports, account, oldest)
+                .into_iter()
+                .filter_map(|event| match event {
+                    HistoryEvent::Dividend(dividend) => Some(DividendRecord::from(dividend)),
+                    _ => None,
+                });
+            Ok(payments
+                .into_iter()
+                .map(DividendRecord::from)
+                .chain(imported)
+                .collect())
+        }
+        Err(e) => {
+            println!(
+                "Failed to fetch dividends of {} from the API, falling back to CSV export: {}",
+                account.name, e
+            );
+            get_export_dividend_records(account, exports).await
+        }
+    }
+}
 
-            let total: f64 = record[9].parse().unwrap_or(0.0);
-            let wht: f64 = record[11].parse().unwrap_or(0.0);
-
-            records.push(DividendRecord {
-                date,
-                isin: record[2].to_string(),
-                ticker: record[3].to_string(),
-                name: record[4].to_string(),
-                quantity: format!("{:.4}", record[5].parse::<f64>().unwrap_or(0.0)),
-                price: format!("{:.4}", record[6].parse::<f64>().unwrap_or(0.0)),
-                currency: record[7].to_string(),
-                total: format!("{:.2}", total),
-                withholding_tax: format!("{:.2}", wht),
-            });
+/// Events of the stored export history of `account` older than `cutoff`,
+/// which is the oldest entry the API returned for the account
+fn imported_history_before(
+    exports: &ExportJobManager,
+    account: &Account,
+    cutoff: Option<DateTime<Utc>>,
+) -> Vec<HistoryEvent> {
+    match read_history(&exports.account_dir(&account.name)) {
+        Ok(events) => events
+            .into_iter()
+            .filter(|e| cutoff.is_none_or(|c| e.time() < c))
+            .collect(),
+        Err(e) => {
+            eprintln!(
+                "Failed to read the export history of {}: {}",
+                account.name, e
+            );
+            Vec::new()
         }
     }
+}
+
+/// Reads the dividends of the stored export history of `account`. Missing
+/// or outdated periods are exported with the portfolio updates, until then
+/// the last export is used as is.
+async fn get_export_dividend_records(
+    account: &Account,
+    exports: &ExportJobManager,
+) -> Result<Vec<DividendRecord>, Box<dyn std::error::Error>> {
+    let mut records: Vec<DividendRecord> = read_history(&exports.account_dir(&account.name))?
+        .into_iter()
+        .filter_map(|event| match event {
+            HistoryEvent::Dividend(dividend) => Some(DividendRecord::from(dividend)),
+            _ => None,
+        })
+        .collect();
 
     // Sort records by date (newest first)
     records.sort_by(|a, b| b.date.cmp(&a.date));
@@ -201,6 +584,23 @@ pub struct AppState {
     pub config: Arc<TokioMutex<Config>>,
     pub tx: mpsc::Sender<()>,
     pub config_success: Arc<AtomicBool>,
+    pub exports: ExportJobManager,
+    pub orders: OrderHistoryCache,
+    pub cash: CashLedgerCache,
+    pub pies: PieCache,
+    /// The next portfolio update fetches the market data of every symbol
+    pub force_market_data: Arc<AtomicBool>,
+}
+
+/// Portfolio of the account picked in the account switcher, or the
+/// consolidated portfolio of all accounts
+async fn selected_portfolio(state: &AppState, config: &Config) -> Portfolio {
+    let portfolio = state.portfolio.lock().await;
+    config
+        .selected_account
+        .as_deref()
+        .and_then(|name| portfolio.for_account(name))
+        .unwrap_or_else(|| portfolio.clone())
 }
 
 // Handler for the dividends page
@@ -208,8 +608,8 @@ pub async fn show_dividends(State(state): State<AppState>) -> impl IntoResponse
     if !state.config_success.load(Ordering::SeqCst) {
         return axum::response::Redirect::to("/settings").into_response();
     }
-    let portfolio = state.portfolio.lock().await;
     let config = state.config.lock().await;
+    let portfolio = selected_portfolio(&state, &config).await;
     let mut dividends: Vec<DividendInfo> = portfolio
         .positions
         .iter()
@@ -239,49 +639,839 @@ pub async fn show_dividends(State(state): State<AppState>) -> impl IntoResponse
             .unwrap()
     });
 
-    let mut upcoming_payments: Vec<UpComingDivPaymetsPred> = Vec::new();
-    portfolio.positions.iter().for_each(|pos| {
-        if let Some(_pay) = &pos.div_prediction.payment_amount_per_share {
-            upcoming_payments.push(UpComingDivPaymetsPred {
-                symbol: pos.ticker.clone(),
-                payment_date: pos
-                    .div_prediction
-                    .next_payment_date
-                    .map(|d| d.format("%Y-%m-%d").to_string())
-                    .unwrap_or_else(|| "-".to_string()),
-                exdate: pos
-                    .div_prediction
-                    .next_exdate
-                    .map(|d| d.format("%Y-%m-%d").to_string())
-                    .unwrap_or_else(|| "-".to_string()),
-                div_per_share: pos.div_prediction.payment_amount_per_share.unwrap_or(0.0),
-                no_of_shares: pos.quantity,
-                total_dividend: pos.div_prediction.net_payment_amount.unwrap_or(0.0),
-                total_wht: pos.div_prediction.net_wht.unwrap_or(0.0),
-                net_dividend: pos
-                    .div_prediction
-                    .net_payment_amount_after_wht
-                    .unwrap_or(0.0),
+    let mut upcoming_payments: Vec<UpComingDivPaymetsPred> = Vec::new();
+    portfolio.positions.iter().for_each(|pos| {
+        if let Some(_pay) = &pos.div_prediction.payment_amount_per_share {
+            upcoming_payments.push(UpComingDivPaymetsPred {
+                symbol: pos.ticker.clone(),
+                payment_date: pos
+                    .div_prediction
+                    .next_payment_date
+                    .map(|d| d.format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                exdate: pos
+                    .div_prediction
+                    .next_exdate
+                    .map(|d| d.format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                div_per_share: pos.div_prediction.payment_amount_per_share.unwrap_or(0.0),
+                no_of_shares: pos.quantity,
+                total_dividend: pos.div_prediction.net_payment_amount.unwrap_or(0.0),
+                total_wht: pos.div_prediction.net_wht.unwrap_or(0.0),
+                net_dividend: pos
+                    .div_prediction
+                    .net_payment_amount_after_wht
+                    .unwrap_or(0.0),
+            });
+        }
+    });
+
+    upcoming_payments.sort_by(|a, b| {
+        NaiveDate::parse_from_str(&a.payment_date, "%Y-%m-%d")
+            .unwrap_or(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap())
+            .cmp(
+                &NaiveDate::parse_from_str(&b.payment_date, "%Y-%m-%d")
+                    .unwrap_or(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap()),
+            )
+    });
+
+    let mut growth: Vec<DividendGrowthRow> = portfolio
+        .positions
+        .iter()
+        .filter(|pos| !pos.dividend_history.is_empty())
+        .map(DividendGrowthRow::new)
+        .collect();
+    growth.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    growth.dedup_by(|a, b| a.symbol == b.symbol);
+
+    let template = DividendsTemplate {
+        dividends,
+        growth,
+        div_per_year: format!("{:.2}", div_per_year),
+        div_yield: format!("{:.2}", div_yield),
+        yield_on_cost: format!("{:.2}", yield_on_cost),
+        upcoming_payments,
+        accounts: portfolio.accounts.clone(),
+        settings: config.clone(),
+    };
+
+    match template.render() {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Template rendering error: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+// Handler for the dividends page
+pub async fn show_portfolio(State(state): State<AppState>) -> impl IntoResponse {
+    if !state.config_success.load(Ordering::SeqCst) {
+        return axum::response::Redirect::to("/settings").into_response();
+    }
+    let config = state.config.lock().await;
+    let mut portfolio = selected_portfolio(&state, &config).await;
+    // Market status changes between portfolio updates, so refresh it on every view
+    portfolio.update_market_status(Utc::now());
+    let positions = &portfolio.positions;
+    let total_pl: f64 = portfolio.positions.iter().map(|p| p.ppl).sum();
+    let cash = &portfolio.cash;
+    let cash_weight: f64 = if portfolio.total_value > 0.0 {
+        cash.uninvested() / portfolio.total_value * 100.0
+    } else {
+        0.0
+    };
+
+    let template = PortfolioTemplate {
+        positions: positions.to_vec(),
+        total_invested: format!("{:.2}", &portfolio.total_cost),
+        total_current_value: format!("{:.2}", &portfolio.total_value),
+        holdings_value: format!("{:.2}", &portfolio.holdings_value),
+        total_pl: format!("{:.2}", total_pl),
+        free_cash: format!("{:.2}", cash.free),
+        blocked_cash: format!("{:.2}", cash.blocked.unwrap_or(0.0)),
+        cash_invested: format!("{:.2}", cash.invested),
+        cash_result: format!("{:.2}", cash.result),
+        account_total: format!("{:.2}", cash.total),
+        cash_weight: format!("{:.2}", cash_weight),
+        last_updated: portfolio
+            .last_updated
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string(),
+        accounts: portfolio.accounts.clone(),
+        data_issue_count: positions
+            .iter()
+            .filter(|p| !p.data_issues.is_empty())
+            .count(),
+        settings: config.clone(),
+    };
+
+    match template.render() {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Template rendering error: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+// Handler for the page of a single position
+pub async fn show_position(
+    State(state): State<AppState>,
+    Path(ticker): Path<String>,
+) -> impl IntoResponse {
+    if !state.config_success.load(Ordering::SeqCst) {
+        return axum::response::Redirect::to("/settings").into_response();
+    }
+    let config = state.config.lock().await.clone();
+    let holdings: Vec<Position> = selected_portfolio(&state, &config)
+        .await
+        .positions
+        .into_iter()
+        .filter(|p| p.ticker == ticker)
+        .collect();
+    let Some(first) = holdings.first().cloned() else {
+        return (StatusCode::NOT_FOUND, format!("No position in {}", ticker)).into_response();
+    };
+
+    // Prices were converted to GBP, the chart is in the instrument currency
+    let rate = first.gbp_rate.unwrap_or(1.0);
+    let quantity: f64 = holdings.iter().map(|p| p.quantity).sum();
+    let average_price = if quantity > 0.0 {
+        holdings
+            .iter()
+            .map(|p| p.quantity * p.average_price)
+            .sum::<f64>()
+            / quantity
+            / rate
+    } else {
+        0.0
+    };
+
+    // Buys are only known for live accounts, the chart is still useful
+    // without them
+    let mut buys: Vec<Order> = Vec::new();
+    if config.mode == Mode::Live {
+        for account in config.selected_accounts() {
+            if !holdings.iter().any(|p| p.account == account.name) {
+                continue;
+            }
+            let orders = match state.orders.orders(&config.for_account(&account)).await {
+                Ok(orders) => orders,
+                Err(e) => {
+                    eprintln!("Error loading orders of {}: {}", account.name, e);
+                    Vec::new()
+                }
+            };
+            let oldest = orders.iter().filter_map(|o| o.date()).min();
+            let imported = imported_history_before(&state.exports, &account, oldest)
+                .into_iter()
+                .filter_map(|event| match event {
+                    HistoryEvent::Trade(trade) => Some(Order::from(trade)),
+                    _ => None,
+                });
+            buys.extend(
+                orders
+                    .into_iter()
+                    .chain(imported)
+                    .filter(|o| o.ticker == ticker),
+            );
+        }
+        buys.retain(|o| o.side == OrderSide::Buy && o.is_filled());
+    }
+
+    let history = PriceHistory::load(PRICE_HISTORY_FILE);
+    let bars = history.bars(&first.yf_ticker);
+    let today = Utc::now().date_naive();
+    let chart_data = serde_json::json!({
+        "dates": bars.iter().map(|b| b.date.to_string()).collect::<Vec<_>>(),
+        "closes": bars.iter().map(|b| b.close).collect::<Vec<_>>(),
+        "averagePrice": average_price,
+        "buys": buys
+            .iter()
+            .filter_map(|o| {
+                // Fills before a split are shown in the current shares, like
+                // the prices
+                let date = o.date()?.date_naive();
+                let factor = per_share_factor(&first.corporate_actions, date, today);
+                Some(serde_json::json!({
+                    "date": date.to_string(),
+                    "price": o.fill_price * factor,
+                    "quantity": o.quantity / factor,
+                }))
+            })
+            .collect::<Vec<_>>(),
+    });
+
+    let template = PositionTemplate {
+        ticker,
+        yf_ticker: first.yf_ticker.clone(),
+        quantity: format!("{}", quantity),
+        average_price: format!("{:.2}", average_price),
+        current_price: format!("{:.2}", first.current_price / rate),
+        currency: first.currency.clone(),
+        chart_data: chart_data.to_string(),
+        corporate_actions: first.corporate_actions.clone(),
+        has_history: !bars.is_empty(),
+        holdings,
+        settings: config,
+    };
+
+    match template.render() {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Template rendering error: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+// Handler for the data health page
+pub async fn show_data_health(State(state): State<AppState>) -> impl IntoResponse {
+    if !state.config_success.load(Ordering::SeqCst) {
+        return axum::response::Redirect::to("/settings").into_response();
+    }
+    let config = state.config.lock().await.clone();
+    let positions = selected_portfolio(&state, &config).await.positions;
+
+    let mut rows: Vec<DataHealthRow> = positions
+        .iter()
+        .flat_map(|position| {
+            position.data_issues.iter().map(|issue| DataHealthRow {
+                account: position.account.clone(),
+                ticker: position.ticker.clone(),
+                yf_ticker: position.yf_ticker.clone(),
+                kind: issue.kind().to_string(),
+                details: issue.details(),
+                suggestion: issue.suggestion(position),
+                is_error: issue.is_error(),
+            })
+        })
+        .collect();
+    // Errors first, then by ticker
+    rows.sort_by(|a, b| {
+        b.is_error
+            .cmp(&a.is_error)
+            .then_with(|| a.ticker.cmp(&b.ticker))
+    });
+
+    let template = DataHealthTemplate {
+        positions_checked: positions.len(),
+        error_count: rows.iter().filter(|r| r.is_error).count(),
+        rows,
+        settings: config,
+    };
+
+    match template.render() {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Template rendering error: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+// Handler for the payout page
+pub async fn show_payouts(State(state): State<AppState>) -> impl IntoResponse {
+    if !state.config_success.load(Ordering::SeqCst) {
+        return axum::response::Redirect::to("/settings").into_response();
+    }
+    let config = state.config.lock().await.clone();
+
+    if config.mode == Mode::Demo {
+        println!("Demo mode: Payouts are not available");
+        return (
+            StatusCode::FORBIDDEN,
+            "Payouts are not available in Demo mode".to_string(),
+        )
+            .into_response();
+    } else {
+        println!("Live mode: Fetching payouts");
+    }
+
+    // Amounts are in the currency of their account
+    let currencies: HashMap<String, String> = state
+        .portfolio
+        .lock()
+        .await
+        .accounts
+        .iter()
+        .map(|a| (a.name.clone(), a.currency.clone()))
+        .collect();
+
+    let mut records = Vec::new();
+    let mut accounts = Vec::new();
+    for account in &config.selected_accounts() {
+        let sign = currency_sign(currencies.get(&account.name).map_or("", |c| c.as_str()));
+        let account_records: Vec<DividendRecord> =
+            match get_latest_dividend_records(&config, account, &state.exports).await {
+                Ok(records) => records
+                    .into_iter()
+                    .map(|r| DividendRecord {
+                        account: account.name.clone(),
+                        sign: sign.clone(),
+                        ..r
+                    })
+                    .collect(),
+                Err(e) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Error loading dividend records of {}: {}", account.name, e),
+                    )
+                        .into_response()
+                }
+            };
+        let refreshing = state.exports.is_refreshing(&account.name).await;
+        accounts.push(AccountPayoutSummary::new(
+            account,
+            sign,
+            &account_records,
+            refreshing,
+        ));
+        records.extend(account_records);
+    }
+    records.sort_by(|a, b| b.date.cmp(&a.date));
+
+    let template = PayoutTemplate {
+        records,
+        accounts,
+        settings: config,
+    };
+
+    match template.render() {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Template rendering error: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+// Handler for the export status page
+pub async fn show_exports(State(state): State<AppState>) -> impl IntoResponse {
+    let config = state.config.lock().await.clone();
+    let mut jobs = state.exports.jobs().await;
+    jobs.sort_by(|a, b| b.batch.cmp(&a.batch).then(a.time_from.cmp(&b.time_from)));
+
+    let template = ExportsTemplate {
+        refreshing: jobs.iter().any(|j| j.status.is_active()),
+        jobs,
+        settings: config,
+    };
+
+    match template.render() {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Template rendering error: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+// Handler for uploading export CSVs saved from Trading212 (POST)
+pub async fn upload_exports(
+    State(state): State<AppState>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    let error = |status: StatusCode, message: String| {
+        (
+            status,
+            serde_json::json!({
+                "status": "error",
+                "message": message
+            })
+            .to_string(),
+        )
+            .into_response()
+    };
+
+    let mut account = None;
+    let mut uploads = Vec::new();
+    let mut skipped = Vec::new();
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => return error(StatusCode::BAD_REQUEST, format!("Invalid upload: {}", e)),
+        };
+        if field.name() == Some("account") {
+            match field.text().await {
+                Ok(name) => account = Some(name),
+                Err(e) => return error(StatusCode::BAD_REQUEST, format!("Invalid upload: {}", e)),
+            }
+            continue;
+        }
+        let name = field.file_name().unwrap_or("upload.csv").to_string();
+        let content = match field.text().await {
+            Ok(content) => content,
+            Err(e) => return error(StatusCode::BAD_REQUEST, format!("{}: {}", name, e)),
+        };
+        if content.trim().is_empty() {
+            continue;
+        }
+        // Nothing is imported unless every file is a valid export
+        match validate_upload(&content) {
+            Ok(check) => {
+                skipped.extend(check.skipped.iter().map(|e| format!("{}: {}", name, e)));
+                uploads.push((content, check.from, check.to));
+            }
+            Err(e) => return error(StatusCode::BAD_REQUEST, format!("{}: {}", name, e)),
+        }
+    }
+    if uploads.is_empty() {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "No files were uploaded".to_string(),
+        );
+    }
+
+    // Without a choice the files belong to the only account
+    let accounts = state.config.lock().await.live_accounts();
+    let account = match account {
+        Some(name) => accounts.into_iter().find(|a| a.name == name),
+        None if accounts.len() == 1 => accounts.into_iter().next(),
+        None => None,
+    };
+    let Some(account) = account else {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "Select the account the exports belong to".to_string(),
+        );
+    };
+
+    match state.exports.import(&account.name, uploads).await {
+        Ok(summary) => {
+            let mut message = format!(
+                "Imported {} file(s): {} new row(s), {} row(s) in total",
+                summary.files, summary.added, summary.total
+            );
+            if !skipped.is_empty() {
+                message.push_str(&format!(
+                    ". Skipped {} unreadable row(s): {}",
+                    skipped.len(),
+                    skipped.join("; ")
+                ));
+            }
+            (
+                StatusCode::OK,
+                serde_json::json!({
+                    "status": "success",
+                    "message": message
+                })
+                .to_string(),
+            )
+                .into_response()
+        }
+        Err(e) => error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to import exports: {}", e),
+        ),
+    }
+}
+
+// Query parameters for the orders page
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OrdersQuery {
+    ticker: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+// Handler for the orders page
+pub async fn show_orders(
+    State(state): State<AppState>,
+    Query(query): Query<OrdersQuery>,
+) -> impl IntoResponse {
+    if !state.config_success.load(Ordering::SeqCst) {
+        return axum::response::Redirect::to("/settings").into_response();
+    }
+    let config = state.config.lock().await.clone();
+
+    if config.mode == Mode::Demo {
+        println!("Demo mode: Orders are not available");
+        return (
+            StatusCode::FORBIDDEN,
+            "Orders are not available in Demo mode".to_string(),
+        )
+            .into_response();
+    }
+
+    let non_empty = |v: &Option<String>| {
+        v.as_ref()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    let filter = OrderFilter {
+        ticker: non_empty(&query.ticker),
+        from: non_empty(&query.from).and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()),
+        to: non_empty(&query.to).and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()),
+    };
+
+    let mut orders = Vec::new();
+    for account in config.selected_accounts() {
+        let account_orders = match state.orders.orders(&config.for_account(&account)).await {
+            Ok(account_orders) => account_orders,
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error loading order history of {}: {}", account.name, e),
+                )
+                    .into_response()
+            }
+        };
+        let oldest = account_orders.iter().filter_map(|o| o.date()).min();
+        orders.extend(account_orders);
+        orders.extend(
+            imported_history_before(&state.exports, &account, oldest)
+                .into_iter()
+                .filter_map(|event| match event {
+                    HistoryEvent::Trade(trade) => Some(Order::from(trade)),
+                    _ => None,
+                }),
+        );
+    }
+    orders.sort_by_key(|o| std::cmp::Reverse(o.date()));
+    let orders = filter.apply(orders);
+
+    let total_fees: f64 = orders.iter().map(|o| o.fees).sum();
+    let template = OrdersTemplate {
+        ticker_summary: summarize_by_ticker(&orders),
+        orders,
+        filter_ticker: filter.ticker.unwrap_or_default(),
+        filter_from: filter.from.map(|d| d.to_string()).unwrap_or_default(),
+        filter_to: filter.to.map(|d| d.to_string()).unwrap_or_default(),
+        total_fees: format!("{:.2}", total_fees),
+        settings: config,
+    };
+
+    match template.render() {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Template rendering error: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+// Handler for the cash flow page
+pub async fn show_cashflow(State(state): State<AppState>) -> impl IntoResponse {
+    if !state.config_success.load(Ordering::SeqCst) {
+        return axum::response::Redirect::to("/settings").into_response();
+    }
+    let config = state.config.lock().await.clone();
+
+    if config.mode == Mode::Demo {
+        println!("Demo mode: Cash flow is not available");
+        return (
+            StatusCode::FORBIDDEN,
+            "Cash flow is not available in Demo mode".to_string(),
+        )
+            .into_response();
+    }
+
+    let ledger = match selected_cash_ledger(&state, &config).await {
+        Ok(ledger) => ledger,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+
+    let portfolio_value = selected_portfolio(&state, &config).await.total_value;
+    let net_contributions = ledger.net_contributions();
+    // Whatever the account is worth above the money put in is market growth
+    let market_growth = portfolio_value - net_contributions;
+    let market_growth_percent = if net_contributions > 0.0 {
+        market_growth / net_contributions * 100.0
+    } else {
+        0.0
+    };
+
+    let mut monthly = ledger.monthly_summary();
+    monthly.reverse();
+
+    let template = CashflowTemplate {
+        monthly,
+        total_deposits: format!("{:.2}", ledger.total_deposits()),
+        total_withdrawals: format!("{:.2}", ledger.total_withdrawals()),
+        total_fees: format!("{:.2}", ledger.total_fees()),
+        total_interest: format!("{:.2}", ledger.total_interest()),
+        show_interest: ledger.has_interest(),
+        net_contributions: format!("{:.2}", net_contributions),
+        portfolio_value: format!("{:.2}", portfolio_value),
+        market_growth: format!("{:.2}", market_growth),
+        market_growth_percent: format!("{:.2}", market_growth_percent),
+        settings: config,
+    };
+
+    match template.render() {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Template rendering error: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+/// Cash transactions of the selected accounts in GBP, completed with the
+/// imported exports for the time before the API history starts
+async fn selected_cash_ledger(state: &AppState, config: &Config) -> Result<CashLedger, String> {
+    // Amounts are in the account currency, bring them to GBP at the rate
+    // the cash of the account was converted at
+    let rates: HashMap<String, f64> = state
+        .portfolio
+        .lock()
+        .await
+        .accounts
+        .iter()
+        .filter_map(|a| Some((a.name.clone(), a.gbp_rate?)))
+        .collect();
+    let rate_of = |account: &Account| rates.get(&account.name).copied().unwrap_or(1.0);
+
+    let mut transactions = Vec::new();
+    for account in &config.selected_accounts() {
+        let rate = rate_of(account);
+        let ledger = match state.cash.ledger(&config.for_account(account)).await {
+            Ok(ledger) => ledger,
+            Err(e) => {
+                return Err(format!(
+                    "Error loading cash transactions of {}: {}",
+                    account.name, e
+                ))
+            }
+        };
+        let oldest = ledger.transactions.iter().map(|t| t.date_time).min();
+        transactions.extend(ledger.transactions.into_iter().map(|t| t.converted(rate)));
+        // The API does not report interest, so that is taken from the
+        // exports for the whole time
+        transactions.extend(
+            imported_history_before(&state.exports, account, None)
+                .into_iter()
+                .filter_map(|event| match event {
+                    HistoryEvent::Cash(transaction)
+                        if transaction.kind == TransactionKind::Interest
+                            || oldest.is_none_or(|oldest| transaction.date_time < oldest) =>
+                    {
+                        Some(transaction.converted(rate))
+                    }
+                    _ => None,
+                }),
+        );
+    }
+    Ok(CashLedger::new(transactions))
+}
+
+// Handler for the benchmarks page
+pub async fn show_benchmarks(State(state): State<AppState>) -> impl IntoResponse {
+    if !state.config_success.load(Ordering::SeqCst) {
+        return axum::response::Redirect::to("/settings").into_response();
+    }
+    let config = state.config.lock().await.clone();
+
+    if config.mode == Mode::Demo {
+        println!("Demo mode: Benchmarks are not available");
+        return (
+            StatusCode::FORBIDDEN,
+            "Benchmarks are not available in Demo mode".to_string(),
+        )
+            .into_response();
+    }
+
+    let ledger = match selected_cash_ledger(&state, &config).await {
+        Ok(ledger) => ledger,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+    let contributions = ledger.daily_contributions();
+    let portfolio = selected_portfolio(&state, &config).await;
+    let today = Utc::now().date_naive();
+
+    // The prices are fetched with the portfolio updates, the page only
+    // reads them
+    let history = PriceHistory::load(PRICE_HISTORY_FILE);
+    let quotes = MarketDataCache::load(MARKET_DATA_CACHE_FILE);
+
+    let accounts: Vec<String> = portfolio.accounts.iter().map(|a| a.name.clone()).collect();
+    let values = ValueHistory::load(VALUE_HISTORY_FILE);
+    let portfolio_returns = benchmark::period_returns(
+        |date| values.value_on(&accounts, date),
+        portfolio.total_value,
+        &contributions,
+        today,
+    );
+
+    let mut benchmarks = Vec::new();
+    let mut simulated = Vec::new();
+    for symbol in &config.benchmarks {
+        // Contributions are converted at the rate of their day, so the
+        // comparison includes the currency moves of the benchmark
+        let currency = quotes
+            .entries
+            .get(symbol)
+            .and_then(|entry| entry.data.quote.currency.clone());
+        let rate = currency
+            .as_deref()
+            .and_then(|currency| benchmark::PoundRate::for_currency(currency, &history));
+        let points = history
+            .series
+            .get(symbol)
+            .zip(rate)
+            .map(|(series, rate)| benchmark::simulate_contributions(series, &contributions, rate))
+            .unwrap_or_default();
+        let Some(last) = points.last().copied() else {
+            let error = if rate.is_none() {
+                "The exchange rates of the benchmark currency are not known yet, they are fetched with the next portfolio update"
+            } else {
+                "No prices since the first contribution yet, they are fetched with the next portfolio update"
+            };
+            benchmarks.push(BenchmarkRow {
+                name: symbol.clone(),
+                value: "-".to_string(),
+                returns: Vec::new(),
+                error: Some(error.to_string()),
             });
-        }
-    });
+            continue;
+        };
+        let returns = benchmark::period_returns(
+            |date| benchmark::value_on(&points, date),
+            last.value,
+            &contributions,
+            today,
+        );
+        benchmarks.push(BenchmarkRow {
+            name: symbol.clone(),
+            value: format!("£{:.2}", last.value),
+            returns: returns
+                .iter()
+                .zip(&portfolio_returns)
+                .map(|(benchmark, portfolio)| ReturnCell {
+                    percent: format_return(*benchmark),
+                    difference: match (portfolio, benchmark) {
+                        (Some(p), Some(b)) => format!("{:+.2} pp", p - b),
+                        _ => String::new(),
+                    },
+                })
+                .collect(),
+            error: None,
+        });
+        simulated.push((symbol.clone(), points));
+    }
 
-    upcoming_payments.sort_by(|a, b| {
-        NaiveDate::parse_from_str(&a.payment_date, "%Y-%m-%d")
-            .unwrap_or(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap())
-            .cmp(
-                &NaiveDate::parse_from_str(&b.payment_date, "%Y-%m-%d")
-                    .unwrap_or(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap()),
-            )
+    // Every trading day of the benchmarks, plus the days the portfolio value
+    // was stored and today
+    let portfolio_values = values.values(&accounts);
+    let first_day = contributions.first().map(|c| c.date);
+    let mut dates: BTreeSet<NaiveDate> = simulated
+        .iter()
+        .flat_map(|(_, points)| points.iter().map(|p| p.date))
+        .chain(portfolio_values.iter().map(|p| p.date))
+        .filter(|date| first_day.is_some_and(|first| *date >= first))
+        .collect();
+    if first_day.is_some() {
+        dates.insert(today);
+    }
+    let mut invested = 0.0;
+    let mut pending = contributions.iter().peekable();
+    let cumulative: Vec<f64> = dates
+        .iter()
+        .map(|date| {
+            while let Some(c) = pending.next_if(|c| c.date <= *date) {
+                invested += c.amount;
+            }
+            invested
+        })
+        .collect();
+    let recorded: HashMap<NaiveDate, f64> =
+        portfolio_values.iter().map(|p| (p.date, p.value)).collect();
+    let portfolio_series: Vec<Option<f64>> = dates
+        .iter()
+        .map(|date| {
+            if *date == today {
+                Some(portfolio.total_value)
+            } else {
+                recorded.get(date).copied()
+            }
+        })
+        .collect();
+    let chart_data = serde_json::json!({
+        "dates": dates.iter().map(|d| d.to_string()).collect::<Vec<_>>(),
+        "contributions": cumulative,
+        "portfolio": portfolio_series,
+        "benchmarks": simulated
+            .iter()
+            .map(|(symbol, points)| serde_json::json!({
+                "symbol": symbol,
+                "values": dates
+                    .iter()
+                    .map(|date| benchmark::value_on(points, *date))
+                    .collect::<Vec<_>>(),
+            }))
+            .collect::<Vec<_>>(),
     });
 
-    let template = DividendsTemplate {
-        dividends,
-        div_per_year: format!("{:.2}", div_per_year),
-        div_yield: format!("{:.2}", div_yield),
-        yield_on_cost: format!("{:.2}", yield_on_cost),
-        upcoming_payments,
-        settings: config.clone(),
+    let template = BenchmarksTemplate {
+        periods: ReturnPeriod::ALL.iter().map(|p| p.label()).collect(),
+        portfolio: BenchmarkRow {
+            name: "Portfolio".to_string(),
+            value: format!("£{:.2}", portfolio.total_value),
+            returns: portfolio_returns
+                .iter()
+                .map(|r| ReturnCell {
+                    percent: format_return(*r),
+                    difference: String::new(),
+                })
+                .collect(),
+            error: None,
+        },
+        benchmarks,
+        net_contributions: format!("{:.2}", ledger.net_contributions()),
+        first_contribution: first_day.map_or_else(|| "-".to_string(), |d| d.to_string()),
+        chart_data: chart_data.to_string(),
+        settings: config,
     };
 
     match template.render() {
@@ -294,26 +1484,71 @@ pub async fn show_dividends(State(state): State<AppState>) -> impl IntoResponse
     }
 }
 
-// Handler for the dividends page
-pub async fn show_portfolio(State(state): State<AppState>) -> impl IntoResponse {
+// Handler for the allocation page
+pub async fn show_allocation(State(state): State<AppState>) -> impl IntoResponse {
     if !state.config_success.load(Ordering::SeqCst) {
         return axum::response::Redirect::to("/settings").into_response();
     }
-    let portfolio = state.portfolio.lock().await;
-    let config = state.config.lock().await;
-    let positions = &portfolio.positions;
-    let total_pl: f64 = portfolio.positions.iter().map(|p| p.ppl).sum();
+    let config = state.config.lock().await.clone();
+    let portfolio = selected_portfolio(&state, &config).await;
+    let limits = &config.concentration;
 
-    let template = PortfolioTemplate {
-        positions: positions.to_vec(),
-        total_invested: format!("{:.2}", &portfolio.total_cost),
-        total_current_value: format!("{:.2}", &portfolio.total_value),
-        total_pl: format!("{:.2}", total_pl),
-        last_updated: portfolio
-            .last_updated
-            .format("%Y-%m-%d %H:%M:%S")
-            .to_string(),
-        settings: config.clone(),
+    let allocations: Vec<Allocation> = AllocationDimension::ALL
+        .iter()
+        .map(|dimension| Allocation::new(&portfolio.positions, *dimension, limits))
+        .collect();
+
+    let mut concentrations: Vec<String> =
+        allocation::position_weights(&portfolio.positions, limits)
+            .iter()
+            .filter(|slice| slice.over_limit)
+            .map(|slice| {
+                format!(
+                    "Position {} is {:.1}% of the holdings, above the limit of {}%",
+                    slice.name, slice.weight, limits.position
+                )
+            })
+            .collect();
+    for allocation in &allocations {
+        let Some(limit) = allocation.limit else {
+            continue;
+        };
+        concentrations.extend(
+            allocation
+                .slices
+                .iter()
+                .filter(|s| s.over_limit)
+                .map(|slice| {
+                    format!(
+                        "{} {} is {:.1}% of the holdings, above the limit of {}%",
+                        allocation.dimension.label(),
+                        slice.name,
+                        slice.weight,
+                        limit
+                    )
+                }),
+        );
+    }
+
+    let chart_data = serde_json::json!(allocations
+        .iter()
+        .map(|allocation| serde_json::json!({
+            "labels": allocation.slices.iter().map(|s| &s.name).collect::<Vec<_>>(),
+            "values": allocation.slices.iter().map(|s| s.value).collect::<Vec<_>>(),
+        }))
+        .collect::<Vec<_>>());
+    let annual_income: f64 = allocations
+        .first()
+        .map(|allocation| allocation.slices.iter().map(|s| s.income).sum())
+        .unwrap_or(0.0);
+
+    let template = AllocationTemplate {
+        allocations,
+        concentrations,
+        holdings_value: format!("{:.2}", portfolio.holdings_value),
+        annual_income: format!("{:.2}", annual_income),
+        chart_data: chart_data.to_string(),
+        settings: config,
     };
 
     match template.render() {
@@ -326,84 +1561,53 @@ pub async fn show_portfolio(State(state): State<AppState>) -> impl IntoResponse
     }
 }
 
-// Handler for the payout page
-pub async fn show_payouts(State(state): State<AppState>) -> impl IntoResponse {
+// Handler for the pies page
+pub async fn show_pies(State(state): State<AppState>) -> impl IntoResponse {
     if !state.config_success.load(Ordering::SeqCst) {
         return axum::response::Redirect::to("/settings").into_response();
     }
-    let config = state.config.lock().await;
+    let config = state.config.lock().await.clone();
 
     if config.mode == Mode::Demo {
-        println!("Demo mode: Payouts are not available");
+        println!("Demo mode: Pies are not available");
         return (
             StatusCode::FORBIDDEN,
-            "Payouts are not available in Demo mode".to_string(),
+            "Pies are not available in Demo mode".to_string(),
         )
             .into_response();
-    } else {
-        println!("Live mode: Fetching payouts");
     }
 
-    // Get the latest export file
-    let records = match get_latest_dividend_records(&config).await {
-        Ok(records) => records,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Error loading dividend records: {}", e),
-            )
-                .into_response()
-        }
-    };
-
-    // Calculate totals and summaries
-    let total_dividends: f64 = records
-        .iter()
-        .filter_map(|r| r.total.parse::<f64>().ok())
-        .sum();
-    let total_wht: f64 = records
-        .iter()
-        .filter_map(|r| r.withholding_tax.parse::<f64>().ok())
-        .sum();
-
-    // Group by ticker
-    let mut ticker_map: HashMap<String, (f64, f64)> = HashMap::new();
-    for record in &records {
-        if let (Ok(total), Ok(wht)) = (
-            record.total.parse::<f64>(),
-            record.withholding_tax.parse::<f64>(),
-        ) {
-            let entry = ticker_map
-                .entry(record.ticker.clone())
-                .or_insert((0.0, 0.0));
-            entry.0 += total;
-            entry.1 += wht;
+    let positions = selected_portfolio(&state, &config).await.positions;
+    let mut pies = Vec::new();
+    for account in config.selected_accounts() {
+        let account_positions: Vec<Position> = positions
+            .iter()
+            .filter(|p| p.account == account.name)
+            .cloned()
+            .collect();
+        match state
+            .pies
+            .pies(&config.for_account(&account), &account_positions)
+            .await
+        {
+            Ok(account_pies) => pies.extend(account_pies),
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error loading pies of {}: {}", account.name, e),
+                )
+                    .into_response()
+            }
         }
     }
 
-    let mut ticker_summary: Vec<TickerSummary> = ticker_map
-        .into_iter()
-        .map(|(ticker, (total, wht))| TickerSummary {
-            ticker,
-            total: format!("{:.2}", total),
-            wht: format!("{:.2}", wht),
-        })
-        .collect();
-    ticker_summary.sort_by(|a, b| {
-        b.total
-            .partial_cmp(&a.total)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
-
-    let monthly_div_summary = calculate_monthly_dividends(&records);
-
-    let template = PayoutTemplate {
-        records,
-        total_dividends: format!("{:.2}", total_dividends),
-        total_wht: format!("{:.2}", total_wht),
-        ticker_summary,
-        monthly_div_summary,
-        settings: config.clone(),
+    let template = PiesTemplate {
+        total_invested: format!("{:.2}", pies.iter().map(|p| p.invested).sum::<f64>()),
+        total_value: format!("{:.2}", pies.iter().map(|p| p.value).sum::<f64>()),
+        total_result: format!("{:.2}", pies.iter().map(|p| p.result).sum::<f64>()),
+        total_cash: format!("{:.2}", pies.iter().map(|p| p.cash).sum::<f64>()),
+        pies,
+        settings: config,
     };
 
     match template.render() {
@@ -419,13 +1623,7 @@ pub async fn show_payouts(State(state): State<AppState>) -> impl IntoResponse {
 // Handler for the settings page (GET)
 pub async fn show_settings(State(state): State<AppState>) -> impl IntoResponse {
     let config = state.config.lock().await;
-    let error_message = if config.mode == Mode::Live
-        && config
-            .api_key
-            .as_ref()
-            .map(|k| k.trim().is_empty())
-            .unwrap_or(true)
-    {
+    let error_message = if config.mode == Mode::Live && config.live_accounts().is_empty() {
         Some("Trading212 API key is missing or invalid. Please enter a valid API key to use Live mode.".to_string())
     } else {
         None
@@ -449,9 +1647,30 @@ pub async fn show_settings(State(state): State<AppState>) -> impl IntoResponse {
 #[derive(Debug, Clone, Deserialize)]
 pub struct UpdateSettingsForm {
     api_key: Option<String>,
+    api_environment: Option<String>,
+    api_base_url: Option<String>,
+    market_data_source: Option<String>,
+    market_data_location: Option<String>,
     currency: String,
     mode: String,
     portfolio_update_interval_secs: u64,
+    refresh_only_when_market_open: Option<String>,
+    instrument_metadata_ttl_hours: Option<u64>,
+    market_data_ttl_hours: Option<u64>,
+    /// Rows of the accounts table, serialized by the settings page
+    accounts_json: Option<String>,
+    export_history_years: Option<u32>,
+    export_chunk_days: Option<u32>,
+    export_include_dividends: Option<String>,
+    export_include_interest: Option<String>,
+    export_include_orders: Option<String>,
+    export_include_transactions: Option<String>,
+    /// Comma separated benchmark symbols
+    benchmarks: Option<String>,
+    concentration_position: Option<f64>,
+    concentration_sector: Option<f64>,
+    concentration_industry: Option<f64>,
+    concentration_country: Option<f64>,
 }
 
 pub async fn save_settings(
@@ -475,7 +1694,85 @@ pub async fn save_settings(
         }
     };
 
-    config_data.api_key = form.api_key.clone();
+    if let Some(environment) = &form.api_environment {
+        config_data.api_environment =
+            match ApiEnvironment::from_form(environment, form.api_base_url.as_deref()) {
+                Ok(environment) => environment,
+                Err(e) => {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        serde_json::json!({
+                            "status": "error",
+                            "message": e.to_string()
+                        })
+                        .to_string(),
+                    )
+                        .into_response()
+                }
+            };
+    }
+
+    if let Some(source) = &form.market_data_source {
+        config_data.market_data =
+            match MarketDataSource::from_form(source, form.market_data_location.as_deref()) {
+                Ok(source) => source,
+                Err(e) => {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        serde_json::json!({
+                            "status": "error",
+                            "message": e.to_string()
+                        })
+                        .to_string(),
+                    )
+                        .into_response()
+                }
+            };
+    }
+
+    let accounts: Vec<Account> = match form
+        .accounts_json
+        .as_deref()
+        .filter(|json| !json.trim().is_empty())
+        .map(serde_json::from_str)
+        .transpose()
+    {
+        Ok(accounts) => accounts.unwrap_or_default(),
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                serde_json::json!({
+                    "status": "error",
+                    "message": format!("Invalid accounts: {}", e)
+                })
+                .to_string(),
+            )
+                .into_response()
+        }
+    };
+    if let Err(e) = Config::validate_accounts(&accounts) {
+        return (
+            StatusCode::BAD_REQUEST,
+            serde_json::json!({
+                "status": "error",
+                "message": e.to_string()
+            })
+            .to_string(),
+        )
+            .into_response();
+    }
+
+    // The first account doubles as the single API key of older configs
+    config_data.api_key = match accounts.first() {
+        Some(account) => Some(account.api_key.clone()),
+        None => form.api_key.clone(),
+    };
+    if let Some(selected) = &config_data.selected_account {
+        if !accounts.iter().any(|a| &a.name == selected) {
+            config_data.selected_account = None;
+        }
+    }
+    config_data.accounts = accounts;
     config_data.currency = form.currency.parse().unwrap_or_default();
     config_data.mode = match form.mode.as_str() {
         "Live" => Mode::Live,
@@ -484,6 +1781,88 @@ pub async fn save_settings(
     };
     config_data.portfolio_update_interval =
         Duration::from_secs(form.portfolio_update_interval_secs);
+    // Unchecked checkboxes are not submitted at all
+    config_data.refresh_only_when_market_open = form.refresh_only_when_market_open.is_some();
+    let ttls = [
+        (
+            form.instrument_metadata_ttl_hours,
+            &mut config_data.instrument_metadata_ttl,
+        ),
+        (form.market_data_ttl_hours, &mut config_data.market_data_ttl),
+    ];
+    for (hours, ttl) in ttls {
+        let Some(hours) = hours else {
+            continue;
+        };
+        match cache_ttl_from_hours(hours) {
+            Ok(duration) => *ttl = duration,
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    serde_json::json!({
+                        "status": "error",
+                        "message": e.to_string()
+                    })
+                    .to_string(),
+                )
+                    .into_response()
+            }
+        }
+    }
+
+    if let Some(history_years) = form.export_history_years {
+        let export = ExportSettings {
+            history_years,
+            chunk_days: form.export_chunk_days.unwrap_or(MAX_EXPORT_CHUNK_DAYS),
+            include_dividends: form.export_include_dividends.is_some(),
+            include_interest: form.export_include_interest.is_some(),
+            include_orders: form.export_include_orders.is_some(),
+            include_transactions: form.export_include_transactions.is_some(),
+        };
+        if let Err(e) = export.validate() {
+            return (
+                StatusCode::BAD_REQUEST,
+                serde_json::json!({
+                    "status": "error",
+                    "message": e.to_string()
+                })
+                .to_string(),
+            )
+                .into_response();
+        }
+        config_data.export = export;
+    }
+
+    if let Some(benchmarks) = &form.benchmarks {
+        let mut seen = HashSet::new();
+        config_data.benchmarks = benchmarks
+            .split(',')
+            .map(|symbol| symbol.trim().to_uppercase())
+            .filter(|symbol| !symbol.is_empty() && seen.insert(symbol.clone()))
+            .collect();
+    }
+
+    if let Some(position) = form.concentration_position {
+        let defaults = ConcentrationLimits::default();
+        let concentration = ConcentrationLimits {
+            position,
+            sector: form.concentration_sector.unwrap_or(defaults.sector),
+            industry: form.concentration_industry.unwrap_or(defaults.industry),
+            country: form.concentration_country.unwrap_or(defaults.country),
+        };
+        if let Err(e) = concentration.validate() {
+            return (
+                StatusCode::BAD_REQUEST,
+                serde_json::json!({
+                    "status": "error",
+                    "message": e.to_string()
+                })
+                .to_string(),
+            )
+                .into_response();
+        }
+        config_data.concentration = concentration;
+    }
 
     match config_data.save_config() {
         Ok(_) => {
@@ -517,6 +1896,95 @@ pub async fn save_settings(
     }
 }
 
+// Form of the account switcher in the navbar
+#[derive(Debug, Clone, Deserialize)]
+pub struct SelectAccountForm {
+    /// Name of the account to show, empty for all accounts
+    account: String,
+}
+
+// Handler for the account switcher (POST)
+pub async fn select_account(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(form): Form<SelectAccountForm>,
+) -> impl IntoResponse {
+    let mut config = state.config.lock().await;
+    let account = form.account.trim();
+    config.selected_account = if account.is_empty() {
+        None
+    } else {
+        Some(account.to_string())
+    };
+    if let Err(e) = config.save_config() {
+        eprintln!("Failed to save selected account: {}", e);
+    }
+
+    // Go back to the page the switch was made on
+    let host = headers.get(header::HOST).and_then(|v| v.to_str().ok());
+    let back = headers
+        .get(header::REFERER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|referer| local_path(referer, host?))
+        .unwrap_or_else(|| "/portfolio".to_string());
+    axum::response::Redirect::to(&back).into_response()
+}
+
+/// Path and query of `referer` when it points at this server, so that the
+/// account switcher never redirects to another site
+fn local_path(referer: &str, host: &str) -> Option<String> {
+    let url = reqwest::Url::parse(referer).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let authority = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str()?, port),
+        None => url.host_str()?.to_string(),
+    };
+    // A path starting with `//` would be taken as another host
+    if !authority.eq_ignore_ascii_case(host) || url.path().starts_with("//") {
+        return None;
+    }
+    Some(match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    })
+}
+
+// Handler to fetch the market data of every symbol again (POST)
+pub async fn refresh_market_data(State(state): State<AppState>) -> impl IntoResponse {
+    if state.config.lock().await.mode == Mode::Demo {
+        return (
+            StatusCode::FORBIDDEN,
+            serde_json::json!({
+                "status": "error",
+                "message": "Demo mode uses the bundled market data"
+            })
+            .to_string(),
+        )
+            .into_response();
+    }
+
+    // The background task owns the cache, it expires the data itself so
+    // that an update in flight cannot overwrite the expiry
+    state.force_market_data.store(true, Ordering::SeqCst);
+
+    // Signal the background task to update immediately
+    if let Err(e) = state.tx.send(()).await {
+        eprintln!("Failed to signal portfolio update: {}", e);
+    }
+
+    (
+        StatusCode::OK,
+        serde_json::json!({
+            "status": "success",
+            "message": "Market data is being refreshed"
+        })
+        .to_string(),
+    )
+        .into_response()
+}
+
 // Handler to reset settings to default (POST)
 pub async fn reset_settings(State(state): State<AppState>, Form(_): Form<()>) -> impl IntoResponse {
     let default_settings = crate::utils::settings::Config::default();
@@ -561,6 +2029,17 @@ pub async fn start_server(
     let portfolio = Arc::new(TokioMutex::new(portfolio));
     let config = Arc::new(TokioMutex::new(config));
     let config_success = Arc::new(AtomicBool::new(config_success));
+    let force_market_data = Arc::new(AtomicBool::new(false));
+
+    // Exports are generated in the background, resuming any left pending
+    let exports = ExportJobManager::load(EXPORT_JOBS_FILE);
+    // Exports stored before they were kept per account were only ever
+    // requested for the first account
+    let first_account = config.lock().await.live_accounts().into_iter().next();
+    if let Some(account) = first_account {
+        exports.adopt_legacy_exports(&account.name).await;
+    }
+    task::spawn(exports.clone().run(config.clone()));
 
     // Create a channel for signaling immediate updates
     let (tx, mut rx) = mpsc::channel(1);
@@ -570,6 +2049,11 @@ pub async fn start_server(
         config: config.clone(),
         tx: tx.clone(),
         config_success: config_success.clone(),
+        exports: exports.clone(),
+        orders: OrderHistoryCache::default(),
+        cash: CashLedgerCache::default(),
+        pies: PieCache::default(),
+        force_market_data: force_market_data.clone(),
     };
 
     let app = Router::new()
@@ -589,6 +2073,43 @@ pub async fn start_server(
             "/payout",
             get(show_payouts as fn(axum::extract::State<AppState>) -> _),
         )
+        .route(
+            "/orders",
+            get(show_orders as fn(axum::extract::State<AppState>, Query<OrdersQuery>) -> _),
+        )
+        .route(
+            "/cashflow",
+            get(show_cashflow as fn(axum::extract::State<AppState>) -> _),
+        )
+        .route(
+            "/benchmarks",
+            get(show_benchmarks as fn(axum::extract::State<AppState>) -> _),
+        )
+        .route(
+            "/allocation",
+            get(show_allocation as fn(axum::extract::State<AppState>) -> _),
+        )
+        .route(
+            "/pies",
+            get(show_pies as fn(axum::extract::State<AppState>) -> _),
+        )
+        .route(
+            "/position/:ticker",
+            get(show_position as fn(axum::extract::State<AppState>, Path<String>) -> _),
+        )
+        .route(
+            "/health/data",
+            get(show_data_health as fn(axum::extract::State<AppState>) -> _),
+        )
+        .route(
+            "/exports",
+            get(show_exports as fn(axum::extract::State<AppState>) -> _),
+        )
+        .route(
+            "/exports/upload",
+            post(upload_exports as fn(axum::extract::State<AppState>, Multipart) -> _)
+                .layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)),
+        )
         .route(
             "/settings",
             get(show_settings as fn(axum::extract::State<AppState>) -> _),
@@ -599,6 +2120,17 @@ pub async fn start_server(
                 save_settings as fn(axum::extract::State<AppState>, Form<UpdateSettingsForm>) -> _,
             ),
         )
+        .route(
+            "/account",
+            post(
+                select_account
+                    as fn(axum::extract::State<AppState>, HeaderMap, Form<SelectAccountForm>) -> _,
+            ),
+        )
+        .route(
+            "/market-data/refresh",
+            post(refresh_market_data as fn(axum::extract::State<AppState>) -> _),
+        )
         .route(
             "/settings/reset",
             post(reset_settings as fn(axum::extract::State<AppState>, Form<()>) -> _),
@@ -609,7 +2141,12 @@ pub async fn start_server(
     let portfolio_for_task = portfolio.clone();
     let config_for_task = config.clone();
     let config_success_for_task = config_success.clone();
+    let exports_for_task = exports.clone();
+    let force_market_data_for_task = force_market_data.clone();
     task::spawn(async move {
+        // Kept across updates, so the instrument list is only downloaded
+        // again once it expires or a new ticker shows up
+        let mut instruments = InstrumentCache::load(INSTRUMENTS_CACHE_FILE);
         loop {
             // Wait for either immediate signal or regular interval
             tokio::select! {
@@ -626,6 +2163,13 @@ pub async fn start_server(
                     // Wait for the full interval, but can be interrupted
                     tokio::select! {
                         _ = sleep(current_config.portfolio_update_interval) => {
+                            let current_config = config_for_task.lock().await.clone();
+                            if current_config.refresh_only_when_market_open
+                                && portfolio_for_task.lock().await.all_markets_closed(Utc::now())
+                            {
+                                println!("All markets are closed, skipping regular portfolio update");
+                                continue;
+                            }
                             println!("Performing regular portfolio update");
                         }
                         _ = rx.recv() => {
@@ -648,26 +2192,44 @@ pub async fn start_server(
 
             // Process the new portfolio data
             let orchestrator = match Orchestrator::new(&current_config).await {
-                Ok(o) => {
+                Ok(o) => o,
+                Err(e) => {
+                    config_success_for_task.store(false, Ordering::SeqCst);
+                    eprintln!("Failed to create orchestrator: {}", e);
+                    continue;
+                }
+            };
+            let instrument_metadata = match instruments
+                .refresh(&current_config, &new_portfolio.tickers(), Utc::now())
+                .await
+            {
+                Ok(metadata) => {
                     config_success_for_task.store(true, Ordering::SeqCst);
-                    o
+                    metadata
                 }
                 Err(e) => {
                     config_success_for_task.store(false, Ordering::SeqCst);
-                    eprintln!("Failed to create orchestrator: {}", e);
+                    eprintln!("Failed to load instrument metadata: {}", e);
                     continue;
                 }
             };
 
+            let force_market_data = force_market_data_for_task.swap(false, Ordering::SeqCst);
             if let Err(e) = new_portfolio
                 .process(
                     &current_config,
                     orchestrator.currency_converter,
-                    orchestrator.instrument_metadata,
+                    instrument_metadata,
+                    orchestrator.market_hours,
+                    force_market_data,
                 )
                 .await
             {
                 eprintln!("Failed to process portfolio: {}", e);
+                // Try the forced refresh again with the next update
+                if force_market_data {
+                    force_market_data_for_task.store(true, Ordering::SeqCst);
+                }
                 continue;
             }
 
@@ -678,6 +2240,19 @@ pub async fn start_server(
                 *shared = new_portfolio;
                 println!("Portfolio update count: {}", shared.update_count);
             }
+
+            // Keep the exports of every account up to date, they are the
+            // only source of interest and of the history the API drops
+            if current_config.mode == Mode::Live {
+                for account in current_config.live_accounts() {
+                    if let Err(e) = exports_for_task
+                        .enqueue_refresh(&current_config, &account)
+                        .await
+                    {
+                        eprintln!("Failed to queue the exports of {}: {}", account.name, e);
+                    }
+                }
+            }
         }
     });
 
@@ -689,3 +2264,25 @@ pub async fn start_server(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_path() {
+        assert_eq!(
+            local_path("http://localhost:3000/orders?ticker=AAPL", "localhost:3000").as_deref(),
+            Some("/orders?ticker=AAPL")
+        );
+        assert_eq!(
+            local_path("https://evil.example/portfolio", "localhost:3000"),
+            None
+        );
+        assert_eq!(
+            local_path("http://localhost:3000//evil.example", "localhost:3000"),
+            None
+        );
+        assert_eq!(local_path("javascript:alert(1)", "localhost:3000"), None);
+    }
+}
@@ -21,12 +21,17 @@ def fetch_info(symbol):
         ticker = yf.Ticker(symbol, session=session)
         info = ticker.info  # May raise HTTPError or others
 
-        # Get last 4 dividends and convert Timestamp keys to string
-        dividends = ticker.dividends.tail(4)
-        dividends_dict = {
+        # Get all dividends and convert Timestamp keys to string
+        dividends = ticker.dividends
+        info["dividend_history"] = {
             date.strftime("%Y-%m-%d"): float(amount) for date, amount in dividends.items()
         }
-        info["last_4_dividends"] = dividends_dict
+        info["last_4_dividends"] = {
+            date.strftime("%Y-%m-%d"): float(amount) for date, amount in dividends.tail(4).items()
+        }
+        info["split_history"] = {
+            date.strftime("%Y-%m-%d"): float(ratio) for date, ratio in ticker.splits.items()
+        }
 
         with lock:
             results[symbol] = info